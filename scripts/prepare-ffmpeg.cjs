@@ -3,27 +3,33 @@ const fs = require('fs');
 const path = require('path');
 
 const ffmpegPath = require('ffmpeg-static');
+const ffprobePath = require('ffprobe-static').path;
 
 if (!ffmpegPath) {
   console.error('Unable to resolve ffmpeg-static binary path. Ensure the dependency is installed.');
   process.exit(1);
 }
 
+if (!ffprobePath) {
+  console.error('Unable to resolve ffprobe-static binary path. Ensure the dependency is installed.');
+  process.exit(1);
+}
+
 const platform = process.platform;
 const arch = process.arch;
 
 const targetMap = new Map([
-  [['win32', 'x64'].toString(), 'ffmpeg-x86_64-pc-windows-msvc.exe'],
-  [['linux', 'x64'].toString(), 'ffmpeg-x86_64-unknown-linux-gnu'],
-  [['linux', 'arm64'].toString(), 'ffmpeg-aarch64-unknown-linux-gnu'],
-  [['darwin', 'x64'].toString(), 'ffmpeg-x86_64-apple-darwin'],
-  [['darwin', 'arm64'].toString(), 'ffmpeg-aarch64-apple-darwin'],
+  [['win32', 'x64'].toString(), 'x86_64-pc-windows-msvc.exe'],
+  [['linux', 'x64'].toString(), 'x86_64-unknown-linux-gnu'],
+  [['linux', 'arm64'].toString(), 'aarch64-unknown-linux-gnu'],
+  [['darwin', 'x64'].toString(), 'x86_64-apple-darwin'],
+  [['darwin', 'arm64'].toString(), 'aarch64-apple-darwin'],
 ]);
 
 const key = [platform, arch].toString();
-const filename = targetMap.get(key);
+const targetSuffix = targetMap.get(key);
 
-if (!filename) {
+if (!targetSuffix) {
   console.error(`Unsupported platform/architecture combination: ${platform} ${arch}`);
   console.error('Update scripts/prepare-ffmpeg.cjs with the appropriate mapping for your target.');
   process.exit(1);
@@ -36,18 +42,24 @@ if (!fs.existsSync(binariesDir)) {
   fs.mkdirSync(binariesDir, { recursive: true });
 }
 
-const destination = path.join(binariesDir, filename);
+function copyBinary(name, sourcePath) {
+  const destination = path.join(binariesDir, `${name}-${targetSuffix}`);
 
-fs.copyFileSync(ffmpegPath, destination);
+  fs.copyFileSync(sourcePath, destination);
 
-try {
-  fs.chmodSync(destination, 0o755);
-} catch (error) {
-  // chmod can fail on Windows; ignore silently there.
-  if (platform !== 'win32') {
-    throw error;
+  try {
+    fs.chmodSync(destination, 0o755);
+  } catch (error) {
+    // chmod can fail on Windows; ignore silently there.
+    if (platform !== 'win32') {
+      throw error;
+    }
   }
+
+  console.log(`Copied ${name} binary to ${path.relative(repoRoot, destination)}`);
 }
 
-console.log(`Copied ffmpeg binary to ${path.relative(repoRoot, destination)}`);
-console.log(`Source version: ${require('ffmpeg-static/package.json').version}`);
+copyBinary('ffmpeg', ffmpegPath);
+copyBinary('ffprobe', ffprobePath);
+
+console.log(`Source versions: ffmpeg-static ${require('ffmpeg-static/package.json').version}, ffprobe-static ${require('ffprobe-static/package.json').version}`);
@@ -0,0 +1,86 @@
+use std::io;
+
+/// Restricts process `pid` to the CPU cores listed in `cores` (zero-based).
+/// Linux pins every thread the process has so far, and threads it starts
+/// later inherit the mask; Windows applies the mask to the whole process.
+/// macOS has no affinity API, so the request is refused there.
+pub fn pin_process(pid: u32, cores: &[usize]) -> io::Result<()> {
+    if cores.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no CPU cores given"));
+    }
+    set_affinity(pid, cores)
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(pid: u32, cores: &[usize]) -> io::Result<()> {
+    // SAFETY: `cpu_set_t` is a plain bit set, so an all-zero value is valid
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &core in cores.iter().filter(|core| **core < libc::CPU_SETSIZE as usize) {
+        // SAFETY: `core` was checked against the size of the set
+        unsafe { libc::CPU_SET(core, &mut set) };
+    }
+
+    let set_thread = |tid: libc::pid_t| {
+        // SAFETY: `set` outlives the call and the size matches its type
+        let result = unsafe {
+            libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    };
+
+    // sched_setaffinity applies to a single thread
+    match std::fs::read_dir(format!("/proc/{}/task", pid)) {
+        Ok(tasks) => {
+            for tid in tasks
+                .flatten()
+                .filter_map(|task| task.file_name().to_str()?.parse::<libc::pid_t>().ok())
+            {
+                set_thread(tid)?;
+            }
+            Ok(())
+        }
+        Err(_) => set_thread(pid as libc::pid_t),
+    }
+}
+
+#[cfg(windows)]
+fn set_affinity(pid: u32, cores: &[usize]) -> io::Result<()> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, SetProcessAffinityMask, PROCESS_QUERY_LIMITED_INFORMATION,
+        PROCESS_SET_INFORMATION,
+    };
+
+    let mask = cores
+        .iter()
+        .filter(|core| **core < usize::BITS as usize)
+        .fold(0usize, |mask, core| mask | 1 << core);
+
+    // SAFETY: the handle is checked before use and closed exactly once
+    unsafe {
+        let handle = OpenProcess(
+            PROCESS_SET_INFORMATION | PROCESS_QUERY_LIMITED_INFORMATION,
+            0,
+            pid,
+        );
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let result = SetProcessAffinityMask(handle, mask);
+        let error = (result == 0).then(io::Error::last_os_error);
+        CloseHandle(handle);
+        error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn set_affinity(_pid: u32, _cores: &[usize]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU affinity is not supported on this platform",
+    ))
+}
@@ -0,0 +1,305 @@
+use std::path::Path;
+
+use tauri::AppHandle;
+
+use crate::ffmpeg::{ffmpeg_command, FfmpegError};
+use crate::types::{
+    CustomPosition, OpacityRange, PositionNudge, WatermarkConfig, WatermarkPosition,
+};
+
+const GRID_SIZE: usize = 64;
+
+/// Luma step between neighbouring cells that counts as an edge.
+const EDGE_THRESHOLD: u8 = 24;
+/// Edge density above which the area under the watermark is considered busy
+/// (text, logos, fine detail) and worth moving away from.
+const BUSY_THRESHOLD: f32 = 0.15;
+/// Edge density and luma standard deviation at which the content under the
+/// watermark counts as fully busy for adaptive opacity.
+const OPACITY_BUSY_EDGES: f32 = 0.3;
+const OPACITY_BUSY_DEVIATION: f32 = 0.25;
+/// Half size of the footprint assumed for the watermark while nudging.
+const NUDGE_HALF_WIDTH: f32 = 0.12;
+const NUDGE_HALF_HEIGHT: f32 = 0.06;
+const NUDGE_STEP: f32 = 0.04;
+const NUDGE_MAX_STEPS: i32 = 4;
+/// Busyness cost per unit of distance moved, so the smallest move wins
+/// between spots that are about as quiet.
+const NUDGE_DISTANCE_PENALTY: f32 = 0.1;
+
+/// A downscaled grayscale copy of a representative frame, used to reason
+/// about the content under the watermark.
+pub struct LumaGrid {
+    pixels: Vec<u8>,
+}
+
+/// A rectangle in normalized (0.0-1.0) frame coordinates.
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+impl LumaGrid {
+    /// Mean luminance of `region`, from 0.0 (black) to 1.0 (white).
+    pub fn mean(&self, region: Region) -> f32 {
+        let values = self.region_values(region);
+        if values.is_empty() {
+            return 0.5;
+        }
+        values.iter().map(|v| *v as f32).sum::<f32>() / values.len() as f32 / 255.0
+    }
+
+    /// Fraction of the cells in `region` that sit on a sharp luma edge.
+    /// Burned-in subtitles and channel logos score high, skies and walls low.
+    pub fn edge_density(&self, region: Region) -> f32 {
+        let (x0, x1, y0, y1) = region_cells(region);
+        let mut edges = 0;
+        let mut cells = 0;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let value = self.pixels[y * GRID_SIZE + x];
+                let right = (x + 1 < GRID_SIZE).then(|| self.pixels[y * GRID_SIZE + x + 1]);
+                let below = (y + 1 < GRID_SIZE).then(|| self.pixels[(y + 1) * GRID_SIZE + x]);
+                if [right, below]
+                    .into_iter()
+                    .flatten()
+                    .any(|neighbour| value.abs_diff(neighbour) > EDGE_THRESHOLD)
+                {
+                    edges += 1;
+                }
+                cells += 1;
+            }
+        }
+        if cells == 0 {
+            return 0.0;
+        }
+        edges as f32 / cells as f32
+    }
+
+    /// Standard deviation of the luminance in `region`, from 0.0 (flat) to
+    /// 0.5 (half black, half white).
+    pub fn deviation(&self, region: Region) -> f32 {
+        let values = self.region_values(region);
+        if values.is_empty() {
+            return 0.0;
+        }
+        let count = values.len() as f32;
+        let mean = values.iter().map(|v| *v as f32).sum::<f32>() / count;
+        let variance = values.iter().map(|v| (*v as f32 - mean).powi(2)).sum::<f32>() / count;
+        variance.sqrt() / 255.0
+    }
+
+    fn region_values(&self, region: Region) -> Vec<u8> {
+        let (x0, x1, y0, y1) = region_cells(region);
+        let mut values = Vec::new();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                values.push(self.pixels[y * GRID_SIZE + x]);
+            }
+        }
+        values
+    }
+}
+
+/// Grid cell ranges `(x0, x1, y0, y1)` covered by `region`, at least one
+/// cell wide and tall.
+fn region_cells(region: Region) -> (usize, usize, usize, usize) {
+    let to_cell = |v: f32| ((v.clamp(0.0, 1.0) * GRID_SIZE as f32) as usize).min(GRID_SIZE);
+    let (x0, x1) = (to_cell(region.x0), to_cell(region.x1).max(to_cell(region.x0) + 1));
+    let (y0, y1) = (to_cell(region.y0), to_cell(region.y1).max(to_cell(region.y0) + 1));
+    (x0.min(GRID_SIZE - 1), x1.min(GRID_SIZE), y0.min(GRID_SIZE - 1), y1.min(GRID_SIZE))
+}
+
+/// Decodes a representative frame (FFmpeg's `thumbnail` pick for videos) as a
+/// 64x64 grayscale grid.
+pub async fn sample_luma_grid(
+    app: &AppHandle,
+    path: &Path,
+    is_video: bool,
+) -> Result<LumaGrid, FfmpegError> {
+    let filter = if is_video {
+        format!("thumbnail,scale={0}:{0},format=gray", GRID_SIZE)
+    } else {
+        format!("scale={0}:{0},format=gray", GRID_SIZE)
+    };
+    decode_luma_grid(app, path, Vec::new(), filter).await
+}
+
+/// Decodes the video frame at `timestamp` seconds as a 64x64 grayscale grid.
+pub async fn sample_luma_grid_at(
+    app: &AppHandle,
+    path: &Path,
+    timestamp: f64,
+) -> Result<LumaGrid, FfmpegError> {
+    decode_luma_grid(
+        app,
+        path,
+        vec!["-ss".to_string(), format!("{:.3}", timestamp)],
+        format!("scale={0}:{0},format=gray", GRID_SIZE),
+    )
+    .await
+}
+
+async fn decode_luma_grid(
+    app: &AppHandle,
+    path: &Path,
+    input_args: Vec<String>,
+    filter: String,
+) -> Result<LumaGrid, FfmpegError> {
+    let mut args = vec!["-hide_banner".to_string()];
+    args.extend(input_args);
+    args.extend([
+        "-i".to_string(),
+        path.to_string_lossy().into_owned(),
+        "-vf".to_string(),
+        filter,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-f".to_string(),
+        "rawvideo".to_string(),
+        "-".to_string(),
+    ]);
+
+    let output = ffmpeg_command(app, "ffmpeg")?
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| FfmpegError::Execution(e.to_string()))?;
+
+    if !output.status.success() || output.stdout.len() != GRID_SIZE * GRID_SIZE {
+        return Err(FfmpegError::Execution(format!(
+            "failed to sample frame from {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(LumaGrid {
+        pixels: output.stdout,
+    })
+}
+
+/// Approximate area the watermark covers: a box around the custom position,
+/// or the third of the frame matching the preset position.
+pub fn watermark_region(config: &WatermarkConfig) -> Region {
+    if config.is_custom_position() {
+        if let Some(custom_pos) = &config.custom_position {
+            return Region {
+                x0: custom_pos.x - 1.0 / 6.0,
+                y0: custom_pos.y - 1.0 / 6.0,
+                x1: custom_pos.x + 1.0 / 6.0,
+                y1: custom_pos.y + 1.0 / 6.0,
+            };
+        }
+    }
+
+    let (column, row) = match config.position {
+        WatermarkPosition::TopLeft => (0.0, 0.0),
+        WatermarkPosition::TopCenter => (1.0, 0.0),
+        WatermarkPosition::TopRight => (2.0, 0.0),
+        WatermarkPosition::CenterLeft => (0.0, 1.0),
+        WatermarkPosition::Center => (1.0, 1.0),
+        WatermarkPosition::CenterRight => (2.0, 1.0),
+        WatermarkPosition::BottomLeft => (0.0, 2.0),
+        WatermarkPosition::BottomCenter => (1.0, 2.0),
+        WatermarkPosition::BottomRight => (2.0, 2.0),
+    };
+
+    Region {
+        x0: column / 3.0,
+        y0: row / 3.0,
+        x1: (column + 1.0) / 3.0,
+        y1: (row + 1.0) / 3.0,
+    }
+}
+
+/// Picks an opacity within `range` from how busy the content under the
+/// watermark is: textured or high-contrast areas get `range.max` so the mark
+/// stays visible, plain ones `range.min` so it stays subtle.
+pub fn adaptive_opacity(grid: &LumaGrid, config: &WatermarkConfig, range: &OpacityRange) -> u8 {
+    let region = watermark_region(config);
+    let busyness = (grid.edge_density(region) / OPACITY_BUSY_EDGES)
+        .max(grid.deviation(region) / OPACITY_BUSY_DEVIATION)
+        .min(1.0);
+    let (min, max) = (f32::from(range.min), f32::from(range.max));
+    (min + (max - min) * busyness).round() as u8
+}
+
+/// Moves the watermark to the quietest spot within a short distance when the
+/// area under it is busy in the sampled frames. The result always uses a
+/// custom position when the watermark was moved.
+pub fn nudge_position(config: &WatermarkConfig, grids: &[LumaGrid]) -> PositionNudge {
+    let (anchor_x, anchor_y) = watermark_anchor(config);
+    let busyness_at = |x: f32, y: f32| {
+        let footprint = Region {
+            x0: x - NUDGE_HALF_WIDTH,
+            y0: y - NUDGE_HALF_HEIGHT,
+            x1: x + NUDGE_HALF_WIDTH,
+            y1: y + NUDGE_HALF_HEIGHT,
+        };
+        grids.iter().map(|grid| grid.edge_density(footprint)).sum::<f32>()
+            / grids.len().max(1) as f32
+    };
+
+    let original_busyness = busyness_at(anchor_x, anchor_y);
+    let mut best = (anchor_x, anchor_y, original_busyness, original_busyness);
+    if original_busyness > BUSY_THRESHOLD {
+        for dy in -NUDGE_MAX_STEPS..=NUDGE_MAX_STEPS {
+            for dx in -NUDGE_MAX_STEPS..=NUDGE_MAX_STEPS {
+                let x = (anchor_x + dx as f32 * NUDGE_STEP)
+                    .clamp(NUDGE_HALF_WIDTH, 1.0 - NUDGE_HALF_WIDTH);
+                let y = (anchor_y + dy as f32 * NUDGE_STEP)
+                    .clamp(NUDGE_HALF_HEIGHT, 1.0 - NUDGE_HALF_HEIGHT);
+                let busyness = busyness_at(x, y);
+                let distance = ((x - anchor_x).powi(2) + (y - anchor_y).powi(2)).sqrt();
+                let score = busyness + distance * NUDGE_DISTANCE_PENALTY;
+                if score < best.3 {
+                    best = (x, y, busyness, score);
+                }
+            }
+        }
+    }
+
+    let (x, y, busyness, _) = best;
+    let moved = (x, y) != (anchor_x, anchor_y);
+    let mut config = config.clone();
+    if moved {
+        config.position_mode = Some("custom".to_string());
+        config.custom_position = Some(CustomPosition { x, y });
+    }
+    PositionNudge {
+        config,
+        moved,
+        original_busyness,
+        busyness,
+    }
+}
+
+/// Estimated center of the watermark: the custom position, or a point just
+/// inside the frame edge for the preset positions.
+fn watermark_anchor(config: &WatermarkConfig) -> (f32, f32) {
+    if config.is_custom_position() {
+        if let Some(custom_pos) = &config.custom_position {
+            return (custom_pos.x, custom_pos.y);
+        }
+    }
+
+    let left = NUDGE_HALF_WIDTH;
+    let right = 1.0 - NUDGE_HALF_WIDTH;
+    let top = NUDGE_HALF_HEIGHT;
+    let bottom = 1.0 - NUDGE_HALF_HEIGHT;
+    match config.position {
+        WatermarkPosition::TopLeft => (left, top),
+        WatermarkPosition::TopCenter => (0.5, top),
+        WatermarkPosition::TopRight => (right, top),
+        WatermarkPosition::CenterLeft => (left, 0.5),
+        WatermarkPosition::Center => (0.5, 0.5),
+        WatermarkPosition::CenterRight => (right, 0.5),
+        WatermarkPosition::BottomLeft => (left, bottom),
+        WatermarkPosition::BottomCenter => (0.5, bottom),
+        WatermarkPosition::BottomRight => (right, bottom),
+    }
+}
@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+
+use tauri::AppHandle;
+
+use crate::ffmpeg::{spawn_ffmpeg, FfmpegError};
+use crate::sniff::{sniff, MediaKind};
+
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "m4a"];
+
+/// Size of the canvas generated when an audio file has no cover art yet.
+const COVER_SIZE: u32 = 1000;
+const WAVEFORM_WIDTH: u32 = 1280;
+const WAVEFORM_HEIGHT: u32 = 720;
+
+/// Whether `path` is an audio-only file, judged by its magic bytes when they
+/// are recognized and by its extension otherwise.
+pub fn is_audio_file(path: &Path) -> bool {
+    if let Some(sniffed) = sniff(path) {
+        return sniffed.kind == MediaKind::Audio;
+    }
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Copies the audio untouched and writes `owner` into the copyright and
+/// comment tags.
+pub async fn tag_ownership(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    owner: &str,
+) -> Result<(), FfmpegError> {
+    let mut args = vec![
+        "-i".to_string(),
+        input_path.to_string_lossy().into_owned(),
+        "-map".to_string(),
+        "0".to_string(),
+        "-c".to_string(),
+        "copy".to_string(),
+    ];
+    args.extend(ownership_metadata(owner));
+    args.extend(["-y".to_string(), output_path.to_string_lossy().into_owned()]);
+    spawn_ffmpeg(app, args).await.map(|_| ())
+}
+
+/// Writes the embedded cover art of `input_path` to `work_dir` as PNG, or a
+/// blank square canvas when the file has none.
+pub async fn extract_cover_art(
+    app: &AppHandle,
+    input_path: &Path,
+    work_dir: &Path,
+) -> Result<PathBuf, FfmpegError> {
+    let cover_path = work_dir.join("cover.png");
+    let extracted = spawn_ffmpeg(
+        app,
+        vec![
+            "-i".into(),
+            input_path.to_string_lossy().into_owned(),
+            "-an".into(),
+            "-frames:v".into(),
+            "1".into(),
+            "-y".into(),
+            cover_path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await;
+    if extracted.is_ok() && cover_path.exists() {
+        return Ok(cover_path);
+    }
+
+    spawn_ffmpeg(
+        app,
+        vec![
+            "-f".into(),
+            "lavfi".into(),
+            "-i".into(),
+            format!("color=c=black:s={}x{}", COVER_SIZE, COVER_SIZE),
+            "-frames:v".into(),
+            "1".into(),
+            "-y".into(),
+            cover_path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await?;
+    Ok(cover_path)
+}
+
+/// Replaces the cover art of `input_path` with `cover_path` and tags the
+/// owner, copying the audio stream as is.
+pub async fn embed_cover_art(
+    app: &AppHandle,
+    input_path: &Path,
+    cover_path: &Path,
+    output_path: &Path,
+    owner: Option<&str>,
+) -> Result<(), FfmpegError> {
+    let extension = input_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    if extension == "wav" {
+        return Err(FfmpegError::UnsupportedFormat(
+            "WAV files cannot carry cover art; use metadata or waveform mode".into(),
+        ));
+    }
+
+    let mut args = vec![
+        "-i".to_string(),
+        input_path.to_string_lossy().into_owned(),
+        "-i".to_string(),
+        cover_path.to_string_lossy().into_owned(),
+        "-map".to_string(),
+        "0:a".to_string(),
+        "-map".to_string(),
+        "1:v".to_string(),
+        "-c:a".to_string(),
+        "copy".to_string(),
+        "-c:v".to_string(),
+        "png".to_string(),
+        "-disposition:v:0".to_string(),
+        "attached_pic".to_string(),
+    ];
+    if extension == "mp3" {
+        // ID3v2.3 is the version most players read cover art from
+        args.extend(["-id3v2_version".to_string(), "3".to_string()]);
+    }
+    if let Some(owner) = owner {
+        args.extend(ownership_metadata(owner));
+    }
+    args.extend(["-y".to_string(), output_path.to_string_lossy().into_owned()]);
+    spawn_ffmpeg(app, args).await.map(|_| ())
+}
+
+/// Renders the audio as a waveform video with the original sound, ready to
+/// be watermarked like any other video.
+pub async fn render_waveform(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+) -> Result<(), FfmpegError> {
+    spawn_ffmpeg(
+        app,
+        vec![
+            "-i".into(),
+            input_path.to_string_lossy().into_owned(),
+            "-filter_complex".into(),
+            format!(
+                "[0:a]showwaves=s={}x{}:mode=cline:colors=white,format=yuv420p[wave]",
+                WAVEFORM_WIDTH, WAVEFORM_HEIGHT
+            ),
+            "-map".into(),
+            "[wave]".into(),
+            "-map".into(),
+            "0:a".into(),
+            "-c:v".into(),
+            "libx264".into(),
+            "-c:a".into(),
+            "aac".into(),
+            "-shortest".into(),
+            "-y".into(),
+            output_path.to_string_lossy().into_owned(),
+        ],
+    )
+    .await
+    .map(|_| ())
+}
+
+fn ownership_metadata(owner: &str) -> Vec<String> {
+    vec![
+        "-metadata".to_string(),
+        format!("copyright={}", owner),
+        "-metadata".to_string(),
+        format!("comment={}", owner),
+    ]
+}
@@ -0,0 +1,472 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+use tracing::warn;
+
+use crate::last_config::SETTINGS_STORE;
+use crate::types::{
+    CacheSettings, FileResult, ProcessedFileRecord, ProcessingStatus, WatermarkConfig,
+};
+
+const DATABASE_FILE: &str = "cache.sqlite3";
+const CACHE_SETTINGS_KEY: &str = "cacheSettings";
+
+/// Folder holding the thumbnails, created inside the configured directory so
+/// cleanups never touch other files there
+const THUMBNAIL_DIR: &str = "bulk-watermark-thumbnails";
+
+/// Thumbnail cache written by versions before the database, removed on open
+const LEGACY_THUMBNAIL_CACHE: &str = "cache.json";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS thumbnails (
+        cache_key TEXT PRIMARY KEY,
+        source_path TEXT NOT NULL,
+        source_mtime INTEGER NOT NULL,
+        thumbnail_path TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        last_accessed INTEGER NOT NULL,
+        file_size INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS thumbnails_last_accessed ON thumbnails (last_accessed);
+    CREATE TABLE IF NOT EXISTS processed_files (
+        id INTEGER PRIMARY KEY,
+        job_id TEXT NOT NULL,
+        input_path TEXT NOT NULL,
+        output_path TEXT,
+        status TEXT NOT NULL,
+        error TEXT,
+        processed_at INTEGER NOT NULL,
+        duration_ms INTEGER,
+        config_json TEXT
+    );
+    CREATE INDEX IF NOT EXISTS processed_files_input_path ON processed_files (input_path);
+    CREATE INDEX IF NOT EXISTS processed_files_processed_at ON processed_files (processed_at);
+";
+
+/// Columns added to `processed_files` after its first release, with their
+/// types, added to older databases on open
+const HISTORY_COLUMNS_ADDED: [(&str, &str); 2] =
+    [("duration_ms", "INTEGER"), ("config_json", "TEXT")];
+
+/// Which history entries `history` returns; unset fields match every entry.
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    pub input_path: Option<String>,
+    /// Text contained in the input or output path or anywhere in the config,
+    /// such as a client name in the watermark text; case-insensitive for ASCII
+    pub text: Option<String>,
+    /// Unix seconds from which entries are included
+    pub from: Option<u64>,
+    /// Unix seconds before which entries are included
+    pub to: Option<u64>,
+}
+
+/// SQLite store in the app data directory for the thumbnail cache and the
+/// history of processed files, managed as Tauri state. Opened on first use;
+/// every update is a single statement or transaction.
+#[derive(Default)]
+pub struct CacheDatabase {
+    connection: Mutex<Option<Connection>>,
+}
+
+impl CacheDatabase {
+    /// Returns the cached thumbnail for `cache_key` if its file still exists,
+    /// refreshing the entry's last access time.
+    pub fn lookup_thumbnail(&self, app: &AppHandle, cache_key: &str) -> Option<PathBuf> {
+        let result = self.with_connection(app, |connection| {
+            let thumbnail_path: Option<String> = connection
+                .query_row(
+                    "SELECT thumbnail_path FROM thumbnails WHERE cache_key = ?1",
+                    [cache_key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let Some(thumbnail_path) = thumbnail_path.map(PathBuf::from) else {
+                return Ok(None);
+            };
+            if !thumbnail_path.exists() {
+                // Thumbnail file missing, remove stale entry
+                connection.execute("DELETE FROM thumbnails WHERE cache_key = ?1", [cache_key])?;
+                return Ok(None);
+            }
+            connection.execute(
+                "UPDATE thumbnails SET last_accessed = ?1 WHERE cache_key = ?2",
+                params![now(), cache_key],
+            )?;
+            Ok(Some(thumbnail_path))
+        });
+        result.unwrap_or_else(|e| {
+            warn!(error = %e, "Failed to look up thumbnail");
+            None
+        })
+    }
+
+    /// Records a freshly generated thumbnail and evicts the least recently
+    /// used ones beyond the cache limits.
+    pub fn store_thumbnail(
+        &self,
+        app: &AppHandle,
+        cache_key: &str,
+        source_path: &str,
+        source_mtime: u64,
+        thumbnail_path: &Path,
+    ) {
+        let file_size = std::fs::metadata(thumbnail_path).map(|m| m.len()).unwrap_or(0);
+        let settings = load_settings(app);
+        let result = self.with_connection(app, |connection| {
+            let transaction = connection.transaction()?;
+            let now = now();
+            transaction.execute(
+                "INSERT OR REPLACE INTO thumbnails
+                    (cache_key, source_path, source_mtime, thumbnail_path, created_at,
+                     last_accessed, file_size)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6)",
+                params![
+                    cache_key,
+                    source_path,
+                    source_mtime,
+                    thumbnail_path.to_string_lossy(),
+                    now,
+                    file_size
+                ],
+            )?;
+            evict_lru_thumbnails(&transaction, &settings)?;
+            transaction.commit()
+        });
+        if let Err(e) = result {
+            warn!(error = %e, "Failed to store thumbnail");
+        }
+    }
+
+    /// Evicts the least recently used thumbnails beyond the current limits.
+    pub fn enforce_limits(&self, app: &AppHandle) -> Result<(), String> {
+        let settings = load_settings(app);
+        self.with_connection(app, |connection| {
+            let transaction = connection.transaction()?;
+            evict_lru_thumbnails(&transaction, &settings)?;
+            transaction.commit()
+        })
+    }
+
+    /// Deletes thumbnails created before `cutoff` (Unix seconds) or whose file
+    /// is gone, returning how many entries were removed and the bytes freed.
+    pub fn remove_thumbnails_before(
+        &self,
+        app: &AppHandle,
+        cutoff: u64,
+    ) -> Result<(usize, u64), String> {
+        self.with_connection(app, |connection| {
+            let transaction = connection.transaction()?;
+            let entries = {
+                let mut statement = transaction.prepare(
+                    "SELECT cache_key, thumbnail_path, created_at, file_size FROM thumbnails",
+                )?;
+                let rows = statement.query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        PathBuf::from(row.get::<_, String>(1)?),
+                        row.get::<_, u64>(2)?,
+                        row.get::<_, u64>(3)?,
+                    ))
+                })?;
+                rows.collect::<Result<Vec<_>, _>>()?
+            };
+
+            let mut cleaned_count = 0;
+            let mut freed_bytes = 0u64;
+            for (cache_key, thumbnail_path, created_at, file_size) in entries {
+                let exists = thumbnail_path.exists();
+                if created_at >= cutoff && exists {
+                    continue;
+                }
+                if exists {
+                    if let Err(e) = std::fs::remove_file(&thumbnail_path) {
+                        warn!(
+                            path = %thumbnail_path.display(),
+                            error = %e,
+                            "Failed to delete thumbnail"
+                        );
+                        continue;
+                    }
+                    freed_bytes += file_size;
+                }
+                transaction.execute("DELETE FROM thumbnails WHERE cache_key = ?1", [&cache_key])?;
+                cleaned_count += 1;
+            }
+            transaction.commit()?;
+            Ok((cleaned_count, freed_bytes))
+        })
+    }
+
+    /// Paths of every cached thumbnail, to find orphaned files.
+    pub fn thumbnail_paths(&self, app: &AppHandle) -> Result<HashSet<PathBuf>, String> {
+        self.with_connection(app, |connection| {
+            let mut statement = connection.prepare("SELECT thumbnail_path FROM thumbnails")?;
+            let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+            rows.map(|path| path.map(PathBuf::from)).collect()
+        })
+    }
+
+    /// Adds a processed file to the history with a snapshot of the `config` it
+    /// was processed with, its own caption included.
+    pub fn record_file(
+        &self,
+        app: &AppHandle,
+        job_id: &str,
+        file: &FileResult,
+        config: &WatermarkConfig,
+    ) {
+        let config_json = serde_json::to_string(config).ok();
+        let outcome = self.with_connection(app, |connection| {
+            connection.execute(
+                "INSERT INTO processed_files
+                    (job_id, input_path, output_path, status, error, processed_at,
+                     duration_ms, config_json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    job_id,
+                    file.input_path.to_string_lossy(),
+                    file.output_path.as_ref().map(|path| path.to_string_lossy()),
+                    status_name(&file.status),
+                    file.error,
+                    now(),
+                    file.duration_ms,
+                    config_json
+                ],
+            )
+        });
+        if let Err(e) = outcome {
+            warn!(error = %e, "Failed to record processing history");
+        }
+    }
+
+    /// Processed files matching `filter`, most recent first, skipping the
+    /// first `offset`.
+    pub fn history(
+        &self,
+        app: &AppHandle,
+        filter: &HistoryFilter,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<ProcessedFileRecord>, String> {
+        let pattern = filter.text.as_deref().map(|text| format!("%{}%", escape_like(text)));
+        self.with_connection(app, |connection| {
+            let mut statement = connection.prepare(
+                "SELECT id, job_id, input_path, output_path, status, error, processed_at,
+                        duration_ms, config_json
+                 FROM processed_files
+                 WHERE (?1 IS NULL OR input_path = ?1)
+                   AND (?2 IS NULL
+                        OR input_path LIKE ?2 ESCAPE '\\'
+                        OR output_path LIKE ?2 ESCAPE '\\'
+                        OR config_json LIKE ?2 ESCAPE '\\')
+                   AND (?3 IS NULL OR processed_at >= ?3)
+                   AND (?4 IS NULL OR processed_at < ?4)
+                 ORDER BY processed_at DESC, id DESC
+                 LIMIT ?5 OFFSET ?6",
+            )?;
+            let rows = statement.query_map(
+                params![
+                    filter.input_path,
+                    pattern,
+                    filter.from,
+                    filter.to,
+                    limit as i64,
+                    offset as i64
+                ],
+                history_record,
+            )?;
+            rows.collect()
+        })
+    }
+
+    /// Deletes history entries processed before `before` (Unix seconds), or
+    /// every entry without it. Returns how many were deleted.
+    pub fn clear_history(&self, app: &AppHandle, before: Option<u64>) -> Result<usize, String> {
+        self.with_connection(app, |connection| {
+            connection.execute(
+                "DELETE FROM processed_files WHERE ?1 IS NULL OR processed_at < ?1",
+                params![before],
+            )
+        })
+    }
+
+    fn with_connection<T>(
+        &self,
+        app: &AppHandle,
+        run: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, String> {
+        let mut guard = self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if guard.is_none() {
+            *guard = Some(open(app)?);
+        }
+        let connection = guard.as_mut().expect("connection was just opened");
+        run(connection).map_err(|e| e.to_string())
+    }
+}
+
+fn open(app: &AppHandle) -> Result<Connection, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    let connection = Connection::open(dir.join(DATABASE_FILE))
+        .map_err(|e| format!("Failed to open cache database: {}", e))?;
+    connection
+        .execute_batch(SCHEMA)
+        .map_err(|e| format!("Failed to create cache database tables: {}", e))?;
+    add_history_columns(&connection)
+        .map_err(|e| format!("Failed to update the processing history table: {}", e))?;
+
+    let legacy = std::env::temp_dir().join(THUMBNAIL_DIR).join(LEGACY_THUMBNAIL_CACHE);
+    if legacy.exists() {
+        // Its thumbnails are orphans now and go with the next cleanup
+        let _ = std::fs::remove_file(legacy);
+    }
+    Ok(connection)
+}
+
+/// Adds the `HISTORY_COLUMNS_ADDED` a database from an older version lacks;
+/// `CREATE TABLE IF NOT EXISTS` leaves its existing table as it was.
+fn add_history_columns(connection: &Connection) -> rusqlite::Result<()> {
+    let mut statement =
+        connection.prepare("SELECT name FROM pragma_table_info('processed_files')")?;
+    let columns = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+    for (column, column_type) in HISTORY_COLUMNS_ADDED {
+        if !columns.contains(column) {
+            connection.execute_batch(&format!(
+                "ALTER TABLE processed_files ADD COLUMN {} {}",
+                column, column_type
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+fn history_record(row: &Row) -> rusqlite::Result<ProcessedFileRecord> {
+    Ok(ProcessedFileRecord {
+        id: row.get(0)?,
+        job_id: row.get(1)?,
+        input_path: PathBuf::from(row.get::<_, String>(2)?),
+        output_path: row.get::<_, Option<String>>(3)?.map(PathBuf::from),
+        status: parse_status(&row.get::<_, String>(4)?),
+        error: row.get(5)?,
+        processed_at: row.get(6)?,
+        duration_ms: row.get(7)?,
+        config: row
+            .get::<_, Option<String>>(8)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+    })
+}
+
+/// Escapes the `LIKE` wildcards in `text` so it is matched literally.
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Deletes the least recently used thumbnails until the cache is within its
+/// entry and size limits.
+fn evict_lru_thumbnails(connection: &Connection, settings: &CacheSettings) -> rusqlite::Result<()> {
+    let (mut count, mut size): (u64, u64) = connection.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(file_size), 0) FROM thumbnails",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if count <= settings.max_entries && size <= settings.max_bytes {
+        return Ok(());
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT cache_key, thumbnail_path, file_size FROM thumbnails ORDER BY last_accessed",
+    )?;
+    let mut rows = statement.query([])?;
+    while count > settings.max_entries || size > settings.max_bytes {
+        let Some(row) = rows.next()? else {
+            break;
+        };
+        let cache_key: String = row.get(0)?;
+        let thumbnail_path = PathBuf::from(row.get::<_, String>(1)?);
+        let file_size: u64 = row.get(2)?;
+
+        // Delete the thumbnail file
+        if thumbnail_path.exists() {
+            if let Err(e) = std::fs::remove_file(&thumbnail_path) {
+                warn!(path = %thumbnail_path.display(), error = %e, "Failed to delete thumbnail");
+            }
+        }
+        connection.execute("DELETE FROM thumbnails WHERE cache_key = ?1", [&cache_key])?;
+        count -= 1;
+        size = size.saturating_sub(file_size);
+    }
+    Ok(())
+}
+
+/// Reads the cache settings saved with `save_settings`, or the defaults.
+pub fn load_settings(app: &AppHandle) -> CacheSettings {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(CACHE_SETTINGS_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(app: &AppHandle, settings: &CacheSettings) -> Result<(), String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings store: {}", e))?;
+    let value = serde_json::to_value(settings)
+        .map_err(|e| format!("Failed to serialize cache settings: {}", e))?;
+    store.set(CACHE_SETTINGS_KEY, value);
+    Ok(())
+}
+
+/// Directory new thumbnails are written to.
+pub fn thumbnail_dir(settings: &CacheSettings) -> PathBuf {
+    settings
+        .directory
+        .clone()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(THUMBNAIL_DIR)
+}
+
+fn status_name(status: &ProcessingStatus) -> &'static str {
+    match status {
+        ProcessingStatus::Success => "success",
+        ProcessingStatus::Failed => "failed",
+        ProcessingStatus::Skipped => "skipped",
+        ProcessingStatus::Duplicate => "duplicate",
+    }
+}
+
+fn parse_status(name: &str) -> ProcessingStatus {
+    match name {
+        "success" => ProcessingStatus::Success,
+        "skipped" => ProcessingStatus::Skipped,
+        "duplicate" => ProcessingStatus::Duplicate,
+        _ => ProcessingStatus::Failed,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tauri::AppHandle;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::ffmpeg::{
+    build_ffmpeg_command, spawn_ffmpeg_monitored, spawn_ffmpeg_with_retry, FfmpegError,
+    DEFAULT_STALL_TIMEOUT,
+};
+use crate::file_log;
+use crate::types::{ChunkedEncoding, WatermarkConfig};
+
+static WORK_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Watermarks a long video by splitting it into keyframe-aligned segments,
+/// encoding the segments in parallel and joining them with the concat demuxer.
+/// Every step has to finish by `deadline`.
+pub async fn encode_chunked(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    chunking: &ChunkedEncoding,
+    stall_timeout: Duration,
+    deadline: Option<Instant>,
+) -> Result<(), FfmpegError> {
+    let work_dir = create_work_dir()?;
+    let result = run_chunked(
+        app,
+        input_path,
+        output_path,
+        config,
+        chunking,
+        stall_timeout,
+        deadline,
+        &work_dir,
+    )
+    .await;
+    let _ = std::fs::remove_dir_all(&work_dir);
+    result
+}
+
+async fn run_chunked(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    chunking: &ChunkedEncoding,
+    stall_timeout: Duration,
+    deadline: Option<Instant>,
+    work_dir: &Path,
+) -> Result<(), FfmpegError> {
+    let extension = input_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("mp4")
+        .to_lowercase();
+
+    // Stream copy splits on keyframes, so segments are only roughly segment_secs long
+    let segment_pattern = work_dir.join(format!("seg_%05d.{}", extension));
+    spawn_ffmpeg_monitored(
+        app,
+        vec![
+            "-i".into(),
+            input_path.to_string_lossy().into_owned(),
+            "-map".into(),
+            "0".into(),
+            "-c".into(),
+            "copy".into(),
+            "-f".into(),
+            "segment".into(),
+            "-segment_time".into(),
+            chunking.segment_secs.max(1).to_string(),
+            "-reset_timestamps".into(),
+            "1".into(),
+            "-y".into(),
+            segment_pattern.to_string_lossy().into_owned(),
+        ],
+        DEFAULT_STALL_TIMEOUT,
+        deadline,
+        None,
+        config.low_priority(),
+    )
+    .await?;
+
+    let segments = list_segments(work_dir)?;
+    if segments.is_empty() {
+        return Err(FfmpegError::Execution(format!(
+            "splitting {} produced no segments",
+            input_path.display()
+        )));
+    }
+
+    let semaphore = Arc::new(Semaphore::new(resolve_parallelism(chunking.parallelism)));
+    let mut tasks = JoinSet::new();
+    let mut outputs = Vec::with_capacity(segments.len());
+
+    for segment in &segments {
+        let file_name = segment
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .replacen("seg_", "out_", 1);
+        let segment_output = work_dir.join(file_name);
+        let args = build_ffmpeg_command(app, segment, &segment_output, config, true)?;
+        outputs.push(segment_output);
+
+        let app = app.clone();
+        let semaphore = semaphore.clone();
+        let cpu_affinity = config.cpu_affinity.clone();
+        let low_priority = config.low_priority();
+        // Segments log into the file they belong to
+        let log = file_log::current();
+        tasks.spawn(file_log::within(log, async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .map_err(|e| FfmpegError::Execution(e.to_string()))?;
+            spawn_ffmpeg_with_retry(
+                &app,
+                args,
+                stall_timeout,
+                deadline,
+                cpu_affinity.as_deref(),
+                low_priority,
+            )
+            .await
+        }));
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        let result = joined.map_err(|e| FfmpegError::Execution(e.to_string()))?;
+        if let Err(err) = result {
+            tasks.abort_all();
+            return Err(err);
+        }
+    }
+
+    let list_path = work_dir.join("concat.txt");
+    let list = outputs
+        .iter()
+        .map(|path| format!("file '{}'\n", path.to_string_lossy().replace('\'', "'\\''")))
+        .collect::<String>();
+    std::fs::write(&list_path, list)
+        .map_err(|e| FfmpegError::Path(format!("failed to write concat list: {e}")))?;
+
+    let mut args: Vec<String> = vec![
+        "-f".into(),
+        "concat".into(),
+        "-safe".into(),
+        "0".into(),
+        "-i".into(),
+        list_path.to_string_lossy().into_owned(),
+    ];
+    if config.preserve_streams {
+        // Segments carry every stream; metadata and chapters come from the source
+        args.extend([
+            "-i".into(),
+            input_path.to_string_lossy().into_owned(),
+            "-map".into(),
+            "0".into(),
+            "-map_metadata".into(),
+            if config.strip_metadata { "-1" } else { "1" }.into(),
+            "-map_chapters".into(),
+            "1".into(),
+        ]);
+    } else if config.strip_metadata {
+        args.extend(["-map_metadata".into(), "-1".into()]);
+    }
+    args.extend([
+        "-c".into(),
+        "copy".into(),
+        "-y".into(),
+        output_path.to_string_lossy().into_owned(),
+    ]);
+    spawn_ffmpeg_monitored(app, args, DEFAULT_STALL_TIMEOUT, deadline, None, config.low_priority())
+        .await?;
+
+    Ok(())
+}
+
+fn create_work_dir() -> Result<PathBuf, FfmpegError> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let sequence = WORK_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+
+    let dir = std::env::temp_dir()
+        .join("bulk-watermark-chunks")
+        .join(format!("{}-{}", millis, sequence));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| FfmpegError::Path(format!("failed to create chunk directory: {e}")))?;
+    Ok(dir)
+}
+
+fn list_segments(work_dir: &Path) -> Result<Vec<PathBuf>, FfmpegError> {
+    let entries = std::fs::read_dir(work_dir)
+        .map_err(|e| FfmpegError::Path(format!("failed to read chunk directory: {e}")))?;
+
+    let mut segments: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("seg_"))
+        })
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+fn resolve_parallelism(requested: usize) -> usize {
+    if requested > 0 {
+        return requested;
+    }
+    // Each FFmpeg process is multi-threaded already; half the cores avoids thrashing
+    std::thread::available_parallelism()
+        .map(|cores| (cores.get() / 2).max(1))
+        .unwrap_or(1)
+}
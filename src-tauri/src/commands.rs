@@ -1,19 +1,100 @@
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager, State};
 use thiserror::Error;
+use tracing::warn;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
 
-use crate::ffmpeg::{build_ffmpeg_command, detect_file_type, spawn_ffmpeg, FfmpegError};
+use crate::analysis::{
+    adaptive_opacity, nudge_position, sample_luma_grid, sample_luma_grid_at, watermark_region,
+    LumaGrid,
+};
+use crate::audio::{
+    embed_cover_art, extract_cover_art, is_audio_file, render_waveform, tag_ownership,
+};
+use crate::cache_db::{self, CacheDatabase, HistoryFilter};
+use crate::chunked::encode_chunked;
+use crate::dedupe::{find_duplicate_inputs, link_or_copy};
+use crate::disk_space;
+use crate::error::{AppError, ErrorCode};
+use crate::ffmpeg::{
+    build_ffmpeg_command, build_original_frame_command, build_preview_clip_command,
+    build_preview_frame_command, build_side_by_side_command, detect_file_type, format_command_line,
+    get_ffmpeg_sidecar_path, has_bundled_ffmpeg, spawn_ffmpeg, spawn_ffmpeg_with_retry, FfmpegError,
+    DEFAULT_STALL_TIMEOUT,
+};
+use crate::ffmpeg_download;
+use crate::file_log::{self, FileLogs};
+use crate::hotfolder::{default_screenshot_dir, HotFolderState};
+use crate::hwaccel::{self, HardwareEncoder, HardwareEncoders};
+use crate::jobs::{BatchHistory, JobRegistry, PendingJob};
+use crate::journal::{self, BatchJournal};
+use crate::last_config::{self, LastConfig};
+use crate::launch::OpenedFiles;
+use crate::logging;
+use crate::mapping::{TextMapping, TextRules};
+use crate::metadata::copy_jpeg_metadata;
+use crate::postprocess::{PostProcessContext, PostProcessorRegistry};
+use crate::presets::{PresetFilter, PresetRepository};
+use crate::path_policy::PathPolicy;
+use crate::plan::estimate_output_bytes;
+use crate::probe::{
+    probe_audio_codecs, probe_dimensions, probe_duration, probe_media_info, probe_rotation,
+    probe_source_color, probe_stream_health,
+};
+use crate::self_test;
+use crate::sidecar::write_output_sidecar;
+use crate::sniff::sniff;
+use crate::sources::{GeneratedWatermark, SourceContext, WatermarkSource, WatermarkSourceRegistry};
+use crate::system_ffmpeg::{self, SystemFfmpeg};
+use crate::telemetry::{self, Telemetry};
+use crate::throttle::{create_staging_dir, throttled_copy};
+use crate::tokens::{expand_tokens, parse_locale, TokenContext};
+use crate::transform::{apply_lossless_transform, is_jpeg};
 use crate::types::{
-    BatchResult, FileItem, FileResult, PresetMetadata, ProcessingStatus, ProgressPayload, 
-    WatermarkConfig, WatermarkPreset, WatermarkType, ThumbnailCache, ThumbnailCacheEntry,
+    ArchiveCopy, AudioHandling, AudioWatermarkMode, BatchPlan, BatchResult, BatchResultPage,
+    CacheSettings, ComparisonPreview, DownloadedFfmpeg, FfmpegCommandPreview, FfmpegSource,
+    FfmpegSourceInfo, FileItem, FileResult, FolderScan, FontSizeMode, HardwareAcceleration,
+    ImageScaleMode, InterruptedBatch, MarginUnit, MediaInfo, MotionMode, PositionNudge,
+    PostProcessorInfo, PreflightReport, PresetMetadata, PresetOutput, PresetSource, PreviewClip,
+    PreviewFrame, ProcessedFileRecord, ProcessingStatus, ProgressPayload, ResizeMode, ResultFilter,
+    SampleBatchResult, ScheduleMode, SelfTestReport, ScrubFrame, ScrubStrip, SizeGrowth,
+    SizeGrowthPolicy, VideoFilmstrip, WatermarkConfig, WatermarkPreset, WatermarkSourceInfo,
+    WatermarkType,
 };
+use crate::updates::{self, ReleaseInfo, UpdateChannel, Updates};
+
+/// Files per page returned by `get_batch_results` when no limit is given.
+const RESULT_PAGE_SIZE: usize = 100;
+const MAX_RESULT_PAGE_SIZE: usize = 1000;
+
+/// Largest output width or height `resize` accepts.
+const MAX_OUTPUT_DIMENSION: u32 = 16384;
+
+/// Frames sampled from a video when looking for a quieter watermark spot.
+const NUDGE_SAMPLE_FRAMES: u32 = 6;
+
+/// Longest side of image thumbnails in pixels.
+const IMAGE_THUMBNAIL_SIZE: u32 = 512;
+
+/// Length and height of `render_video_preview` clips when not given.
+const PREVIEW_CLIP_SECS: f64 = 5.0;
+const MAX_PREVIEW_CLIP_SECS: f64 = 30.0;
+const PREVIEW_CLIP_HEIGHT: u32 = 480;
+
+/// Log lines returned by `get_recent_logs` when no count is given.
+const DEFAULT_RECENT_LOG_LINES: usize = 200;
+
+/// Lines of FFmpeg output kept in the error of a failed file.
+const ERROR_SUMMARY_LINES: usize = 3;
 
 #[derive(Debug, Error)]
-enum ProcessingError {
+pub(crate) enum ProcessingError {
     #[error("{0}")]
     Message(String),
     #[error(transparent)]
@@ -23,6 +104,21 @@ enum ProcessingError {
 }
 
 impl ProcessingError {
+    /// Message for a failed file's result. FFmpeg failures keep only the last
+    /// lines of its output, where the actual error is; the full output is in
+    /// the file's log.
+    pub(crate) fn summary(&self) -> String {
+        match self {
+            ProcessingError::Ffmpeg(FfmpegError::Execution(output)) => {
+                let lines: Vec<&str> =
+                    output.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+                let tail = &lines[lines.len().saturating_sub(ERROR_SUMMARY_LINES)..];
+                format!("FFmpeg exited with error: {}", tail.join("\n"))
+            }
+            err => err.to_string(),
+        }
+    }
+
     fn is_catastrophic(&self) -> bool {
         matches!(
             self,
@@ -30,6 +126,24 @@ impl ProcessingError {
                 | ProcessingError::Ffmpeg(FfmpegError::Spawn(_))
         )
     }
+
+    /// Coarse failure kind for telemetry, free of paths and messages.
+    pub(crate) fn category(&self) -> &'static str {
+        match self {
+            ProcessingError::Message(_) => "processing",
+            ProcessingError::Io(_) => "io",
+            ProcessingError::Ffmpeg(err) => match err {
+                FfmpegError::MissingBinary(_) => "ffmpeg_missing",
+                FfmpegError::InvalidConfig(_) => "invalid_config",
+                FfmpegError::UnsupportedFormat(_) => "unsupported_format",
+                FfmpegError::Spawn(_) => "ffmpeg_spawn",
+                FfmpegError::Execution(_) => "ffmpeg_execution",
+                FfmpegError::Path(_) => "path",
+                FfmpegError::Stalled(_) => "ffmpeg_stalled",
+                FfmpegError::Timeout(_) => "ffmpeg_timeout",
+            },
+        }
+    }
 }
 
 #[tauri::command]
@@ -38,57 +152,719 @@ pub async fn process_single_file(
     input_path: String,
     output_path: String,
     config: WatermarkConfig,
-) -> Result<FileResult, String> {
+) -> Result<FileResult, AppError> {
+    telemetry::record_usage(&app, "process_single_file", &config);
     if let Err(err) = validate_config(&config) {
         return Ok(FileResult {
             input_path: PathBuf::from(&input_path),
             output_path: None,
             status: ProcessingStatus::Failed,
-            error: Some(err.to_string()),
+            error: Some(err.summary()),
+            error_code: Some(ErrorCode::from(&err)),
+            duplicate_of: None,
+            size_growth: None,
+            estimated_bytes: None,
+            duration_ms: None,
+            variant_outputs: Vec::new(),
         });
     }
 
     let input = PathBuf::from(&input_path);
     let output = PathBuf::from(&output_path);
 
-    match process_file_internal(&app, &input, &output, &config).await {
-        Ok(_) => Ok(FileResult {
-            input_path: input,
-            output_path: Some(output),
-            status: ProcessingStatus::Success,
-            error: None,
-        }),
-        Err(err) if err.is_catastrophic() => Err(err.to_string()),
-        Err(err) => Ok(FileResult {
-            input_path: PathBuf::from(input_path),
+    let started = Instant::now();
+    let result = match process_file_internal(&app, &input, &output, &config, None).await {
+        Err(err) if err.is_catastrophic() => return Err(err.into()),
+        result => result,
+    };
+    let file_result = processed_file_result(&input, &output, &config, &result, started);
+    let job_id = app.state::<BatchHistory>().next_job_id();
+    app.state::<CacheDatabase>().record_file(&app, &job_id, &file_result, &config);
+    Ok(file_result)
+}
+
+/// `FileResult` of a file watermarked outside a batch by
+/// `process_file_internal`, which started at `started`.
+pub(crate) fn processed_file_result(
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    result: &Result<Option<SizeGrowth>, ProcessingError>,
+    started: Instant,
+) -> FileResult {
+    let duration_ms = Some(started.elapsed().as_millis() as u64);
+    match result {
+        Ok(size_growth) => {
+            let outputs = output_paths(output_path, config);
+            FileResult {
+                input_path: input_path.to_path_buf(),
+                output_path: outputs.first().cloned(),
+                status: ProcessingStatus::Success,
+                error: None,
+                error_code: None,
+                duplicate_of: None,
+                size_growth: size_growth.clone(),
+                estimated_bytes: None,
+                duration_ms,
+                variant_outputs: variant_outputs(outputs, config),
+            }
+        }
+        Err(err) => FileResult {
+            input_path: input_path.to_path_buf(),
             output_path: None,
             status: ProcessingStatus::Failed,
-            error: Some(err.to_string()),
-        }),
+            error: Some(err.summary()),
+            error_code: Some(ErrorCode::from(err)),
+            duplicate_of: None,
+            size_growth: None,
+            estimated_bytes: None,
+            duration_ms,
+            variant_outputs: Vec::new(),
+        },
     }
 }
 
+/// Watermarks `files` into `output_dir`. With a `preset_id`, the preset's
+/// output settings fill in whatever `config` leaves at its defaults.
+///
+/// With `simulate`, every file is validated, probed and given its output path
+/// and estimated size, but FFmpeg never runs and nothing is written: the
+/// result reports what the batch would do.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn process_batch(
     app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    files: Vec<FileItem>,
+    mut config: WatermarkConfig,
+    output_dir: String,
+    text_mapping_path: Option<String>,
+    summary_only: Option<bool>,
+    preset_id: Option<String>,
+    simulate: Option<bool>,
+) -> Result<BatchResult, AppError> {
+    let mut output_dir_path = PathBuf::from(&output_dir);
+    if let Some(preset_id) = preset_id {
+        if let Some(output) = presets.load(&app, &preset_id)?.output {
+            validate_preset_output(&output).map_err(AppError::invalid_config)?;
+            output.apply_to(&mut config);
+            if let Some(subfolder) = &output.subfolder {
+                output_dir_path.push(subfolder);
+            }
+        }
+    }
+
+    telemetry::record_usage(&app, "process_batch", &config);
+    validate_config(&config).map_err(AppError::invalid_config)?;
+    let text_mapping = load_text_mapping(text_mapping_path)?;
+
+    let simulate = simulate.unwrap_or(false);
+    if !simulate {
+        ensure_output_approved(&app, &output_dir_path)?;
+        std::fs::create_dir_all(&output_dir_path)?;
+        disk_space::check(&files, &config, &output_dir_path)
+            .map_err(AppError::disk_full_predicted)?;
+    }
+
+    let job_id = app.state::<BatchHistory>().next_job_id();
+    let batch_result = run_batch(
+        &app,
+        &job_id,
+        &files,
+        &config,
+        text_mapping.as_ref(),
+        &output_dir_path,
+        &(0..files.len() as u64).collect::<Vec<_>>(),
+        simulate,
+    )
+        .await?;
+
+    finish_batch(&app, job_id, batch_result, summary_only.unwrap_or(false))
+}
+
+/// Optional check before a batch starts: reads the header of every file and
+/// reports the ones that are missing, unsupported or corrupt.
+#[tauri::command]
+pub async fn preflight_batch(
+    app: AppHandle,
+    files: Vec<FileItem>,
+) -> Result<PreflightReport, AppError> {
+    Ok(crate::preflight::preflight(&app, &files).await)
+}
+
+/// Dry run of `process_batch`: lists the output path of every file and flags
+/// collisions, overwrites and unsupported files, without running FFmpeg.
+#[tauri::command]
+pub async fn plan_batch(
+    files: Vec<FileItem>,
+    config: WatermarkConfig,
+    output_dir: String,
+) -> Result<BatchPlan, AppError> {
+    validate_config(&config).map_err(AppError::invalid_config)?;
+    Ok(crate::plan::plan_batch(&files, &config, Path::new(&output_dir)))
+}
+
+/// Processes a random sample of the batch first and parks the remaining files
+/// under a job ID so they can be continued with `continue_sample_batch` once the
+/// user has approved the sample output.
+#[tauri::command]
+pub async fn process_sample_batch(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
     files: Vec<FileItem>,
     config: WatermarkConfig,
     output_dir: String,
-) -> Result<BatchResult, String> {
-    validate_config(&config).map_err(|err| err.to_string())?;
+    sample_size: usize,
+    text_mapping_path: Option<String>,
+) -> Result<SampleBatchResult, AppError> {
+    telemetry::record_usage(&app, "process_sample_batch", &config);
+    validate_config(&config).map_err(AppError::invalid_config)?;
+    let text_mapping = load_text_mapping(text_mapping_path)?;
+
+    if sample_size == 0 {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            "Sample size must be at least 1",
+        ));
+    }
+
+    let output_dir_path = PathBuf::from(&output_dir);
+    ensure_output_approved(&app, &output_dir_path)?;
+    std::fs::create_dir_all(&output_dir_path)?;
+    disk_space::check(&files, &config, &output_dir_path).map_err(AppError::disk_full_predicted)?;
+
+    // Files keep their position in the list the user chose for `{counter}`
+    let numbered = files.into_iter().enumerate().map(|(index, file)| (index as u64, file));
+    let (sample, remaining) = split_random_sample(numbered.collect(), sample_size);
+    let (sample_sequences, sample): (Vec<u64>, Vec<FileItem>) = sample.into_iter().unzip();
+    let (remaining_sequences, remaining): (Vec<u64>, Vec<FileItem>) =
+        remaining.into_iter().unzip();
+
+    let batch_job_id = app.state::<BatchHistory>().next_job_id();
+    let sample_result = run_batch(
+        &app,
+        &batch_job_id,
+        &sample,
+        &config,
+        text_mapping.as_ref(),
+        &output_dir_path,
+        &sample_sequences,
+        false,
+    )
+        .await?;
+
+    let remaining_count = remaining.len();
+    let job_id = jobs.insert(PendingJob {
+        remaining,
+        remaining_sequences,
+        config,
+        text_mapping,
+        output_dir: output_dir_path,
+        sample_result: sample_result.clone(),
+        batch_job_id,
+    });
+
+    Ok(SampleBatchResult {
+        job_id,
+        sample: sample_result,
+        remaining: remaining_count,
+    })
+}
+
+/// Continues a sampled batch with the files that were held back. The returned
+/// result covers the whole batch, sample files included.
+#[tauri::command]
+pub async fn continue_sample_batch(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+) -> Result<BatchResult, AppError> {
+    let job = jobs
+        .take(&job_id)
+        .ok_or_else(|| AppError::not_found(format!("No pending job with ID {job_id}")))?;
+
+    if let Err(shortfall) = disk_space::check(&job.remaining, &job.config, &job.output_dir) {
+        // Kept so the batch can be continued once space has been freed
+        jobs.restore(job_id, job);
+        return Err(AppError::disk_full_predicted(shortfall));
+    }
+
+    let remainder = match run_batch(
+        &app,
+        &job.batch_job_id,
+        &job.remaining,
+        &job.config,
+        job.text_mapping.as_ref(),
+        &job.output_dir,
+        &job.remaining_sequences,
+        false,
+    )
+    .await
+    {
+        Ok(remainder) => remainder,
+        Err(err) => {
+            // Kept so the batch can be continued once the problem is fixed
+            jobs.restore(job_id, job);
+            return Err(err.into());
+        }
+    };
+
+    let mut batch_result = job.sample_result;
+    batch_result.total += remainder.total;
+    batch_result.successful += remainder.successful;
+    batch_result.failed += remainder.failed;
+    batch_result.duplicates += remainder.duplicates;
+    batch_result.grown += remainder.grown;
+    batch_result.files.extend(remainder.files);
+
+    finish_batch(&app, job.batch_job_id, batch_result, false)
+}
+
+/// Pages through the files of a finished batch, optionally filtered by status
+/// or by text in the input path.
+#[tauri::command]
+pub async fn get_batch_results(
+    history: State<'_, BatchHistory>,
+    job_id: String,
+    filter: Option<ResultFilter>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<BatchResultPage, AppError> {
+    let filter = filter.unwrap_or_default();
+    let query = filter.query.as_deref().map(str::to_lowercase).filter(|q| !q.is_empty());
+    let offset = offset.unwrap_or(0);
+    let limit = limit.unwrap_or(RESULT_PAGE_SIZE).clamp(1, MAX_RESULT_PAGE_SIZE);
+
+    history
+        .with_result(&job_id, |result| {
+            let matching: Vec<&FileResult> = result
+                .files
+                .iter()
+                .filter(|file| filter.status.as_ref().is_none_or(|status| file.status == *status))
+                .filter(|file| {
+                    query.as_ref().is_none_or(|query| {
+                        file.input_path.to_string_lossy().to_lowercase().contains(query)
+                    })
+                })
+                .collect();
+            BatchResultPage {
+                files: matching.iter().skip(offset).take(limit).map(|&file| file.clone()).collect(),
+                offset,
+                matching: matching.len(),
+                total: result.files.len(),
+            }
+        })
+        .ok_or_else(|| AppError::not_found(format!("No finished batch with ID {job_id}")))
+}
+
+/// Returns the last `lines` lines of the app log (200 by default), oldest
+/// first.
+#[tauri::command]
+pub async fn get_recent_logs(app: AppHandle, lines: Option<usize>) -> Result<Vec<String>, AppError> {
+    Ok(logging::recent_lines(&app, lines.unwrap_or(DEFAULT_RECENT_LOG_LINES))?)
+}
+
+/// Writes the kept log files into `destination` as one file, for attaching to
+/// a bug report. Returns the path written.
+#[tauri::command]
+pub async fn export_logs(app: AppHandle, destination: String) -> Result<String, AppError> {
+    logging::export(&app, Path::new(&destination))?;
+    Ok(destination)
+}
+
+/// Returns the FFmpeg output of the last time `input_path` was processed, or
+/// `None` when it has not been processed since launch.
+#[tauri::command]
+pub async fn get_file_log(
+    logs: State<'_, FileLogs>,
+    input_path: String,
+) -> Result<Option<String>, AppError> {
+    Ok(logs.get(Path::new(&input_path)))
+}
+
+/// Lists processed files from every batch run so far, most recent first,
+/// optionally only those of one input file.
+#[tauri::command]
+pub async fn get_processing_history(
+    app: AppHandle,
+    cache: State<'_, CacheDatabase>,
+    input_path: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<ProcessedFileRecord>, AppError> {
+    let limit = limit.unwrap_or(RESULT_PAGE_SIZE).clamp(1, MAX_RESULT_PAGE_SIZE);
+    let filter = HistoryFilter {
+        input_path,
+        ..HistoryFilter::default()
+    };
+    Ok(cache.history(&app, &filter, limit, 0)?)
+}
+
+/// Lists every processed file in the history, most recent first, a page of
+/// `limit` at a time starting after the first `offset`.
+#[tauri::command]
+pub async fn get_history(
+    app: AppHandle,
+    cache: State<'_, CacheDatabase>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<ProcessedFileRecord>, AppError> {
+    let limit = limit.unwrap_or(RESULT_PAGE_SIZE).clamp(1, MAX_RESULT_PAGE_SIZE);
+    Ok(cache.history(&app, &HistoryFilter::default(), limit, offset.unwrap_or(0))?)
+}
+
+/// Searches the history for files whose paths or watermark config contain
+/// `query`, such as a client name in the watermark text, processed from
+/// `from` until before `to` (Unix seconds). Most recent first.
+#[tauri::command]
+pub async fn search_history(
+    app: AppHandle,
+    cache: State<'_, CacheDatabase>,
+    query: Option<String>,
+    from: Option<u64>,
+    to: Option<u64>,
+    limit: Option<usize>,
+) -> Result<Vec<ProcessedFileRecord>, AppError> {
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(AppError::invalid_config("The search must start before it ends"));
+        }
+    }
+    let limit = limit.unwrap_or(RESULT_PAGE_SIZE).clamp(1, MAX_RESULT_PAGE_SIZE);
+    let filter = HistoryFilter {
+        text: query.filter(|query| !query.trim().is_empty()),
+        from,
+        to,
+        ..HistoryFilter::default()
+    };
+    Ok(cache.history(&app, &filter, limit, 0)?)
+}
+
+/// Deletes the history of files processed before `before` (Unix seconds), or
+/// all of it. Returns how many entries were deleted.
+#[tauri::command]
+pub async fn clear_history(
+    app: AppHandle,
+    cache: State<'_, CacheDatabase>,
+    before: Option<u64>,
+) -> Result<usize, AppError> {
+    Ok(cache.clear_history(&app, before)?)
+}
+
+/// Lists the hardware video encoders that work on this machine, most
+/// preferred first.
+#[tauri::command]
+pub async fn get_hardware_encoders(app: AppHandle) -> Result<Vec<HardwareEncoder>, AppError> {
+    Ok(hwaccel::detect(&app).await)
+}
+
+/// Returns which FFmpeg runs and whether the bundled and system binaries are
+/// usable.
+#[tauri::command]
+pub async fn get_ffmpeg_source(
+    app: AppHandle,
+    system: State<'_, SystemFfmpeg>,
+) -> Result<FfmpegSourceInfo, AppError> {
+    system.reset();
+    let (system_binary, system_error) = match system_ffmpeg::find(&app, "ffmpeg") {
+        Ok(binary) => (Some(binary), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    Ok(FfmpegSourceInfo {
+        source: system_ffmpeg::load_source(&app),
+        bundled_available: has_bundled_ffmpeg(&app),
+        system_path: system_binary.as_ref().map(|binary| binary.path.clone()),
+        system_version: system_binary.map(|binary| binary.version),
+        system_error,
+    })
+}
+
+/// Chooses between the bundled FFmpeg and one on `PATH`. Choosing `System`
+/// fails when no recent enough system FFmpeg and ffprobe are found.
+#[tauri::command]
+pub async fn set_ffmpeg_source(
+    app: AppHandle,
+    system: State<'_, SystemFfmpeg>,
+    encoders: State<'_, HardwareEncoders>,
+    source: FfmpegSource,
+) -> Result<(), AppError> {
+    system.reset();
+    if source == FfmpegSource::System {
+        for name in ["ffmpeg", "ffprobe"] {
+            system_ffmpeg::find(&app, name).map_err(AppError::invalid_config)?;
+        }
+    }
+    system_ffmpeg::save_source(&app, source)?;
+    // Encoders were detected with the previous binary
+    encoders.reset();
+    Ok(())
+}
+
+/// Downloads a static FFmpeg and ffprobe build for this platform into the app
+/// data directory, used when the bundled binaries are missing. Emits
+/// `ffmpeg-download-progress` while downloading.
+#[tauri::command]
+pub async fn download_ffmpeg(
+    app: AppHandle,
+    encoders: State<'_, HardwareEncoders>,
+) -> Result<DownloadedFfmpeg, AppError> {
+    let downloaded = ffmpeg_download::download(&app).await?;
+    // Detection may have run without any FFmpeg
+    encoders.reset();
+    Ok(downloaded)
+}
+
+/// Runs FFmpeg and watermarks a bundled sample image, reporting which step
+/// fails, so support can tell a missing binary from missing fonts.
+#[tauri::command]
+pub async fn self_test(app: AppHandle) -> Result<SelfTestReport, AppError> {
+    Ok(self_test::run(&app).await)
+}
+
+/// Lists batches that stopped before finishing, as recorded by their journals.
+#[tauri::command]
+pub async fn list_interrupted_batches(app: AppHandle) -> Result<Vec<InterruptedBatch>, AppError> {
+    Ok(journal::list_interrupted(&app)?)
+}
+
+/// Resumes an interrupted batch: partial outputs of the files that were
+/// mid-encode are deleted, then every file that did not finish is processed
+/// again. The returned result covers only those files.
+#[tauri::command]
+pub async fn resume_interrupted_batch(
+    app: AppHandle,
+    batch_id: String,
+) -> Result<BatchResult, AppError> {
+    let replay = journal::load_interrupted(&app, &batch_id)?;
+    validate_config(&replay.config).map_err(AppError::invalid_config)?;
+    let unfinished = replay.unfinished_files();
+    disk_space::check(&unfinished, &replay.config, &replay.output_dir)
+        .map_err(AppError::disk_full_predicted)?;
+    replay.remove_partial_outputs();
+    journal::remove(&app, &batch_id)?;
+
+    std::fs::create_dir_all(&replay.output_dir)?;
+    let job_id = app.state::<BatchHistory>().next_job_id();
+    let batch_result = run_batch(
+        &app,
+        &job_id,
+        &unfinished,
+        &replay.config,
+        replay.text_mapping.as_ref(),
+        &replay.output_dir,
+        &replay.unfinished_sequences(),
+        false,
+    )
+        .await?;
+
+    finish_batch(&app, job_id, batch_result, false)
+}
+
+/// Abandons an interrupted batch, deleting the partial outputs of the files
+/// that were mid-encode along with its journal.
+#[tauri::command]
+pub async fn discard_interrupted_batch(app: AppHandle, batch_id: String) -> Result<(), AppError> {
+    let replay = journal::load_interrupted(&app, &batch_id)?;
+    replay.remove_partial_outputs();
+    Ok(journal::remove(&app, &batch_id)?)
+}
+
+/// Drops a pending sampled batch when the user rejects the sample output.
+#[tauri::command]
+pub async fn discard_sample_batch(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+) -> Result<(), AppError> {
+    jobs.take(&job_id)
+        .map(|_| ())
+        .ok_or_else(|| AppError::not_found(format!("No pending job with ID {job_id}")))
+}
+
+/// Records a finished batch for paging and announces it. With `summary_only`
+/// the file list is left out of the returned result and the event; it can be
+/// fetched page by page with `get_batch_results`.
+fn finish_batch(
+    app: &AppHandle,
+    job_id: String,
+    mut batch_result: BatchResult,
+    summary_only: bool,
+) -> Result<BatchResult, AppError> {
+    app.state::<BatchHistory>().record(&job_id, &batch_result);
+    batch_result.job_id = Some(job_id);
+    if summary_only {
+        batch_result.files = Vec::new();
+    }
+    app.emit_to("main", "watermark-complete", &batch_result)?;
+    Ok(batch_result)
+}
+
+/// Approves `path` as an output folder once the user has confirmed the
+/// `CONFIRMATION_REQUIRED` error of a batch; folders below it count as
+/// approved too.
+#[tauri::command]
+pub async fn approve_output_location(
+    app: AppHandle,
+    policy: State<'_, PathPolicy>,
+    path: String,
+) -> Result<(), AppError> {
+    Ok(policy.approve(&app, Path::new(&path))?)
+}
+
+/// Fails with `ConfirmationRequired` until `dir` has been approved.
+fn ensure_output_approved(app: &AppHandle, dir: &Path) -> Result<(), AppError> {
+    app.state::<PathPolicy>()
+        .check_output_dir(app, dir)
+        .map_err(AppError::confirmation_required)
+}
+
+/// Reads an image from the OS clipboard, watermarks it and returns the output path.
+#[tauri::command]
+pub async fn process_clipboard_image(
+    app: AppHandle,
+    config: WatermarkConfig,
+    output_dir: String,
+) -> Result<String, AppError> {
+    telemetry::record_usage(&app, "process_clipboard_image", &config);
+    validate_config(&config).map_err(AppError::invalid_config)?;
 
     let output_dir_path = PathBuf::from(&output_dir);
-    std::fs::create_dir_all(&output_dir_path).map_err(|err| err.to_string())?;
+    ensure_output_approved(&app, &output_dir_path)?;
+    std::fs::create_dir_all(&output_dir_path)?;
+
+    let clipboard_path = save_clipboard_image()?;
+    let output_path = build_output_path(&output_dir_path, &clipboard_path, &config, None);
+
+    let started = Instant::now();
+    let result = process_file_internal(&app, &clipboard_path, &output_path, &config, None).await;
+    let file_result =
+        processed_file_result(&clipboard_path, &output_path, &config, &result, started);
+    let job_id = app.state::<BatchHistory>().next_job_id();
+    app.state::<CacheDatabase>().record_file(&app, &job_id, &file_result, &config);
+
+    // The clipboard capture is only an intermediate file
+    if let Err(e) = std::fs::remove_file(&clipboard_path) {
+        warn!(path = %clipboard_path.display(), error = %e, "Failed to remove clipboard capture");
+    }
+
+    result?;
+    let output_path = output_paths(&output_path, &config).swap_remove(0);
+    Ok(output_path.to_string_lossy().into_owned())
+}
+
+fn save_clipboard_image() -> Result<PathBuf, ProcessingError> {
+    let mut clipboard = arboard::Clipboard::new()
+        .map_err(|e| ProcessingError::Message(format!("Failed to access clipboard: {}", e)))?;
+    let image = clipboard
+        .get_image()
+        .map_err(|e| ProcessingError::Message(format!("Clipboard does not contain an image: {}", e)))?;
+
+    let width = u32::try_from(image.width)
+        .map_err(|_| ProcessingError::Message("Clipboard image is too large".into()))?;
+    let height = u32::try_from(image.height)
+        .map_err(|_| ProcessingError::Message("Clipboard image is too large".into()))?;
+    let buffer = image::RgbaImage::from_raw(width, height, image.bytes.into_owned())
+        .ok_or_else(|| ProcessingError::Message("Clipboard image data is malformed".into()))?;
+
+    let capture_dir = std::env::temp_dir().join("bulk-watermark-clipboard");
+    std::fs::create_dir_all(&capture_dir)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let capture_path = capture_dir.join(format!("clipboard_{}.png", timestamp));
+
+    buffer
+        .save(&capture_path)
+        .map_err(|e| ProcessingError::Message(format!("Failed to save clipboard image: {}", e)))?;
+
+    Ok(capture_path)
+}
+
+fn load_text_mapping(path: Option<String>) -> Result<Option<TextMapping>, String> {
+    path.filter(|p| !p.trim().is_empty())
+        .map(|p| TextMapping::load(Path::new(&p)))
+        .transpose()
+}
+
+fn split_random_sample<T>(items: Vec<T>, sample_size: usize) -> (Vec<T>, Vec<T>) {
+    let amount = sample_size.min(items.len());
+    let mut picked = rand::seq::index::sample(&mut rand::thread_rng(), items.len(), amount).into_vec();
+    picked.sort_unstable();
 
+    let mut sample = Vec::with_capacity(amount);
+    let mut remaining = Vec::with_capacity(items.len() - amount);
+    let mut picked_iter = picked.into_iter().peekable();
+
+    for (index, item) in items.into_iter().enumerate() {
+        if picked_iter.peek() == Some(&index) {
+            picked_iter.next();
+            sample.push(item);
+        } else {
+            remaining.push(item);
+        }
+    }
+
+    (sample, remaining)
+}
+
+/// Processes `files` in order, adding each to the processing history under
+/// `job_id`. `sequences` holds the zero-based position of every file within
+/// the whole batch, used for the `{counter}` token. A `simulate`d batch keeps
+/// no journal or history and writes nothing; see `simulate_file`.
+#[allow(clippy::too_many_arguments)]
+async fn run_batch(
+    app: &AppHandle,
+    job_id: &str,
+    files: &[FileItem],
+    config: &WatermarkConfig,
+    text_mapping: Option<&TextMapping>,
+    output_dir_path: &Path,
+    sequences: &[u64],
+    simulate: bool,
+) -> Result<BatchResult, ProcessingError> {
     let total_files = files.len();
     let mut successful = 0usize;
     let mut failed = 0usize;
-    let mut results = Vec::with_capacity(total_files);
+    let mut duplicates = 0usize;
+    let mut grown = 0usize;
+    let mut results: Vec<FileResult> = Vec::with_capacity(total_files);
+    let text_rules = TextRules::compile(&config.text_rules).map_err(ProcessingError::Message)?;
+    let history = app.state::<CacheDatabase>();
+
+    let journal = if simulate {
+        Ok(None)
+    } else {
+        BatchJournal::create(app, files, sequences, config, text_mapping, output_dir_path)
+            .map(Some)
+    };
+    let mut journal = match journal {
+        Ok(journal) => journal,
+        Err(e) => {
+            warn!(error = %e, "Failed to create batch journal");
+            None
+        }
+    };
+
+    // Per-file tokens, mapped captions and watermark sources, which generate
+    // from the path and sequence, make the output depend on more than the content
+    let per_file_watermark = has_per_file_watermark(config) || text_mapping.is_some();
+    let duplicate_inputs = if config.reuse_duplicate_inputs && !per_file_watermark {
+        // Hashing large videos takes long enough to stall the async runtime
+        let files = files.to_vec();
+        tokio::task::spawn_blocking(move || find_duplicate_inputs(&files))
+            .await
+            .unwrap_or_else(|e| {
+                warn!(error = %e, "Duplicate input detection panicked");
+                HashMap::new()
+            })
+    } else {
+        HashMap::new()
+    };
 
     for (index, file) in files.iter().enumerate() {
         let file_path_string = file.path.to_string_lossy().to_string();
         emit_progress(
-            &app,
+            app,
             ProgressPayload {
                 file_path: file_path_string.clone(),
                 file_index: index,
@@ -97,25 +873,100 @@ pub async fn process_batch(
             },
         );
 
-        let output_path = build_output_path(&output_dir_path, &file.path);
+        // Files listed in the text mapping or matched by a text rule get their own caption
+        let caption = match text_mapping.and_then(|mapping| mapping.text_for(&file.path)) {
+            Some(text) => Some(text.to_string()),
+            None => text_rules.text_for(&file.path),
+        };
+        let file_config = match caption {
+            Some(text) => {
+                let mut mapped = config.clone();
+                mapped.text = text;
+                Cow::Owned(mapped)
+            }
+            None => Cow::Borrowed(config),
+        };
+
+        let sequence = sequences.get(index).copied();
+        let output_path = build_output_path(output_dir_path, &file.path, &file_config, sequence);
+        let outputs = output_paths(&output_path, &file_config);
+
+        if let Some(journal) = journal.as_mut() {
+            journal.file_started(index, &outputs);
+        }
+
+        if let Some(file_result) = reuse_duplicate_output(
+            &duplicate_inputs,
+            &results,
+            text_mapping,
+            index,
+            &file.path,
+            &outputs,
+            simulate,
+        ) {
+            successful += 1;
+            duplicates += 1;
+            if let Some(journal) = journal.as_mut() {
+                journal.file_finished(index, ProcessingStatus::Duplicate);
+            }
+            emit_progress(
+                app,
+                ProgressPayload {
+                    file_path: file_path_string,
+                    file_index: index,
+                    total_files,
+                    status: "complete".to_string(),
+                },
+            );
+            if !simulate {
+                history.record_file(app, job_id, &file_result, &file_config);
+            }
+            results.push(file_result);
+            continue;
+        }
 
-        let processing_result =
-            process_file_internal(&app, &file.path, &output_path, &config).await;
+        let started = Instant::now();
+        let processing_result = if simulate {
+            simulate_file(app, &file.path, &output_path, &file_config, sequence)
+                .await
+                .map(|estimated| (None, Some(estimated)))
+        } else {
+            process_file_internal(app, &file.path, &output_path, &file_config, sequence)
+                .await
+                .map(|size_growth| (size_growth, None))
+        };
 
+        let duration_ms = started.elapsed().as_millis() as u64;
         let (file_result, status_label) = match processing_result {
-            Ok(_) => {
+            Ok((size_growth, estimated_bytes)) => {
                 successful += 1;
+                if size_growth.is_some() {
+                    grown += 1;
+                }
                 (
                     FileResult {
                         input_path: file.path.clone(),
-                        output_path: Some(output_path.clone()),
+                        output_path: Some(outputs[0].clone()),
                         status: ProcessingStatus::Success,
                         error: None,
+                        error_code: None,
+                        duplicate_of: None,
+                        size_growth,
+                        estimated_bytes,
+                        duration_ms: Some(duration_ms),
+                        variant_outputs: variant_outputs(outputs, &file_config),
                     },
                     "complete".to_string(),
                 )
             }
-            Err(err) if err.is_catastrophic() => return Err(err.to_string()),
+            Err(err) if err.is_catastrophic() => {
+                // Nothing can be resumed without a working FFmpeg
+                if let Some(mut journal) = journal {
+                    journal.file_finished(index, ProcessingStatus::Failed);
+                    journal.finish();
+                }
+                return Err(err);
+            }
             Err(err) => {
                 failed += 1;
                 (
@@ -123,15 +974,25 @@ pub async fn process_batch(
                         input_path: file.path.clone(),
                         output_path: None,
                         status: ProcessingStatus::Failed,
-                        error: Some(err.to_string()),
+                        error: Some(err.summary()),
+                        error_code: Some(ErrorCode::from(&err)),
+                        duplicate_of: None,
+                        size_growth: None,
+                        estimated_bytes: None,
+                        duration_ms: Some(duration_ms),
+                        variant_outputs: Vec::new(),
                     },
                     "error".to_string(),
                 )
             }
         };
 
+        if let Some(journal) = journal.as_mut() {
+            journal.file_finished(index, file_result.status.clone());
+        }
+
         emit_progress(
-            &app,
+            app,
             ProgressPayload {
                 file_path: file_path_string,
                 file_index: index,
@@ -140,28 +1001,183 @@ pub async fn process_batch(
             },
         );
 
+        if !simulate {
+            history.record_file(app, job_id, &file_result, &file_config);
+        }
         results.push(file_result);
     }
 
-    let batch_result = BatchResult {
+    if let Some(journal) = journal {
+        journal.finish();
+    }
+
+    let estimated_bytes =
+        simulate.then(|| results.iter().filter_map(|file| file.estimated_bytes).sum());
+    Ok(BatchResult {
         files: results,
         total: total_files,
         successful,
         failed,
+        duplicates,
+        grown,
+        job_id: None,
+        simulated: simulate,
+        estimated_bytes,
+    })
+}
+
+/// Links or copies the output of an identical earlier input. Returns `None`
+/// when the file has no duplicate, its original failed or was captioned
+/// differently, or the output could not be reused, so the caller processes
+/// it normally. A `simulate`d batch only reports the reuse. `outputs` are the
+/// file's outputs as returned by `output_paths`.
+fn reuse_duplicate_output(
+    duplicate_inputs: &HashMap<usize, usize>,
+    results: &[FileResult],
+    text_mapping: Option<&TextMapping>,
+    index: usize,
+    input_path: &Path,
+    outputs: &[PathBuf],
+    simulate: bool,
+) -> Option<FileResult> {
+    let original = &results[*duplicate_inputs.get(&index)?];
+    if original.status != ProcessingStatus::Success {
+        return None;
+    }
+    if let Some(mapping) = text_mapping {
+        if mapping.text_for(input_path) != mapping.text_for(&original.input_path) {
+            return None;
+        }
+    }
+    let original_outputs = match original.variant_outputs.as_slice() {
+        [] => vec![original.output_path.clone()?],
+        variant_outputs => variant_outputs.to_vec(),
     };
 
-    app.emit_to("main", "watermark-complete", &batch_result)
-        .map_err(|err| err.to_string())?;
+    if !simulate {
+        for (original_output, output_path) in original_outputs.iter().zip(outputs) {
+            if let Err(e) = link_or_copy(original_output, output_path) {
+                warn!(path = %input_path.display(), error = %e, "Failed to reuse duplicate output");
+                return None;
+            }
+        }
+    }
 
-    Ok(batch_result)
+    let variant_outputs = if original.variant_outputs.is_empty() {
+        Vec::new()
+    } else {
+        outputs.to_vec()
+    };
+    Some(FileResult {
+        input_path: input_path.to_path_buf(),
+        output_path: outputs.first().cloned(),
+        status: ProcessingStatus::Duplicate,
+        error: None,
+        error_code: None,
+        duplicate_of: Some(original.input_path.clone()),
+        size_growth: None,
+        estimated_bytes: None,
+        duration_ms: None,
+        variant_outputs,
+    })
+}
+
+fn has_per_file_watermark(config: &WatermarkConfig) -> bool {
+    if config.watermark_source.is_some() {
+        return true;
+    }
+    matches!(config.watermark_type, WatermarkType::Text)
+        && (config.text.contains('{') || !config.text_rules.is_empty())
 }
 
-async fn process_file_internal(
+pub(crate) async fn process_file_internal(
     app: &AppHandle,
     input_path: &Path,
     output_path: &Path,
     config: &WatermarkConfig,
-) -> Result<(), ProcessingError> {
+    sequence: Option<u64>,
+) -> Result<Option<SizeGrowth>, ProcessingError> {
+    ensure_input_preserved(input_path, output_path, config)?;
+    let result = file_log::capture(app, input_path, async {
+        if config.export_variants.is_empty() {
+            watermark_file(app, input_path, output_path, config, sequence).await
+        } else {
+            watermark_variants(app, input_path, output_path, config, sequence).await
+        }
+    })
+    .await;
+    if let Err(err) = &result {
+        telemetry::record_failure(app, err.category());
+    }
+    result
+}
+
+/// Writes one output per export variant, stopping at the first failure. The
+/// archive copy is made once; the first output that grew is reported.
+async fn watermark_variants(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    sequence: Option<u64>,
+) -> Result<Option<SizeGrowth>, ProcessingError> {
+    let mut size_growth = None;
+    let mut variant_config = config.clone();
+    let outputs = output_paths(output_path, config);
+    for (variant, variant_path) in config.export_variants.iter().zip(&outputs) {
+        variant_config.resize = variant.resize.clone();
+        let grown = watermark_file(app, input_path, variant_path, &variant_config, sequence).await?;
+        size_growth = size_growth.or(grown);
+        variant_config.archive_copy = None;
+    }
+    Ok(size_growth)
+}
+
+/// The checks `watermark_file` makes before encoding, without running
+/// FFmpeg: the input must exist, be a supported and readable media file, and
+/// yield a valid FFmpeg command. Returns the estimated output size.
+async fn simulate_file(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    sequence: Option<u64>,
+) -> Result<u64, ProcessingError> {
+    let input_bytes = std::fs::metadata(input_path)
+        .map_err(|_| ProcessingError::Message("Input file not found".into()))?
+        .len();
+    ensure_input_preserved(input_path, output_path, config)?;
+
+    let is_audio = is_audio_file(input_path);
+    if !is_audio {
+        let is_video = detect_file_type(input_path)?;
+        probe_stream_health(app, input_path).await?;
+
+        if let Some(id) = &config.watermark_source {
+            // Sources may call out to services, so only check that it is registered
+            watermark_source(app, id)?;
+        } else {
+            let mut file_config = config.clone();
+            if matches!(file_config.watermark_type, WatermarkType::Text) {
+                file_config.text =
+                    expand_tokens(&config.text, &token_context(input_path, config, sequence));
+            }
+            // Detecting hardware encoders runs FFmpeg; the CPU command checks the same settings
+            file_config.hardware_acceleration = HardwareAcceleration::Off;
+            build_ffmpeg_command(app, input_path, output_path, &file_config, is_video)?;
+        }
+    }
+
+    Ok(estimate_output_bytes(input_bytes, is_audio, config))
+}
+
+async fn watermark_file(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    sequence: Option<u64>,
+) -> Result<Option<SizeGrowth>, ProcessingError> {
     if !input_path.exists() {
         return Err(ProcessingError::Message("Input file not found".into()));
     }
@@ -170,20 +1186,540 @@ async fn process_file_internal(
         std::fs::create_dir_all(parent)?;
     }
 
-    let is_video = detect_file_type(input_path).map_err(ProcessingError::from)?;
-    let args = build_ffmpeg_command(app, input_path, output_path, config, is_video)
-        .map_err(ProcessingError::from)?;
-    spawn_ffmpeg(app, args)
-        .await
-        .map_err(ProcessingError::from)?;
+    // Expand per-file placeholders such as {date} before building the filter
+    let mut file_config = config.clone();
+    if let Some(id) = &config.watermark_source {
+        generate_watermark(app, id, input_path, sequence, &mut file_config).await?;
+    }
+    if matches!(file_config.watermark_type, WatermarkType::Text) {
+        file_config.text =
+            expand_tokens(&file_config.text, &token_context(input_path, config, sequence));
+    }
 
-    Ok(())
-}
+    let is_audio = is_audio_file(input_path);
+    let is_video = if is_audio {
+        false
+    } else {
+        detect_file_type(input_path).map_err(ProcessingError::from)?
+    };
 
-fn validate_config(config: &WatermarkConfig) -> Result<(), ProcessingError> {
-    match config.watermark_type {
-        WatermarkType::Text => {
-            if config.text.trim().is_empty() {
+    let mut size_growth = None;
+    if is_audio {
+        encode_audio(app, input_path, output_path, file_config).await?;
+    } else {
+        prepare_file_config(app, input_path, is_video, &mut file_config).await?;
+
+        let stronger = (config.size_growth == SizeGrowthPolicy::Recompress)
+            .then(|| file_config.with_stronger_compression());
+        match config.io_limit_kb_per_sec.filter(|limit| *limit > 0) {
+            Some(limit) => {
+                encode_file_throttled(app, input_path, output_path, file_config, is_video, limit.saturating_mul(1024))
+                    .await?
+            }
+            None => encode_file(app, input_path, output_path, file_config, is_video).await?,
+        }
+
+        if config.size_growth != SizeGrowthPolicy::Accept {
+            size_growth = check_size_growth(app, input_path, output_path, stronger, is_video).await?;
+        }
+
+        // The JPEG rotate/crop promises to keep EXIF, which the encode drops
+        let keep_metadata = config.preserve_metadata || config.jpeg_transform.is_some();
+        if keep_metadata && !config.strip_metadata && !is_video {
+            // Like the sidecar, metadata is best effort on an already written output
+            if let Err(e) = copy_jpeg_metadata(input_path, output_path) {
+                warn!(path = %output_path.display(), error = %e, "Failed to copy metadata");
+            }
+        }
+    }
+
+    if let Some(archive) = &config.archive_copy {
+        write_archive_copy(app, input_path, archive, is_video).await?;
+    }
+
+    run_post_processors(app, input_path, output_path, config).await?;
+
+    if config.write_sidecar {
+        // Provenance metadata is best effort; the output itself is already written.
+        // Hashing large videos takes long enough to stall the async runtime
+        let (input, output) = (input_path.to_path_buf(), output_path.to_path_buf());
+        let sidecar_config = config.clone();
+        let written = tokio::task::spawn_blocking(move || {
+            write_output_sidecar(&input, &output, &sidecar_config)
+        })
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        if let Err(e) = written {
+            warn!(path = %output_path.display(), error = %e, "Failed to write sidecar");
+        }
+    }
+
+    Ok(size_growth)
+}
+
+/// Reports an output larger than its input. With a `stronger` config the
+/// file is encoded again and the smaller of the two encodes is kept.
+async fn check_size_growth(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    stronger: Option<WatermarkConfig>,
+    is_video: bool,
+) -> Result<Option<SizeGrowth>, ProcessingError> {
+    let input_bytes = std::fs::metadata(input_path)?.len();
+    let mut output_bytes = std::fs::metadata(output_path)?.len();
+    if output_bytes <= input_bytes {
+        return Ok(None);
+    }
+
+    let mut recompressed = false;
+    if let Some(stronger) = stronger {
+        let extension = output_path.extension().and_then(|ext| ext.to_str()).unwrap_or("out");
+        let retry_path = output_path.with_extension(format!("recompress.{}", extension));
+        match encode_file(app, input_path, &retry_path, stronger, is_video).await {
+            Ok(()) => {
+                let retry_bytes = std::fs::metadata(&retry_path)?.len();
+                if retry_bytes < output_bytes {
+                    std::fs::rename(&retry_path, output_path)?;
+                    output_bytes = retry_bytes;
+                    recompressed = true;
+                }
+            }
+            Err(e) => warn!(path = %output_path.display(), error = %e, "Failed to recompress"),
+        }
+        let _ = std::fs::remove_file(&retry_path);
+    }
+
+    Ok(Some(SizeGrowth {
+        input_bytes,
+        output_bytes,
+        recompressed,
+    }))
+}
+
+/// Resolves the per-file settings that depend on the frame being watermarked:
+/// relative font size, auto-contrast variant and the fade-out end.
+async fn prepare_file_config(
+    app: &AppHandle,
+    frame_source: &Path,
+    is_video: bool,
+    file_config: &mut WatermarkConfig,
+) -> Result<(), ProcessingError> {
+    if matches!(file_config.watermark_type, WatermarkType::Text)
+        && file_config.font_size_mode == FontSizeMode::PercentHeight
+    {
+        file_config.font_size = resolve_relative_font_size(app, frame_source, file_config).await?;
+    }
+
+    if file_config.auto_contrast || file_config.adaptive_opacity.is_some() {
+        // Both read the same frame; without it the configured look is kept
+        match sample_luma_grid(app, frame_source, is_video).await {
+            Ok(grid) => {
+                if let Some(range) = file_config.adaptive_opacity.clone() {
+                    file_config.opacity = adaptive_opacity(&grid, file_config, &range);
+                }
+                if file_config.auto_contrast {
+                    apply_auto_contrast(&grid, file_config);
+                }
+            }
+            Err(e) => {
+                warn!(path = %frame_source.display(), error = %e, "Failed to sample luminance")
+            }
+        }
+    }
+
+    // The bit depth and HDR tags decide the pixel format and encoder
+    if is_video {
+        match probe_source_color(app, frame_source).await {
+            Ok(color) => file_config.source_color = color,
+            Err(e) => warn!(path = %frame_source.display(), error = %e, "Failed to probe colors"),
+        }
+    }
+
+    // Copying audio the output container cannot hold fails the encode
+    if is_video && file_config.audio_handling == AudioHandling::Auto {
+        match probe_audio_codecs(app, frame_source).await {
+            Ok(codecs) => file_config.source_audio_codecs = codecs,
+            Err(e) => warn!(path = %frame_source.display(), error = %e, "Failed to probe audio"),
+        }
+    }
+
+    // Detection runs once; encoders are picked when the command is built
+    if is_video && file_config.hardware_acceleration != HardwareAcceleration::Off {
+        hwaccel::detect(app).await;
+    }
+
+    if is_video && file_config.schedule != ScheduleMode::Always {
+        let duration = match file_config.schedule {
+            ScheduleMode::Outro => Some(probe_duration(app, frame_source).await?),
+            _ => None,
+        };
+        file_config.apply_schedule(duration);
+    }
+
+    // Fading out needs to know when the video ends
+    if is_video && file_config.fade_out_secs > 0.0 && file_config.end_time.is_none() {
+        match probe_duration(app, frame_source).await {
+            Ok(duration) => file_config.end_time = Some(duration),
+            Err(e) => warn!(path = %frame_source.display(), error = %e, "Failed to probe duration"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Brands an audio-only file according to `audio_mode`. Cover art and
+/// waveform frames go through the regular image/video watermark pipeline.
+async fn encode_audio(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    mut file_config: WatermarkConfig,
+) -> Result<(), ProcessingError> {
+    let owner = file_config.audio_owner.clone().or_else(|| {
+        matches!(file_config.watermark_type, WatermarkType::Text).then(|| file_config.text.clone())
+    });
+
+    let work_dir = create_staging_dir()?;
+    let result = async {
+        match file_config.audio_mode {
+            AudioWatermarkMode::Metadata => {
+                let owner = owner.ok_or_else(|| {
+                    ProcessingError::Message("Audio metadata mode requires an owner".into())
+                })?;
+                tag_ownership(app, input_path, output_path, &owner).await?;
+            }
+            AudioWatermarkMode::CoverArt => {
+                let cover = extract_cover_art(app, input_path, &work_dir).await?;
+                let branded = work_dir.join("cover_watermarked.png");
+                prepare_file_config(app, &cover, false, &mut file_config).await?;
+                encode_file(app, &cover, &branded, file_config, false).await?;
+                embed_cover_art(app, input_path, &branded, output_path, owner.as_deref()).await?;
+            }
+            AudioWatermarkMode::Waveform => {
+                let waveform = work_dir.join("waveform.mp4");
+                render_waveform(app, input_path, &waveform).await?;
+                prepare_file_config(app, &waveform, true, &mut file_config).await?;
+                encode_file(app, &waveform, output_path, file_config, true).await?;
+            }
+        }
+        Ok::<(), ProcessingError>(())
+    }
+    .await;
+
+    if let Err(e) = std::fs::remove_dir_all(&work_dir) {
+        warn!(path = %work_dir.display(), error = %e, "Failed to remove audio work directory");
+    }
+
+    result
+}
+
+/// Writes the unwatermarked archive copy of `input_path`, keeping its file
+/// name. Transcoded videos get an `.mp4` extension.
+async fn write_archive_copy(
+    app: &AppHandle,
+    input_path: &Path,
+    archive: &ArchiveCopy,
+    is_video: bool,
+) -> Result<(), ProcessingError> {
+    let archive_dir = Path::new(&archive.output_dir);
+    std::fs::create_dir_all(archive_dir)?;
+    let file_name = input_path
+        .file_name()
+        .ok_or_else(|| ProcessingError::Message("Input path has no file name".into()))?;
+    let mut archive_path = archive_dir.join(file_name);
+
+    if is_video && archive.transcode {
+        archive_path.set_extension("mp4");
+        let args = vec![
+            "-i".to_string(),
+            input_path.to_string_lossy().into_owned(),
+            "-map".to_string(),
+            "0:v:0".to_string(),
+            "-map".to_string(),
+            "0:a?".to_string(),
+            "-c:v".to_string(),
+            "libx264".to_string(),
+            "-crf".to_string(),
+            "18".to_string(),
+            "-c:a".to_string(),
+            "aac".to_string(),
+            "-y".to_string(),
+            archive_path.to_string_lossy().into_owned(),
+        ];
+        spawn_ffmpeg(app, args).await?;
+    } else {
+        std::fs::copy(input_path, &archive_path)?;
+    }
+
+    Ok(())
+}
+
+/// Converts a percent-of-height font size to pixels for this file, measured on
+/// the frame after any rotate/crop transform.
+async fn resolve_relative_font_size(
+    app: &AppHandle,
+    input_path: &Path,
+    config: &WatermarkConfig,
+) -> Result<u32, ProcessingError> {
+    let (width, height) = probe_dimensions(app, input_path).await?;
+
+    let transform = config.jpeg_transform.as_ref().filter(|_| is_jpeg(input_path));
+    let (frame_width, frame_height) = match transform {
+        Some(transform) => match &transform.crop {
+            Some(crop) => (crop.width, crop.height),
+            None if matches!(transform.rotate, 90 | 270) => (height, width),
+            None => (width, height),
+        },
+        None => (width, height),
+    };
+    let frame_height = match &config.resize {
+        Some(resize) => resize.output_size(frame_width, frame_height).1,
+        None => frame_height,
+    };
+
+    let size = (frame_height as f32 * config.relative_font_size / 100.0).round();
+    Ok((size as u32).max(1))
+}
+
+/// Swaps in the dark watermark variant when the area under the watermark is
+/// bright in `grid`.
+fn apply_auto_contrast(grid: &LumaGrid, config: &mut WatermarkConfig) {
+    if grid.mean(watermark_region(config)) < 0.55 {
+        return;
+    }
+
+    match config.watermark_type {
+        WatermarkType::Text => {
+            config.text_color = config
+                .dark_text_color
+                .clone()
+                .unwrap_or_else(|| invert_color(&config.text_color));
+        }
+        WatermarkType::Image => {
+            if let Some(dark_image) = config.dark_image_path.clone() {
+                config.image_path = Some(dark_image);
+            }
+        }
+        // A clip has no dark variant to switch to
+        WatermarkType::Video => {}
+    }
+}
+
+/// Inverts a `#rrggbb` color; other color formats fall back to black.
+fn invert_color(color: &str) -> String {
+    color
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .map(|rgb| format!("#{:06x}", 0xffffff - rgb))
+        .unwrap_or_else(|| "#000000".to_string())
+}
+
+fn watermark_source(
+    app: &AppHandle,
+    id: &str,
+) -> Result<Arc<dyn WatermarkSource>, ProcessingError> {
+    app.state::<WatermarkSourceRegistry>()
+        .get(id)
+        .ok_or_else(|| ProcessingError::Message(format!("Unknown watermark source: {id}")))
+}
+
+/// Replaces the configured text or image with the one the source generates
+/// for this file.
+async fn generate_watermark(
+    app: &AppHandle,
+    id: &str,
+    input_path: &Path,
+    sequence: Option<u64>,
+    file_config: &mut WatermarkConfig,
+) -> Result<(), ProcessingError> {
+    let source = watermark_source(app, id)?;
+    let context = SourceContext {
+        input_path: input_path.to_path_buf(),
+        sequence,
+        config: file_config.clone(),
+    };
+    let generated = tokio::task::spawn_blocking(move || source.generate(&context))
+        .await
+        .map_err(|e| ProcessingError::Message(format!("Watermark source {id} panicked: {e}")))?
+        .map_err(|e| ProcessingError::Message(format!("Watermark source {id} failed: {e}")))?;
+
+    match generated {
+        GeneratedWatermark::Image(path) => {
+            file_config.watermark_type = WatermarkType::Image;
+            file_config.image_path = Some(path.to_string_lossy().into_owned());
+        }
+        GeneratedWatermark::Text(text) => {
+            file_config.watermark_type = WatermarkType::Text;
+            file_config.text = text;
+        }
+    }
+    Ok(())
+}
+
+async fn run_post_processors(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+) -> Result<(), ProcessingError> {
+    if config.post_processors.is_empty() {
+        return Ok(());
+    }
+
+    let registry = app.state::<PostProcessorRegistry>();
+    for id in &config.post_processors {
+        let processor = registry
+            .get(id)
+            .ok_or_else(|| ProcessingError::Message(format!("Unknown post-processor: {id}")))?;
+        if !processor.applies_to(output_path) {
+            continue;
+        }
+
+        let context = PostProcessContext {
+            input_path: input_path.to_path_buf(),
+            output_path: output_path.to_path_buf(),
+            config: config.clone(),
+        };
+        tokio::task::spawn_blocking(move || processor.process(&context))
+            .await
+            .map_err(|e| ProcessingError::Message(format!("Post-processor {id} panicked: {e}")))?
+            .map_err(|e| ProcessingError::Message(format!("Post-processor {id} failed: {e}")))?;
+    }
+
+    Ok(())
+}
+
+/// Stages the input and output on local disk so reads from and writes to
+/// network storage happen at no more than `bytes_per_sec`.
+async fn encode_file_throttled(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    file_config: WatermarkConfig,
+    is_video: bool,
+    bytes_per_sec: u64,
+) -> Result<(), ProcessingError> {
+    let staging_dir = create_staging_dir()?;
+    let staged_input = staging_dir.join("in").join(input_path.file_name().unwrap_or_default());
+    let staged_output = staging_dir.join("out").join(output_path.file_name().unwrap_or_default());
+
+    let result = async {
+        throttled_copy(input_path, &staged_input, bytes_per_sec).await?;
+        encode_file(app, &staged_input, &staged_output, file_config, is_video).await?;
+        throttled_copy(&staged_output, output_path, bytes_per_sec).await?;
+        Ok::<(), ProcessingError>(())
+    }
+    .await;
+
+    if let Err(e) = std::fs::remove_dir_all(&staging_dir) {
+        warn!(path = %staging_dir.display(), error = %e, "Failed to remove staging directory");
+    }
+
+    result
+}
+
+async fn encode_file(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    mut file_config: WatermarkConfig,
+    is_video: bool,
+) -> Result<(), ProcessingError> {
+    let stall_timeout = file_config
+        .stall_timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_STALL_TIMEOUT);
+    let deadline = file_config
+        .file_timeout_secs
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+    // Segments restart at t=0, which would shift a time-based watermark.
+    // Stream-copied segments do not reliably keep the rotation either, so
+    // rotated phone videos are encoded in one pass
+    if is_video && !file_config.is_time_based() {
+        if let Some(chunking) = file_config.chunked_encoding.clone() {
+            if probe_rotation(app, input_path).await.unwrap_or(0) == 0 {
+                return encode_chunked(
+                    app,
+                    input_path,
+                    output_path,
+                    &file_config,
+                    &chunking,
+                    stall_timeout,
+                    deadline,
+                )
+                .await
+                .map_err(ProcessingError::from);
+            }
+        }
+    }
+
+    // Rotate/crop JPEGs losslessly up front when possible; otherwise the
+    // transform is applied by FFmpeg in the same pass as the watermark
+    let mut intermediate = None;
+    if let Some(transform) = file_config.jpeg_transform.clone().filter(|t| !t.is_identity()) {
+        if is_jpeg(input_path) {
+            if let Some(transformed) = apply_lossless_transform(input_path, &transform).await {
+                file_config.jpeg_transform = None;
+                intermediate = Some(transformed);
+            }
+        }
+    }
+    let source_path = intermediate.as_deref().unwrap_or(input_path);
+
+    let result = match build_ffmpeg_command(app, source_path, output_path, &file_config, is_video) {
+        Ok(args) => {
+            spawn_ffmpeg_with_retry(
+                app,
+                args,
+                stall_timeout,
+                deadline,
+                file_config.cpu_affinity.as_deref(),
+                file_config.low_priority(),
+            )
+            .await
+            .map(|_| ())
+        }
+        Err(err) => Err(err),
+    };
+
+    if let Some(path) = &intermediate {
+        let _ = std::fs::remove_file(path);
+    }
+
+    result.map_err(ProcessingError::from)
+}
+
+/// Checks the output settings of a preset. The subfolder has to stay inside
+/// the batch output directory.
+fn validate_preset_output(output: &PresetOutput) -> Result<(), ProcessingError> {
+    if output.quality.is_some_and(|quality| quality == 0 || quality > 100) {
+        return Err(ProcessingError::Message(
+            "Preset output quality must be between 1 and 100".into(),
+        ));
+    }
+    if let Some(subfolder) = &output.subfolder {
+        let is_nested = Path::new(subfolder)
+            .components()
+            .all(|component| matches!(component, std::path::Component::Normal(_)));
+        if subfolder.trim().is_empty() || !is_nested {
+            return Err(ProcessingError::Message(format!(
+                "Preset output subfolder must be a relative folder name: {}",
+                subfolder
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_config(config: &WatermarkConfig) -> Result<(), ProcessingError> {
+    match config.watermark_type {
+        // The source supplies the text or image for each file
+        _ if config.watermark_source.is_some() => {}
+        WatermarkType::Text => {
+            if config.text.trim().is_empty() {
                 return Err(ProcessingError::Message(
                     "Text watermark requires non-empty text".into(),
                 ));
@@ -199,6 +1735,16 @@ fn validate_config(config: &WatermarkConfig) -> Result<(), ProcessingError> {
                 )));
             }
         }
+        WatermarkType::Video => {
+            let video_path = config.video_path.as_ref().ok_or_else(|| {
+                ProcessingError::Message("Video watermark requires video_path".into())
+            })?;
+            if !Path::new(video_path).exists() {
+                return Err(ProcessingError::Message(format!(
+                    "Watermark video not found at {video_path}"
+                )));
+            }
+        }
     }
 
     if config.opacity > 100 {
@@ -207,6 +1753,185 @@ fn validate_config(config: &WatermarkConfig) -> Result<(), ProcessingError> {
         ));
     }
 
+    if let Some(range) = &config.adaptive_opacity {
+        if range.min > range.max || range.max > 100 {
+            return Err(ProcessingError::Message(
+                "Adaptive opacity needs a minimum no higher than a maximum of at most 100".into(),
+            ));
+        }
+    }
+
+    if config.font_size_mode == FontSizeMode::PercentHeight
+        && !(config.relative_font_size > 0.0 && config.relative_font_size <= 100.0)
+    {
+        return Err(ProcessingError::Message(
+            "Relative font size must be between 0 and 100 percent".into(),
+        ));
+    }
+
+    if matches!(config.watermark_type, WatermarkType::Image | WatermarkType::Video) {
+        let scale = config.image_scale.unwrap_or(20);
+        if scale == 0 {
+            return Err(ProcessingError::Message(
+                "Image scale must be greater than 0".into(),
+            ));
+        }
+        if config.image_scale_mode != ImageScaleMode::Pixels && scale > 100 {
+            return Err(ProcessingError::Message(
+                "Image scale must be between 1 and 100 percent".into(),
+            ));
+        }
+    }
+
+    if config.margin_x < 0.0 || config.margin_y < 0.0 {
+        return Err(ProcessingError::Message("Margins must not be negative".into()));
+    }
+    if config.margin_unit == MarginUnit::Percent && (config.margin_x > 50.0 || config.margin_y > 50.0) {
+        return Err(ProcessingError::Message(
+            "Percentage margins must be between 0 and 50".into(),
+        ));
+    }
+
+    if config.start_time.is_some_and(|start| start < 0.0)
+        || config.end_time.is_some_and(|end| end < 0.0)
+    {
+        return Err(ProcessingError::Message("Watermark times must not be negative".into()));
+    }
+    if config.motion != MotionMode::Static && config.motion_speed <= 0.0 {
+        return Err(ProcessingError::Message("Motion speed must be greater than 0".into()));
+    }
+    if let Some(cycle) = &config.intermittent {
+        if !(cycle.show_secs > 0.0 && cycle.show_secs < cycle.every_secs) {
+            return Err(ProcessingError::Message(
+                "Intermittent display time must be greater than 0 and shorter than its interval"
+                    .into(),
+            ));
+        }
+    }
+    if config.schedule != ScheduleMode::Always && config.schedule_secs <= 0.0 {
+        return Err(ProcessingError::Message(
+            "Intro/outro length must be greater than 0".into(),
+        ));
+    }
+    if config.jitter_interval_secs.is_some_and(|secs| secs <= 0.0) {
+        return Err(ProcessingError::Message("Jitter interval must be greater than 0".into()));
+    }
+    if let Some(locale) = &config.token_locale {
+        parse_locale(locale).map_err(ProcessingError::Message)?;
+    }
+    if config.cpu_affinity.as_ref().is_some_and(|cores| cores.is_empty()) {
+        return Err(ProcessingError::Message(
+            "CPU affinity must list at least one core".into(),
+        ));
+    }
+    if config.background_mode.as_ref().is_some_and(|mode| mode.threads == Some(0)) {
+        return Err(ProcessingError::Message("Background mode needs at least one thread".into()));
+    }
+    TextRules::compile(&config.text_rules).map_err(ProcessingError::Message)?;
+
+    let image_output = &config.image_output;
+    if image_output.jpeg_quality == 0 || image_output.jpeg_quality > 100 {
+        return Err(ProcessingError::Message("JPEG quality must be between 1 and 100".into()));
+    }
+    if image_output.png_compression > 9 {
+        return Err(ProcessingError::Message(
+            "PNG compression level must be between 0 and 9".into(),
+        ));
+    }
+    if image_output.webp_quality > 100 {
+        return Err(ProcessingError::Message("WebP quality must be between 0 and 100".into()));
+    }
+    if let Some(encoding) = &config.encoding {
+        if let Some(crf) = encoding.crf.filter(|crf| *crf > encoding.codec.max_crf()) {
+            return Err(ProcessingError::Message(format!(
+                "CRF must be between 0 and {} for this codec, got {}",
+                encoding.codec.max_crf(),
+                crf
+            )));
+        }
+        if encoding.bitrate_kbps == Some(0) {
+            return Err(ProcessingError::Message("Bitrate must be greater than 0".into()));
+        }
+    }
+    if config.fade_in_secs < 0.0 || config.fade_out_secs < 0.0 {
+        return Err(ProcessingError::Message("Fade durations must not be negative".into()));
+    }
+    if let (Some(start), Some(end)) = (config.start_time, config.end_time) {
+        if end <= start {
+            return Err(ProcessingError::Message(
+                "Watermark end time must be after the start time".into(),
+            ));
+        }
+    }
+
+    if let Some(archive) = &config.archive_copy {
+        if archive.output_dir.trim().is_empty() {
+            return Err(ProcessingError::Message(
+                "Archive copy requires an output folder".into(),
+            ));
+        }
+    }
+
+    if let Some(dark_image) = &config.dark_image_path {
+        if !Path::new(dark_image).exists() {
+            return Err(ProcessingError::Message(format!(
+                "Dark watermark image not found at {dark_image}"
+            )));
+        }
+    }
+
+    if let Some(transform) = &config.jpeg_transform {
+        if !matches!(transform.rotate, 0 | 90 | 180 | 270) {
+            return Err(ProcessingError::Message(format!(
+                "Rotation must be 0, 90, 180 or 270 degrees, got {}",
+                transform.rotate
+            )));
+        }
+        if let Some(crop) = &transform.crop {
+            if crop.width == 0 || crop.height == 0 {
+                return Err(ProcessingError::Message(
+                    "Crop width and height must be greater than 0".into(),
+                ));
+            }
+        }
+    }
+
+    let variant_resizes =
+        config.export_variants.iter().filter_map(|variant| variant.resize.as_ref());
+    for resize in config.resize.iter().chain(variant_resizes) {
+        let sizes = [resize.width, resize.height];
+        if sizes.iter().flatten().any(|size| *size == 0 || *size > MAX_OUTPUT_DIMENSION) {
+            return Err(ProcessingError::Message(format!(
+                "Resize width and height must be between 1 and {} pixels",
+                MAX_OUTPUT_DIMENSION
+            )));
+        }
+        let complete = match resize.mode {
+            ResizeMode::Fit => sizes.iter().any(Option::is_some),
+            ResizeMode::Exact => sizes.iter().all(Option::is_some),
+        };
+        if !complete {
+            return Err(ProcessingError::Message(
+                "Resizing to fit needs a width or height, an exact size needs both".into(),
+            ));
+        }
+    }
+
+    let mut variant_names = HashSet::new();
+    for variant in &config.export_variants {
+        let name = variant.name.trim();
+        if name.is_empty() {
+            return Err(ProcessingError::Message("Export variants need a name".into()));
+        }
+        // Outputs are told apart by name, also on case-insensitive file systems
+        if !variant_names.insert(name.to_lowercase()) {
+            return Err(ProcessingError::Message(format!(
+                "Export variant names must be unique, got {} twice",
+                name
+            )));
+        }
+    }
+
     // Validate custom position if in custom mode
     if let Some(mode) = &config.position_mode {
         if mode == "custom" {
@@ -229,450 +1954,1264 @@ fn validate_config(config: &WatermarkConfig) -> Result<(), ProcessingError> {
         }
     }
 
-    Ok(())
+    Ok(())
+}
+
+fn emit_progress(app: &AppHandle, payload: ProgressPayload) {
+    let _ = app.emit_to("main", "watermark-progress", &payload);
+}
+
+pub(crate) fn build_output_path(
+    output_dir: &Path,
+    input_path: &Path,
+    config: &WatermarkConfig,
+    sequence: Option<u64>,
+) -> PathBuf {
+    let file_stem = input_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("watermarked");
+    // Waveform renders of audio files are videos
+    let is_waveform =
+        is_audio_file(input_path) && config.audio_mode == AudioWatermarkMode::Waveform;
+    let file_type = detect_file_type(input_path).ok().filter(|_| !is_audio_file(input_path));
+    let is_image = file_type == Some(false);
+    let image_format = config.image_output.format.filter(|_| is_image);
+    // Web-optimized videos are always MP4, whatever container they came in
+    let extension = if is_waveform || (config.web_optimized && file_type == Some(true)) {
+        "mp4"
+    } else if let Some(format) = image_format {
+        format.extension()
+    } else {
+        match sniff(input_path) {
+            // Name the output after the real container, not a misleading extension
+            Some(sniffed) if !sniffed.matches_extension(input_path) => sniffed.extensions[0],
+            _ => input_path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("out"),
+        }
+    };
+
+    let file_name = match config.output_name_template.as_deref().filter(|t| !t.trim().is_empty()) {
+        Some(template) => {
+            let expanded = expand_tokens(template, &token_context(input_path, config, sequence));
+            sanitize_file_name(&expanded)
+        }
+        None => format!("{}_watermarked", file_stem),
+    };
+
+    let output_path = output_dir.join(format!("{}.{}", file_name, extension));
+    // Writing over the input would destroy it while FFmpeg is still reading it
+    if is_same_file(&output_path, input_path) {
+        return output_dir.join(format!("{}_watermarked.{}", file_name, extension));
+    }
+    output_path
+}
+
+/// Whether `a` and `b` are the same existing file, however they are spelled:
+/// through symlinks, hard links or in another case on a case-insensitive
+/// filesystem.
+#[cfg(unix)]
+pub(crate) fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_same_file(a: &Path, b: &Path) -> bool {
+    // Canonical Windows paths carry the case the names are stored in
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Fails when any output of `input_path` would be written over the input,
+/// such as an explicit output path or an export variant naming it.
+fn ensure_input_preserved(
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+) -> Result<(), ProcessingError> {
+    match output_paths(output_path, config)
+        .into_iter()
+        .find(|output| is_same_file(output, input_path))
+    {
+        Some(output) => Err(ProcessingError::Message(format!(
+            "The output {} would overwrite the input file",
+            output.display()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Where a file is written: `output_path` itself, or one path per export
+/// variant with the variant name appended to the file name.
+pub(crate) fn output_paths(output_path: &Path, config: &WatermarkConfig) -> Vec<PathBuf> {
+    if config.export_variants.is_empty() {
+        return vec![output_path.to_path_buf()];
+    }
+    let stem = output_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "watermarked".to_string());
+    let extension = output_path.extension().map(|ext| ext.to_string_lossy().into_owned());
+    config
+        .export_variants
+        .iter()
+        .map(|variant| {
+            let mut file_name = format!("{}_{}", stem, sanitize_file_name(variant.name.trim()));
+            if let Some(extension) = &extension {
+                file_name = format!("{}.{}", file_name, extension);
+            }
+            output_path.with_file_name(file_name)
+        })
+        .collect()
+}
+
+/// The outputs a `FileResult` lists in `variant_outputs`.
+fn variant_outputs(outputs: Vec<PathBuf>, config: &WatermarkConfig) -> Vec<PathBuf> {
+    if config.export_variants.is_empty() {
+        Vec::new()
+    } else {
+        outputs
+    }
+}
+
+/// Token values for a file; `sequence` is its zero-based position in the batch.
+fn token_context<'a>(
+    input_path: &'a Path,
+    config: &WatermarkConfig,
+    sequence: Option<u64>,
+) -> TokenContext<'a> {
+    let counter = config.counter_start.saturating_add(sequence.unwrap_or(0));
+    TokenContext::for_file(input_path)
+        .with_counter(counter, config.counter_padding)
+        .with_locale(config.token_locale.as_deref())
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    // Expanded tokens such as {time} can contain characters that are invalid in file names
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn list_post_processors(
+    registry: State<'_, PostProcessorRegistry>,
+) -> Result<Vec<PostProcessorInfo>, AppError> {
+    Ok(registry.list())
+}
+
+#[tauri::command]
+pub async fn list_watermark_sources(
+    registry: State<'_, WatermarkSourceRegistry>,
+) -> Result<Vec<WatermarkSourceInfo>, AppError> {
+    Ok(registry.list())
+}
+
+/// Lists the media files in a dropped or selected folder, along with the
+/// files that cannot be processed and why.
+#[tauri::command]
+pub async fn scan_folder(path: String, recursive: Option<bool>) -> Result<FolderScan, AppError> {
+    let dir = PathBuf::from(&path);
+    if !dir.is_dir() {
+        return Err(AppError::not_found(format!("Folder not found: {}", path)));
+    }
+    Ok(crate::scan::scan_folder(&dir, recursive.unwrap_or(true))?)
+}
+
+#[tauri::command]
+pub async fn open_folder_in_explorer(path: String) -> Result<(), AppError> {
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("explorer")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    }
+    
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        std::process::Command::new("xdg-open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| format!("Failed to open folder: {}", e))?;
+    }
+    
+    Ok(())
+}
+
+/// Drains the files the OS asked the app to open since the last call.
+#[tauri::command]
+pub async fn take_opened_files(
+    opened: State<'_, OpenedFiles>,
+) -> Result<Vec<String>, AppError> {
+    Ok(opened
+        .take()
+        .into_iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Lists bundled and user presets, optionally only those in `category` that
+/// carry every one of `tags`.
+#[tauri::command]
+pub async fn list_presets(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<Vec<PresetMetadata>, AppError> {
+    let filter = PresetFilter {
+        category: category.filter(|category| !category.trim().is_empty()),
+        tags: tags.unwrap_or_default(),
+    };
+    Ok(presets.list(&app, &filter)?)
+}
+
+#[tauri::command]
+pub async fn load_preset(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    preset_id: Option<String>,
+) -> Result<WatermarkConfig, AppError> {
+    let preset_id = preset_id.unwrap_or_else(|| presets.default_id(&app));
+    Ok(presets.load(&app, &preset_id)?.config)
+}
+
+/// Chooses the preset `load_preset` returns without an ID, which the app
+/// starts from on first launch. `None` restores the bundled default.
+#[tauri::command]
+pub async fn set_default_preset(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    preset_id: Option<String>,
+) -> Result<(), AppError> {
+    Ok(presets.set_default(&app, preset_id.as_deref())?)
+}
+
+/// Saves `config` and optional output settings as a user preset, replacing
+/// any user preset with the same ID. With a `base`, only the fields that
+/// differ from that preset are stored. `category` and `tags` organize the
+/// preset list.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn save_preset(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    preset_id: String,
+    name: String,
+    description: String,
+    base: Option<String>,
+    config: WatermarkConfig,
+    output: Option<PresetOutput>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<(), AppError> {
+    validate_config(&config).map_err(AppError::invalid_config)?;
+    if let Some(output) = &output {
+        validate_preset_output(output).map_err(AppError::invalid_config)?;
+    }
+    presets.save(
+        &app,
+        &preset_id,
+        &WatermarkPreset {
+            name,
+            description,
+            base,
+            category: category
+                .map(|category| category.trim().to_string())
+                .filter(|category| !category.is_empty()),
+            tags: tags
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            config,
+            output,
+            assets: Vec::new(),
+        },
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_preset(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    preset_id: String,
+) -> Result<(), AppError> {
+    Ok(presets.delete(&app, &preset_id)?)
+}
+
+/// Writes a preset to a file for sharing. With `embed_assets` the watermark
+/// images travel inside the file.
+#[tauri::command]
+pub async fn export_preset(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    preset_id: String,
+    output_path: String,
+    embed_assets: Option<bool>,
+) -> Result<(), AppError> {
+    let embed_assets = embed_assets.unwrap_or(true);
+    Ok(presets.export(&app, &preset_id, Path::new(&output_path), embed_assets)?)
+}
+
+/// Adds a preset file written by `export_preset` as a new user preset.
+#[tauri::command]
+pub async fn import_preset(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    path: String,
+) -> Result<PresetMetadata, AppError> {
+    let (preset_id, preset) = presets.prepare_import(&app, Path::new(&path))?;
+    let saved = validate_config(&preset.config)
+        .map_err(AppError::invalid_config)
+        .and_then(|_| presets.save(&app, &preset_id, &preset).map_err(AppError::from));
+    if let Err(err) = saved {
+        presets.discard_assets(&app, &preset_id);
+        return Err(err);
+    }
+    Ok(PresetMetadata {
+        id: preset_id,
+        name: preset.name,
+        description: preset.description,
+        source: PresetSource::User,
+        category: preset.category,
+        tags: preset.tags,
+        is_default: false,
+    })
+}
+
+#[tauri::command]
+pub async fn rename_preset(
+    app: AppHandle,
+    presets: State<'_, PresetRepository>,
+    preset_id: String,
+    name: String,
+) -> Result<(), AppError> {
+    Ok(presets.rename(&app, &preset_id, &name)?)
+}
+
+#[tauri::command]
+pub async fn get_telemetry_enabled(
+    app: AppHandle,
+    telemetry: State<'_, Telemetry>,
+) -> Result<bool, AppError> {
+    Ok(telemetry.is_enabled(&app))
+}
+
+/// Turns local usage telemetry on or off. Either way the counters collected
+/// so far are discarded.
+#[tauri::command]
+pub async fn set_telemetry_enabled(
+    app: AppHandle,
+    telemetry: State<'_, Telemetry>,
+    enabled: bool,
+) -> Result<(), AppError> {
+    Ok(telemetry.set_enabled(&app, enabled)?)
+}
+
+/// Writes the collected usage counters as JSON so they can be shared with
+/// the maintainers.
+#[tauri::command]
+pub async fn export_telemetry(
+    app: AppHandle,
+    telemetry: State<'_, Telemetry>,
+    output_path: String,
+) -> Result<(), AppError> {
+    Ok(telemetry.export(&app, Path::new(&output_path))?)
+}
+
+/// Returns the configuration saved by `save_last_config`, or `None` on first
+/// launch.
+#[tauri::command]
+pub async fn load_last_config(app: AppHandle) -> Result<Option<LastConfig>, AppError> {
+    Ok(last_config::load(&app)?)
+}
+
+/// Remembers the complete configuration, and the preset it came from, for
+/// the next launch.
+#[tauri::command]
+pub async fn save_last_config(
+    app: AppHandle,
+    config: WatermarkConfig,
+    preset_id: Option<String>,
+) -> Result<(), AppError> {
+    Ok(last_config::save(&app, &LastConfig { config, preset_id })?)
+}
+
+#[tauri::command]
+pub async fn get_update_channel(
+    app: AppHandle,
+    updates: State<'_, Updates>,
+) -> Result<UpdateChannel, AppError> {
+    Ok(updates.channel(&app))
+}
+
+/// Selects the release channel future update checks use.
+#[tauri::command]
+pub async fn set_update_channel(
+    app: AppHandle,
+    updates: State<'_, Updates>,
+    channel: UpdateChannel,
+) -> Result<(), AppError> {
+    Ok(updates.set_channel(&app, channel)?)
+}
+
+/// Checks the selected channel for a newer release. `None` when the app is
+/// up to date.
+#[tauri::command]
+pub async fn check_for_updates_now(app: AppHandle) -> Result<Option<ReleaseInfo>, AppError> {
+    Ok(updates::check(&app).await?.map(|(_, info)| info))
+}
+
+/// Downloads and installs the newest release of the selected channel, even
+/// when its staged rollout has not reached this install, then restarts.
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> Result<(), AppError> {
+    let Some((update, _)) = updates::check(&app).await? else {
+        return Err(AppError::not_found("No update is available"));
+    };
+    updates::install(&app, &update).await?;
+    app.restart()
+}
+
+#[tauri::command]
+pub async fn get_screenshot_folder(app: AppHandle) -> Result<Option<String>, AppError> {
+    Ok(default_screenshot_dir(&app).map(|dir| dir.to_string_lossy().into_owned()))
+}
+
+/// Starts auto-watermarking new screenshots and screen recordings that appear
+/// in `watch_dir` (the OS screenshot folder by default). Without a config the bundled "screenshot"
+/// preset is used.
+#[tauri::command]
+pub async fn start_hot_folder(
+    app: AppHandle,
+    hot_folder: State<'_, HotFolderState>,
+    presets: State<'_, PresetRepository>,
+    output_dir: String,
+    watch_dir: Option<String>,
+    config: Option<WatermarkConfig>,
+) -> Result<String, AppError> {
+    let watch_dir_path = match watch_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => default_screenshot_dir(&app)
+            .ok_or_else(|| "Failed to resolve the screenshot folder".to_string())?,
+    };
+    if !watch_dir_path.is_dir() {
+        return Err(AppError::not_found(format!(
+            "Folder not found: {}",
+            watch_dir_path.display()
+        )));
+    }
+
+    let output_dir_path = PathBuf::from(&output_dir);
+    // Writing outputs into the watched folder would re-trigger the watcher
+    if output_dir_path == watch_dir_path {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            "Output folder must differ from the watched folder",
+        ));
+    }
+    ensure_output_approved(&app, &output_dir_path)?;
+    std::fs::create_dir_all(&output_dir_path)?;
+
+    let config = match config {
+        Some(config) => config,
+        None => presets.load(&app, "screenshot")?.config,
+    };
+    telemetry::record_usage(&app, "hot_folder", &config);
+    validate_config(&config).map_err(AppError::invalid_config)?;
+
+    hot_folder.start(app, watch_dir_path.clone(), output_dir_path, config);
+    Ok(watch_dir_path.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub async fn stop_hot_folder(
+    hot_folder: State<'_, HotFolderState>,
+) -> Result<Option<String>, AppError> {
+    Ok(hot_folder
+        .stop()
+        .map(|dir| dir.to_string_lossy().into_owned()))
+}
+
+#[tauri::command]
+pub async fn get_hot_folder_status(
+    hot_folder: State<'_, HotFolderState>,
+) -> Result<Option<String>, AppError> {
+    Ok(hot_folder
+        .watch_dir()
+        .map(|dir| dir.to_string_lossy().into_owned()))
+}
+
+// Cache utility functions
+
+/// Bytes read from each end of a file for its content hash.
+const CONTENT_HASH_SAMPLE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Hashes a file's size and its first and last `CONTENT_HASH_SAMPLE_BYTES`,
+/// so copies of a file share cached thumbnails wherever they are and
+/// whatever their modification time.
+fn content_hash(path: &Path) -> Result<String, std::io::Error> {
+    let mut file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut hasher = Xxh3::new();
+    hasher.update(&size.to_le_bytes());
+
+    let mut sample = Vec::new();
+    (&mut file).take(CONTENT_HASH_SAMPLE_BYTES).read_to_end(&mut sample)?;
+    hasher.update(&sample);
+    if size > CONTENT_HASH_SAMPLE_BYTES {
+        // The tail never overlaps the head, for files under two samples long
+        let tail_start = (size - CONTENT_HASH_SAMPLE_BYTES).max(CONTENT_HASH_SAMPLE_BYTES);
+        file.seek(SeekFrom::Start(tail_start))?;
+        sample.clear();
+        file.take(CONTENT_HASH_SAMPLE_BYTES).read_to_end(&mut sample)?;
+        hasher.update(&sample);
+    }
+    Ok(format!("{:016x}", hasher.digest()))
+}
+
+/// Cache key of one thumbnail of a file; `variant` tells apart the
+/// thumbnails of the same content.
+fn generate_cache_key(content_hash: &str, variant: &str) -> String {
+    format!("{:016x}", xxh3_64(format!("{}{}", content_hash, variant).as_bytes()))
 }
 
-fn emit_progress(app: &AppHandle, payload: ProgressPayload) {
-    let _ = app.emit_to("main", "watermark-progress", &payload);
+fn get_file_mtime(path: &Path) -> Result<u64, std::io::Error> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata.modified()?;
+    let duration = modified.duration_since(UNIX_EPOCH)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    Ok(duration.as_secs())
 }
 
-fn build_output_path(output_dir: &Path, input_path: &Path) -> PathBuf {
-    let file_stem = input_path
-        .file_stem()
-        .and_then(|stem| stem.to_str())
-        .unwrap_or("watermarked");
-    let extension = input_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("out");
+/// Returns the cached thumbnail for `cache_key` if its file still exists,
+/// refreshing the entry's last access time.
+fn lookup_cached_thumbnail(app: &AppHandle, cache_key: &str) -> Option<PathBuf> {
+    app.state::<CacheDatabase>().lookup_thumbnail(app, cache_key)
+}
 
-    output_dir.join(format!("{}_watermarked.{}", file_stem, extension))
+/// Path a new cached thumbnail should be written to.
+fn thumbnail_output_path(
+    app: &AppHandle,
+    cache_key: &str,
+    extension: &str,
+) -> Result<PathBuf, String> {
+    let thumbnail_dir = cache_db::thumbnail_dir(&cache_db::load_settings(app));
+    std::fs::create_dir_all(&thumbnail_dir)
+        .map_err(|e| format!("Failed to create thumbnail directory: {}", e))?;
+
+    Ok(thumbnail_dir.join(format!("{}.{}", cache_key, extension)))
 }
 
-#[tauri::command]
-pub async fn open_folder_in_explorer(path: String) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        std::process::Command::new("explorer")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
-    }
-    
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
-    }
-    
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("xdg-open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open folder: {}", e))?;
+/// Records a freshly generated thumbnail in the cache and evicts old entries.
+fn store_cached_thumbnail(
+    app: &AppHandle,
+    cache_key: String,
+    video_path: &str,
+    video_mtime: u64,
+    thumbnail_path: &Path,
+) {
+    app.state::<CacheDatabase>().store_thumbnail(
+        app,
+        &cache_key,
+        video_path,
+        video_mtime,
+        thumbnail_path,
+    );
+}
+
+fn thumbnail_error(err: FfmpegError) -> AppError {
+    let err = AppError::from(err);
+    AppError {
+        message: format!("Failed to extract thumbnail: {}", err.message),
+        ..err
     }
-    
-    Ok(())
 }
 
+/// Extracts a video frame as a cached JPEG thumbnail: the first frame by
+/// default, or the one at `timestamp` seconds or `timestamp_percent` of the
+/// duration, since first frames are often black.
 #[tauri::command]
-pub async fn list_presets(app: AppHandle) -> Result<Vec<PresetMetadata>, String> {
-    use tauri::Manager;
-    
-    let presets_path = app
-        .path()
-        .resolve("resources/presets", tauri::path::BaseDirectory::Resource)
-        .map_err(|e| format!("Failed to resolve presets directory: {}", e))?;
-
-    let mut presets = Vec::new();
+pub async fn extract_video_thumbnail(
+    app: AppHandle,
+    video_path: String,
+    timestamp: Option<f64>,
+    timestamp_percent: Option<f64>,
+) -> Result<String, AppError> {
+    let timestamp = match (timestamp, timestamp_percent) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::invalid_config(
+                "Pass either a timestamp or a percentage of the duration, not both",
+            ));
+        }
+        (Some(seconds), None) => Some(seconds.max(0.0)),
+        (None, Some(percent)) => {
+            let duration = probe_duration(&app, Path::new(&video_path))
+                .await
+                .map_err(thumbnail_error)?;
+            Some(duration * percent.clamp(0.0, 100.0) / 100.0)
+        }
+        (None, None) => None,
+    };
 
-    let entries = std::fs::read_dir(&presets_path)
-        .map_err(|e| format!("Failed to read presets directory: {}", e))?;
+    let thumbnail_path = video_thumbnail(&app, &video_path, timestamp).await?;
+    Ok(thumbnail_path.to_string_lossy().into_owned())
+}
 
-    for entry in entries {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(e) => {
-                eprintln!("Failed to read directory entry: {}", e);
-                continue;
-            }
-        };
+/// Returns the cached thumbnail of the frame at `timestamp` seconds, or of the
+/// first frame, extracting it on a cache miss.
+pub(crate) async fn video_thumbnail(
+    app: &AppHandle,
+    video_path: &str,
+    timestamp: Option<f64>,
+) -> Result<PathBuf, AppError> {
+    let video_path_buf = PathBuf::from(video_path);
 
-        let path = entry.path();
-        
-        // Only process .json files
-        if path.extension().and_then(|s| s.to_str()) != Some("json") {
-            continue;
-        }
+    // Get video file mtime
+    let video_mtime = get_file_mtime(&video_path_buf)
+        .map_err(|e| format!("Failed to get video file modification time: {}", e))?;
+    let video_hash = content_hash(&video_path_buf)
+        .map_err(|e| format!("Failed to read video file: {}", e))?;
 
-        // Get the preset ID from filename
-        let id = match path.file_stem().and_then(|s| s.to_str()) {
-            Some(s) => s.to_string(),
-            None => continue,
-        };
+    // Generate cache key; each timestamp is cached separately
+    let cache_key = match timestamp {
+        Some(timestamp) => generate_cache_key(&video_hash, &format!("#t-{:.3}", timestamp)),
+        None => generate_cache_key(&video_hash, ""),
+    };
 
-        // Read and parse the preset file
-        match std::fs::read_to_string(&path) {
-            Ok(content) => {
-                match serde_json::from_str::<WatermarkPreset>(&content) {
-                    Ok(preset) => {
-                        presets.push(PresetMetadata {
-                            id,
-                            name: preset.name,
-                            description: preset.description,
-                        });
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to parse preset {}: {}", path.display(), e);
-                        continue;
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("Failed to read preset file {}: {}", path.display(), e);
-                continue;
-            }
-        }
+    // Return cached thumbnail path if present
+    if let Some(thumbnail_path) = lookup_cached_thumbnail(app, &cache_key) {
+        return Ok(thumbnail_path);
     }
 
-    // Sort presets alphabetically by name
-    presets.sort_by(|a, b| a.name.cmp(&b.name));
+    // Cache miss - extract thumbnail
+    let output_path = thumbnail_output_path(app, &cache_key, "jpg")?;
+
+    crate::ffmpeg::extract_video_thumbnail(app, &video_path_buf, &output_path, timestamp)
+        .await
+        .map_err(thumbnail_error)?;
 
-    Ok(presets)
+    store_cached_thumbnail(app, cache_key, video_path, video_mtime, &output_path);
+    Ok(output_path)
 }
 
+/// Returns a cached JPEG of an image no larger than `IMAGE_THUMBNAIL_SIZE` on
+/// either side, turned upright, so file grids do not load full-size photos.
 #[tauri::command]
-pub async fn load_preset(app: AppHandle, preset_id: String) -> Result<WatermarkConfig, String> {
-    use tauri::Manager;
-    
-    // Validate preset_id to prevent path traversal
-    if !preset_id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
-        return Err("Invalid preset ID".to_string());
+pub async fn extract_image_thumbnail(
+    app: AppHandle,
+    image_path: String,
+) -> Result<String, AppError> {
+    let thumbnail_path = image_thumbnail(&app, &image_path).await?;
+    Ok(thumbnail_path.to_string_lossy().into_owned())
+}
+
+/// Returns the cached downscaled thumbnail of an image, rendering it on a
+/// cache miss.
+pub(crate) async fn image_thumbnail(
+    app: &AppHandle,
+    image_path: &str,
+) -> Result<PathBuf, AppError> {
+    let image_path_buf = PathBuf::from(image_path);
+
+    let image_mtime = get_file_mtime(&image_path_buf)
+        .map_err(|e| format!("Failed to get image file modification time: {}", e))?;
+    let image_hash = content_hash(&image_path_buf)
+        .map_err(|e| format!("Failed to read image file: {}", e))?;
+    let cache_key = generate_cache_key(&image_hash, "#image");
+
+    if let Some(thumbnail_path) = lookup_cached_thumbnail(app, &cache_key) {
+        return Ok(thumbnail_path);
     }
 
-    let preset_filename = format!("{}.json", preset_id);
-    let preset_path = app
-        .path()
-        .resolve(
-            format!("resources/presets/{}", preset_filename),
-            tauri::path::BaseDirectory::Resource,
-        )
-        .map_err(|e| format!("Failed to resolve preset path: {}", e))?;
+    let output_path = thumbnail_output_path(app, &cache_key, "jpg")?;
+    let output = output_path.clone();
+    tokio::task::spawn_blocking(move || render_image_thumbnail(&image_path_buf, &output))
+        .await
+        .map_err(|e| format!("Thumbnail task panicked: {}", e))?
+        .map_err(|e| format!("Failed to extract thumbnail: {}", e))?;
 
-    // Read the preset file
-    let content = std::fs::read_to_string(&preset_path)
-        .map_err(|e| format!("Preset not found: {}", e))?;
+    store_cached_thumbnail(app, cache_key, image_path, image_mtime, &output_path);
+    Ok(output_path)
+}
 
-    // Parse the preset
-    let preset: WatermarkPreset = serde_json::from_str(&content)
-        .map_err(|e| format!("Invalid preset format: {}", e))?;
+/// Generates thumbnails for `files` in the background, a few at a time, and
+/// emits `thumbnail-ready` as each one finishes. Returns immediately.
+#[tauri::command]
+pub async fn prefetch_thumbnails(app: AppHandle, files: Vec<FileItem>) -> Result<(), AppError> {
+    crate::prefetch::prefetch_thumbnails(&app, files);
+    Ok(())
+}
 
-    Ok(preset.config)
+/// Decodes `image_path`, applies its EXIF orientation and writes a downscaled
+/// JPEG to `output_path`.
+fn render_image_thumbnail(image_path: &Path, output_path: &Path) -> Result<(), String> {
+    let mut decoder = image::ImageReader::open(image_path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    // Cameras store portrait photos sideways with an orientation tag
+    let orientation = image::ImageDecoder::orientation(&mut decoder)
+        .unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut image = image::DynamicImage::from_decoder(decoder).map_err(|e| e.to_string())?;
+    image.apply_orientation(orientation);
+
+    if image.width().max(image.height()) > IMAGE_THUMBNAIL_SIZE {
+        image = image.thumbnail(IMAGE_THUMBNAIL_SIZE, IMAGE_THUMBNAIL_SIZE);
+    }
+    // JPEG has no alpha channel
+    image
+        .into_rgb8()
+        .save_with_format(output_path, image::ImageFormat::Jpeg)
+        .map_err(|e| e.to_string())
 }
 
-// Cache utility functions
+/// Renders a contact sheet of evenly spaced frames with burned-in timestamps,
+/// so users can pick a watermark time window visually. Cached like thumbnails.
+#[tauri::command]
+pub async fn extract_scrub_strip(
+    app: AppHandle,
+    video_path: String,
+    frame_count: Option<u32>,
+) -> Result<ScrubStrip, AppError> {
+    let frame_count = frame_count.unwrap_or(10).clamp(2, 30);
+    let video_path_buf = PathBuf::from(&video_path);
 
-fn get_cache_file_path() -> Result<PathBuf, std::io::Error> {
-    let cache_dir = std::env::temp_dir().join("bulk-watermark-thumbnails");
-    std::fs::create_dir_all(&cache_dir)?;
-    Ok(cache_dir.join("cache.json"))
-}
+    let video_mtime = get_file_mtime(&video_path_buf)
+        .map_err(|e| format!("Failed to get video file modification time: {}", e))?;
+    let duration = probe_duration(&app, &video_path_buf)
+        .await
+        .map_err(thumbnail_error)?;
+
+    let columns = frame_count.min(5);
+    let rows = frame_count.div_ceil(columns);
+    let interval = duration / frame_count as f64;
+    let frames = (0..frame_count)
+        .map(|index| ScrubFrame {
+            index,
+            timestamp: index as f64 * interval,
+        })
+        .collect();
 
-fn load_thumbnail_cache() -> ThumbnailCache {
-    let cache_path = match get_cache_file_path() {
-        Ok(path) => path,
-        Err(e) => {
-            eprintln!("Failed to get cache file path: {}", e);
-            return ThumbnailCache::default();
+    let video_hash = content_hash(&video_path_buf)
+        .map_err(|e| format!("Failed to read video file: {}", e))?;
+    let cache_key = generate_cache_key(&video_hash, &format!("#scrub-{}", frame_count));
+    let image_path = match lookup_cached_thumbnail(&app, &cache_key) {
+        Some(path) => path,
+        None => {
+            let output_path = thumbnail_output_path(&app, &cache_key, "jpg")?;
+            crate::ffmpeg::extract_scrub_strip(
+                &app,
+                &video_path_buf,
+                &output_path,
+                frame_count,
+                duration,
+                columns,
+                rows,
+            )
+            .await
+            .map_err(thumbnail_error)?;
+            store_cached_thumbnail(&app, cache_key, &video_path, video_mtime, &output_path);
+            output_path
         }
     };
 
-    if !cache_path.exists() {
-        return ThumbnailCache {
-            entries: std::collections::HashMap::new(),
-            version: 1,
+    Ok(ScrubStrip {
+        image_path: image_path.to_string_lossy().into_owned(),
+        duration,
+        columns,
+        rows,
+        frames,
+    })
+}
+
+/// Extracts `frame_count` small frames spread across a video, one from the
+/// middle of each equal part, for a scrub-style preview in the file list.
+/// With `tiled` they come as a single contact sheet row. Cached like
+/// thumbnails.
+#[tauri::command]
+pub async fn extract_video_filmstrip(
+    app: AppHandle,
+    video_path: String,
+    frame_count: Option<u32>,
+    tiled: Option<bool>,
+) -> Result<VideoFilmstrip, AppError> {
+    let frame_count = frame_count.unwrap_or(8).clamp(2, 30);
+    let video_path_buf = PathBuf::from(&video_path);
+
+    let video_mtime = get_file_mtime(&video_path_buf)
+        .map_err(|e| format!("Failed to get video file modification time: {}", e))?;
+    let duration = probe_duration(&app, &video_path_buf)
+        .await
+        .map_err(thumbnail_error)?;
+
+    let interval = duration / frame_count as f64;
+    let frames: Vec<ScrubFrame> = (0..frame_count)
+        .map(|index| ScrubFrame {
+            index,
+            timestamp: (index as f64 + 0.5) * interval,
+        })
+        .collect();
+
+    let video_hash = content_hash(&video_path_buf)
+        .map_err(|e| format!("Failed to read video file: {}", e))?;
+    if tiled.unwrap_or(false) {
+        let cache_key = generate_cache_key(&video_hash, &format!("#filmstrip-{}", frame_count));
+        let sheet_path = match lookup_cached_thumbnail(&app, &cache_key) {
+            Some(path) => path,
+            None => {
+                let output_path = thumbnail_output_path(&app, &cache_key, "jpg")?;
+                crate::ffmpeg::extract_filmstrip_sheet(
+                    &app,
+                    &video_path_buf,
+                    &output_path,
+                    frame_count,
+                    duration,
+                )
+                .await
+                .map_err(thumbnail_error)?;
+                store_cached_thumbnail(&app, cache_key, &video_path, video_mtime, &output_path);
+                output_path
+            }
         };
+        return Ok(VideoFilmstrip {
+            duration,
+            frames,
+            frame_paths: Vec::new(),
+            sheet_path: Some(sheet_path.to_string_lossy().into_owned()),
+        });
     }
 
-    match std::fs::read_to_string(&cache_path) {
-        Ok(content) => {
-            match serde_json::from_str::<ThumbnailCache>(&content) {
-                Ok(mut cache) => {
-                    // Validate that cached thumbnail files still exist, remove stale entries
-                    cache.entries.retain(|_, entry| entry.thumbnail_path.exists());
-                    cache
-                }
-                Err(e) => {
-                    eprintln!("Failed to parse cache file: {}", e);
-                    ThumbnailCache {
-                        entries: std::collections::HashMap::new(),
-                        version: 1,
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to read cache file: {}", e);
-            ThumbnailCache {
-                entries: std::collections::HashMap::new(),
-                version: 1,
+    let mut frame_paths = Vec::with_capacity(frames.len());
+    for frame in &frames {
+        let cache_key = generate_cache_key(
+            &video_hash,
+            &format!("#filmstrip-{}-{}", frame_count, frame.index),
+        );
+        let frame_path = match lookup_cached_thumbnail(&app, &cache_key) {
+            Some(path) => path,
+            None => {
+                let output_path = thumbnail_output_path(&app, &cache_key, "jpg")?;
+                crate::ffmpeg::extract_filmstrip_frame(
+                    &app,
+                    &video_path_buf,
+                    &output_path,
+                    frame.timestamp,
+                )
+                .await
+                .map_err(thumbnail_error)?;
+                store_cached_thumbnail(&app, cache_key, &video_path, video_mtime, &output_path);
+                output_path
             }
-        }
+        };
+        frame_paths.push(frame_path.to_string_lossy().into_owned());
     }
-}
 
-fn save_thumbnail_cache(cache: &ThumbnailCache) -> Result<(), std::io::Error> {
-    let cache_path = get_cache_file_path()?;
-    let content = serde_json::to_string_pretty(cache)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    std::fs::write(cache_path, content)?;
-    Ok(())
+    Ok(VideoFilmstrip {
+        duration,
+        frames,
+        frame_paths,
+        sheet_path: None,
+    })
 }
 
-fn generate_cache_key(video_path: &str, mtime: u64) -> String {
-    let mut hasher = DefaultHasher::new();
-    format!("{}{}", video_path, mtime).hash(&mut hasher);
-    format!("{:x}", hasher.finish())
-}
+/// Renders a handful of frames spread across the video with the watermark
+/// applied, so time-based watermark motion can be checked before a full encode.
+#[tauri::command]
+pub async fn render_watermark_frames(
+    app: AppHandle,
+    video_path: String,
+    config: WatermarkConfig,
+    frame_count: Option<u32>,
+) -> Result<Vec<PreviewFrame>, AppError> {
+    validate_config(&config).map_err(AppError::invalid_config)?;
+
+    let frame_count = frame_count.unwrap_or(8).clamp(5, 10);
+    let input = PathBuf::from(&video_path);
+    if !detect_file_type(&input)? {
+        return Err(AppError::new(
+            ErrorCode::UnsupportedFormat,
+            "Frame previews are only available for videos",
+        ));
+    }
 
-fn get_file_mtime(path: &Path) -> Result<u64, std::io::Error> {
-    let metadata = std::fs::metadata(path)?;
-    let modified = metadata.modified()?;
-    let duration = modified.duration_since(UNIX_EPOCH)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    Ok(duration.as_secs())
-}
+    let duration = probe_duration(&app, &input)
+        .await?;
 
-fn evict_lru_entries(cache: &mut ThumbnailCache, max_entries: usize, max_size_bytes: u64) {
-    // Calculate total size
-    let total_size: u64 = cache.entries.values().map(|e| e.file_size).sum();
-    
-    // Check if eviction is needed
-    if cache.entries.len() <= max_entries && total_size <= max_size_bytes {
-        return;
+    let mut frame_config = config.clone();
+    frame_config.apply_schedule(Some(duration));
+    if matches!(frame_config.watermark_type, WatermarkType::Text) {
+        frame_config.text = expand_tokens(&config.text, &token_context(&input, &config, None));
+    }
+    if frame_config.font_size_mode == FontSizeMode::PercentHeight {
+        frame_config.font_size = resolve_relative_font_size(&app, &input, &frame_config)
+            .await?;
     }
 
-    // Collect owned keys and timestamps sorted by last_accessed (oldest first)
-    let mut entries: Vec<_> = cache.entries
-        .iter()
-        .map(|(key, entry)| (key.clone(), entry.last_accessed, entry.thumbnail_path.clone(), entry.file_size))
-        .collect();
-    entries.sort_by_key(|(_, last_accessed, _, _)| *last_accessed);
-
-    // Remove oldest entries until under limits
-    let mut current_size = total_size;
-    let mut current_count = cache.entries.len();
+    let preview_dir = std::env::temp_dir().join("bulk-watermark-previews");
+    std::fs::create_dir_all(&preview_dir)
+        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
 
-    for (key, _, thumbnail_path, file_size) in entries {
-        if current_count <= max_entries && current_size <= max_size_bytes {
-            break;
-        }
+    let mut frames = Vec::with_capacity(frame_count as usize);
+    for index in 0..frame_count {
+        // Sample the middle of each segment so the last frame is not past the end
+        let timestamp = (index as f64 + 0.5) * duration / frame_count as f64;
+        let output_path = preview_dir.join(format!("{}_{:02}.jpg", run_id, index));
 
-        // Delete the thumbnail file
-        if thumbnail_path.exists() {
-            if let Err(e) = std::fs::remove_file(&thumbnail_path) {
-                eprintln!("Failed to delete thumbnail {}: {}", thumbnail_path.display(), e);
-            }
-        }
+        let args = build_preview_frame_command(&app, &input, &output_path, &frame_config, timestamp)?;
+        spawn_ffmpeg(&app, args).await?;
 
-        // Remove from cache
-        cache.entries.remove(&key);
-        current_size = current_size.saturating_sub(file_size);
-        current_count -= 1;
+        frames.push(PreviewFrame {
+            timestamp,
+            image_path: output_path.to_string_lossy().into_owned(),
+        });
     }
+
+    Ok(frames)
 }
 
+/// Watermarks the first `duration_secs` seconds (5 by default, at most 30) of
+/// a video at reduced resolution, so position and opacity can be checked on
+/// moving footage without processing the whole file.
 #[tauri::command]
-pub async fn extract_video_thumbnail(
+pub async fn render_video_preview(
     app: AppHandle,
     video_path: String,
-) -> Result<String, String> {
-    // Convert video path to PathBuf
-    let video_path_buf = PathBuf::from(&video_path);
-
-    // Get video file mtime
-    let video_mtime = get_file_mtime(&video_path_buf)
-        .map_err(|e| format!("Failed to get video file modification time: {}", e))?;
+    config: WatermarkConfig,
+    duration_secs: Option<f64>,
+    max_height: Option<u32>,
+) -> Result<PreviewClip, AppError> {
+    validate_config(&config).map_err(AppError::invalid_config)?;
+
+    let input = PathBuf::from(&video_path);
+    if is_audio_file(&input) || !detect_file_type(&input)? {
+        return Err(AppError::new(
+            ErrorCode::UnsupportedFormat,
+            "Clip previews are only available for videos",
+        ));
+    }
+    let video_duration = probe_duration(&app, &input).await?;
+    let duration = duration_secs
+        .unwrap_or(PREVIEW_CLIP_SECS)
+        .clamp(1.0, MAX_PREVIEW_CLIP_SECS)
+        .min(video_duration);
+    let max_height = max_height.unwrap_or(PREVIEW_CLIP_HEIGHT).max(2);
+
+    let mut clip_config = config.clone();
+    clip_config.hardware_acceleration = HardwareAcceleration::Off;
+    if matches!(clip_config.watermark_type, WatermarkType::Text) {
+        clip_config.text = expand_tokens(&config.text, &token_context(&input, &config, None));
+    }
+    prepare_file_config(&app, &input, true, &mut clip_config).await?;
 
-    // Generate cache key
-    let cache_key = generate_cache_key(&video_path, video_mtime);
+    let preview_dir = std::env::temp_dir().join("bulk-watermark-previews");
+    std::fs::create_dir_all(&preview_dir)
+        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let output_path = preview_dir.join(format!("{}_clip.mp4", run_id));
 
-    // Load cache
-    let mut cache = load_thumbnail_cache();
+    let args =
+        build_preview_clip_command(&app, &input, &output_path, &clip_config, duration, max_height)?;
+    spawn_ffmpeg(&app, args).await?;
 
-    // Check cache for existing entry
-    if let Some(entry) = cache.entries.get(&cache_key) {
-        if entry.thumbnail_path.exists() {
-            // Clone the thumbnail path before updating the cache
-            let thumbnail_path = entry.thumbnail_path.clone();
-            
-            // Update last_accessed timestamp
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            
-            // Get mutable reference and update (now entry reference is dropped)
-            if let Some(entry_mut) = cache.entries.get_mut(&cache_key) {
-                entry_mut.last_accessed = now;
-            }
+    Ok(PreviewClip {
+        video_path: output_path.to_string_lossy().into_owned(),
+        duration,
+    })
+}
 
-            // Save updated cache (log but don't fail on error)
-            if let Err(e) = save_thumbnail_cache(&cache) {
-                eprintln!("Failed to save cache after access update: {}", e);
-            }
+/// Renders the frame at `timestamp` seconds (the middle of a video by default)
+/// with and without the watermark, after the same per-file preparation as a
+/// real run, so the result can be compared before starting a long batch. With
+/// `side_by_side` both frames are also joined into one image.
+#[tauri::command]
+pub async fn render_comparison_preview(
+    app: AppHandle,
+    input_path: String,
+    config: WatermarkConfig,
+    timestamp: Option<f64>,
+    side_by_side: Option<bool>,
+) -> Result<ComparisonPreview, AppError> {
+    validate_config(&config).map_err(AppError::invalid_config)?;
 
-            // Return cached thumbnail path
-            return Ok(thumbnail_path.to_string_lossy().into_owned());
-        } else {
-            // Thumbnail file missing, remove stale entry
-            cache.entries.remove(&cache_key);
-        }
+    let input = PathBuf::from(&input_path);
+    if is_audio_file(&input) {
+        return Err(AppError::new(
+            ErrorCode::UnsupportedFormat,
+            "Comparison previews are only available for images and videos",
+        ));
     }
+    let is_video = detect_file_type(&input)?;
+    let timestamp = if is_video {
+        let duration = probe_duration(&app, &input).await?;
+        timestamp.map_or(duration / 2.0, |timestamp| timestamp.clamp(0.0, duration))
+    } else {
+        0.0
+    };
 
-    // Cache miss - extract thumbnail
-    let temp_dir = std::env::temp_dir().join("bulk-watermark-thumbnails");
-    std::fs::create_dir_all(&temp_dir)
-        .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    let mut file_config = config.clone();
+    if matches!(file_config.watermark_type, WatermarkType::Text) {
+        file_config.text = expand_tokens(&config.text, &token_context(&input, &config, None));
+    }
+    prepare_file_config(&app, &input, is_video, &mut file_config).await?;
 
-    // Generate thumbnail filename using cache key
-    let thumbnail_filename = format!("{}.jpg", cache_key);
-    let output_path = temp_dir.join(thumbnail_filename);
+    let preview_dir = std::env::temp_dir().join("bulk-watermark-previews");
+    std::fs::create_dir_all(&preview_dir)
+        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
+    let run_id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let original_path = preview_dir.join(format!("{}_original.jpg", run_id));
+    let watermarked_path = preview_dir.join(format!("{}_watermarked.jpg", run_id));
+
+    let args = build_original_frame_command(&app, &input, &original_path, timestamp)?;
+    spawn_ffmpeg(&app, args).await?;
+    let args =
+        build_preview_frame_command(&app, &input, &watermarked_path, &file_config, timestamp)?;
+    spawn_ffmpeg(&app, args).await?;
+
+    let composite_path = if side_by_side.unwrap_or(false) {
+        let composite_path = preview_dir.join(format!("{}_comparison.jpg", run_id));
+        let args =
+            build_side_by_side_command(&app, &original_path, &watermarked_path, &composite_path)?;
+        spawn_ffmpeg(&app, args).await?;
+        Some(composite_path.to_string_lossy().into_owned())
+    } else {
+        None
+    };
 
-    // Extract the thumbnail using FFmpeg
-    match crate::ffmpeg::extract_video_thumbnail(&app, &video_path_buf, &output_path).await {
-        Ok(_) => {
-            // Get thumbnail file size
-            let file_size = std::fs::metadata(&output_path)
-                .map(|m| m.len())
-                .unwrap_or(0);
+    Ok(ComparisonPreview {
+        timestamp,
+        original_path: original_path.to_string_lossy().into_owned(),
+        watermarked_path: watermarked_path.to_string_lossy().into_owned(),
+        composite_path,
+    })
+}
 
-            // Create cache entry
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
+/// Reads the dimensions, duration, codecs, frame rate, rotation and audio
+/// streams of a media file with ffprobe.
+#[tauri::command]
+pub async fn get_media_info(app: AppHandle, path: String) -> Result<MediaInfo, AppError> {
+    let path = PathBuf::from(&path);
+    if !path.is_file() {
+        return Err(AppError::not_found(format!("File not found: {}", path.display())));
+    }
+    Ok(probe_media_info(&app, &path).await?)
+}
 
-            let cache_entry = ThumbnailCacheEntry {
-                video_path: video_path.clone(),
-                video_mtime,
-                thumbnail_path: output_path.clone(),
-                created_at: now,
-                last_accessed: now,
-                file_size,
-            };
+/// Returns the FFmpeg arguments a single-pass encode of `input_path` would
+/// use, after the same per-file preparation as a real run, so filter issues
+/// can be diagnosed and the command re-run by hand.
+#[tauri::command]
+pub async fn preview_ffmpeg_command(
+    app: AppHandle,
+    input_path: String,
+    output_path: String,
+    config: WatermarkConfig,
+) -> Result<FfmpegCommandPreview, AppError> {
+    validate_config(&config).map_err(AppError::invalid_config)?;
 
-            // Add to cache
-            cache.entries.insert(cache_key, cache_entry);
+    let input = PathBuf::from(&input_path);
+    let output = PathBuf::from(&output_path);
+    if is_audio_file(&input) {
+        return Err(AppError::new(
+            ErrorCode::UnsupportedFormat,
+            "Audio files are branded in several FFmpeg passes and have no single command",
+        ));
+    }
+    let is_video = detect_file_type(&input)?;
 
-            // Evict LRU entries if needed (100 entries max, 500MB max)
-            evict_lru_entries(&mut cache, 100, 500 * 1024 * 1024);
+    let mut file_config = config.clone();
+    if matches!(file_config.watermark_type, WatermarkType::Text) {
+        file_config.text = expand_tokens(&config.text, &token_context(&input, &config, None));
+    }
+    prepare_file_config(&app, &input, is_video, &mut file_config).await?;
+
+    let program = get_ffmpeg_sidecar_path(&app)?;
+    let args = build_ffmpeg_command(&app, &input, &output, &file_config, is_video)?;
+    Ok(FfmpegCommandPreview {
+        command_line: format_command_line(&program, &args),
+        program,
+        args,
+    })
+}
 
-            // Save cache (log but don't fail on error)
-            if let Err(e) = save_thumbnail_cache(&cache) {
-                eprintln!("Failed to save cache: {}", e);
-            }
+/// Suggests a slightly moved watermark position that avoids burned-in
+/// subtitles or channel logos, judged by edge density in frames sampled
+/// across the video (or the image itself).
+#[tauri::command]
+pub async fn nudge_watermark_position(
+    app: AppHandle,
+    input_path: String,
+    config: WatermarkConfig,
+) -> Result<PositionNudge, AppError> {
+    validate_config(&config).map_err(AppError::invalid_config)?;
 
-            Ok(output_path.to_string_lossy().into_owned())
+    let input = PathBuf::from(&input_path);
+    let grids = if detect_file_type(&input)? {
+        let duration = probe_duration(&app, &input).await?;
+        let mut grids = Vec::with_capacity(NUDGE_SAMPLE_FRAMES as usize);
+        for index in 0..NUDGE_SAMPLE_FRAMES {
+            let timestamp = (index as f64 + 0.5) * duration / NUDGE_SAMPLE_FRAMES as f64;
+            grids.push(sample_luma_grid_at(&app, &input, timestamp).await?);
         }
-        Err(FfmpegError::MissingBinary(msg)) => Err(format!("FFmpeg not found: {}", msg)),
-        Err(FfmpegError::UnsupportedFormat(msg)) => Err(format!("Unsupported format: {}", msg)),
-        Err(e) => Err(format!("Failed to extract thumbnail: {}", e)),
-    }
+        grids
+    } else {
+        vec![sample_luma_grid(&app, &input, false).await?]
+    };
+
+    Ok(nudge_position(&config, &grids))
 }
 
 #[tauri::command]
 pub async fn cleanup_thumbnail_cache(
+    app: AppHandle,
     max_age_days: Option<u32>,
-) -> Result<String, String> {
-    // Get temp directory path
-    let temp_dir = std::env::temp_dir().join("bulk-watermark-thumbnails");
+) -> Result<String, AppError> {
+    // Get thumbnail directory path
+    let settings = cache_db::load_settings(&app);
+    let temp_dir = cache_db::thumbnail_dir(&settings);
     if !temp_dir.exists() {
         return Ok("No thumbnails to clean up.".to_string());
     }
 
-    // Load cache
-    let mut cache = load_thumbnail_cache();
-
-    // Determine cutoff timestamp (default: 7 days)
-    let max_age = max_age_days.unwrap_or(7);
+    // Determine cutoff timestamp (default: the configured retention)
+    let max_age = max_age_days.unwrap_or(settings.retention_days);
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
         .unwrap_or(0);
     let cutoff_timestamp = now.saturating_sub((max_age as u64) * 24 * 60 * 60);
 
+    // Remove old entries from cache
+    let cache = app.state::<CacheDatabase>();
+    let (mut cleaned_count, mut freed_bytes) =
+        cache.remove_thumbnails_before(&app, cutoff_timestamp)?;
+    let cached_paths = cache.thumbnail_paths(&app)?;
+    let (orphaned_count, orphaned_bytes) = remove_orphaned_thumbnails(&temp_dir, &cached_paths);
+    cleaned_count += orphaned_count;
+    freed_bytes += orphaned_bytes;
+
+    let freed_mb = freed_bytes as f64 / (1024.0 * 1024.0);
+    Ok(format!("Cleaned up {} thumbnails, freed {:.2} MB", cleaned_count, freed_mb))
+}
+
+/// Deletes every cached thumbnail right away, returning the bytes freed.
+#[tauri::command]
+pub async fn clear_thumbnail_cache(
+    app: AppHandle,
+    cache: State<'_, CacheDatabase>,
+) -> Result<u64, AppError> {
+    let (_, freed_bytes) = cache.remove_thumbnails_before(&app, u64::MAX)?;
+    let thumbnail_dir = cache_db::thumbnail_dir(&cache_db::load_settings(&app));
+    let (_, orphaned_bytes) =
+        remove_orphaned_thumbnails(&thumbnail_dir, &cache.thumbnail_paths(&app)?);
+    Ok(freed_bytes + orphaned_bytes)
+}
+
+/// Deletes the thumbnails in `thumbnail_dir` the cache has no entry for,
+/// returning how many were deleted and their size.
+fn remove_orphaned_thumbnails(
+    thumbnail_dir: &Path,
+    cached_paths: &HashSet<PathBuf>,
+) -> (usize, u64) {
     let mut cleaned_count = 0;
     let mut freed_bytes = 0u64;
+    let Ok(entries) = std::fs::read_dir(thumbnail_dir) else {
+        return (0, 0);
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
 
-    // Remove old entries from cache
-    let keys_to_remove: Vec<_> = cache
-        .entries
-        .iter()
-        .filter(|(_, entry)| {
-            // Remove if older than cutoff or if file doesn't exist
-            entry.created_at < cutoff_timestamp || !entry.thumbnail_path.exists()
-        })
-        .map(|(key, _)| key.clone())
-        .collect();
+        // Check if this file is in the cache
+        let is_orphaned = !cached_paths.contains(&path);
 
-    for key in keys_to_remove {
-        if let Some(entry) = cache.entries.remove(&key) {
-            // Delete the thumbnail file if it exists
-            if entry.thumbnail_path.exists() {
-                match std::fs::remove_file(&entry.thumbnail_path) {
+        if is_orphaned && path.extension().and_then(|e| e.to_str()) == Some("jpg") {
+            if let Ok(metadata) = std::fs::metadata(&path) {
+                let file_size = metadata.len();
+                match std::fs::remove_file(&path) {
                     Ok(_) => {
                         cleaned_count += 1;
-                        freed_bytes += entry.file_size;
+                        freed_bytes += file_size;
                     }
                     Err(e) => {
-                        eprintln!("Failed to delete thumbnail {}: {}", entry.thumbnail_path.display(), e);
+                        warn!(path = %path.display(), error = %e, "Failed to delete orphaned file");
                     }
                 }
-            } else {
-                cleaned_count += 1;
             }
         }
     }
+    (cleaned_count, freed_bytes)
+}
 
-    // Scan for orphaned files (files in directory but not in cache)
-    if let Ok(entries) = std::fs::read_dir(&temp_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            
-            // Skip the cache.json file
-            if path.file_name().and_then(|n| n.to_str()) == Some("cache.json") {
-                continue;
-            }
-
-            // Check if this file is in the cache
-            let is_orphaned = !cache.entries.values().any(|e| e.thumbnail_path == path);
-
-            if is_orphaned && path.extension().and_then(|e| e.to_str()) == Some("jpg") {
-                if let Ok(metadata) = std::fs::metadata(&path) {
-                    let file_size = metadata.len();
-                    match std::fs::remove_file(&path) {
-                        Ok(_) => {
-                            cleaned_count += 1;
-                            freed_bytes += file_size;
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to delete orphaned file {}: {}", path.display(), e);
-                        }
-                    }
-                }
-            }
-        }
-    }
+/// Returns the thumbnail cache limits and location.
+#[tauri::command]
+pub async fn get_cache_settings(app: AppHandle) -> Result<CacheSettings, AppError> {
+    Ok(cache_db::load_settings(&app))
+}
 
-    // Save updated cache
-    if let Err(e) = save_thumbnail_cache(&cache) {
-        eprintln!("Failed to save cache after cleanup: {}", e);
+/// Saves the thumbnail cache limits and location, then evicts thumbnails
+/// beyond the new limits. Thumbnails in a previous directory stay usable
+/// until they are evicted.
+#[tauri::command]
+pub async fn set_cache_settings(
+    app: AppHandle,
+    cache: State<'_, CacheDatabase>,
+    settings: CacheSettings,
+) -> Result<(), AppError> {
+    if settings.max_entries == 0 || settings.max_bytes == 0 {
+        return Err(AppError::invalid_config(
+            "The thumbnail cache must allow at least one thumbnail",
+        ));
+    }
+    if let Some(directory) = &settings.directory {
+        if !directory.is_absolute() {
+            return Err(AppError::invalid_config("The cache directory must be an absolute path"));
+        }
+        std::fs::create_dir_all(cache_db::thumbnail_dir(&settings)).map_err(|e| {
+            AppError::invalid_config(format!(
+                "Cannot use {} as the cache directory: {}",
+                directory.display(),
+                e
+            ))
+        })?;
     }
 
-    let freed_mb = freed_bytes as f64 / (1024.0 * 1024.0);
-    Ok(format!("Cleaned up {} thumbnails, freed {:.2} MB", cleaned_count, freed_mb))
+    cache_db::save_settings(&app, &settings)?;
+    cache.enforce_limits(&app)?;
+    Ok(())
 }
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use tracing::warn;
+
+use crate::sidecar::sha256_file;
+use crate::types::FileItem;
+
+/// Maps the index of every file that repeats an earlier file in the batch to
+/// the index of that first occurrence. Only files of equal size are compared,
+/// first by filesystem identity (hard links) and then by SHA-256 checksum.
+pub fn find_duplicate_inputs(files: &[FileItem]) -> HashMap<usize, usize> {
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        if let Ok(metadata) = std::fs::metadata(&file.path) {
+            by_size.entry(metadata.len()).or_default().push(index);
+        }
+    }
+
+    let mut duplicates = HashMap::new();
+    for indices in by_size.into_values().filter(|indices| indices.len() > 1) {
+        let mut seen_files: HashMap<(u64, u64), usize> = HashMap::new();
+        let mut seen_hashes: HashMap<String, usize> = HashMap::new();
+        for index in indices {
+            let path = &files[index].path;
+
+            // Hard links to the same file are identical without hashing
+            if let Some(id) = file_id(path) {
+                if let Some(&original) = seen_files.get(&id) {
+                    duplicates.insert(index, original);
+                    continue;
+                }
+                seen_files.insert(id, index);
+            }
+
+            match sha256_file(path) {
+                Ok(hash) => match seen_hashes.get(&hash) {
+                    Some(&original) => {
+                        duplicates.insert(index, original);
+                    }
+                    None => {
+                        seen_hashes.insert(hash, index);
+                    }
+                },
+                Err(e) => warn!(path = %path.display(), error = %e, "Failed to checksum input"),
+            }
+        }
+    }
+    duplicates
+}
+
+/// Places the already rendered output at `target`, hard-linking when the
+/// filesystem allows it and copying otherwise.
+pub fn link_or_copy(source: &Path, target: &Path) -> std::io::Result<()> {
+    if target.exists() {
+        std::fs::remove_file(target)?;
+    }
+    if std::fs::hard_link(source, target).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(source, target).map(|_| ())
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::audio::is_audio_file;
+use crate::plan::estimate_output_bytes;
+use crate::types::{FileItem, WatermarkConfig};
+
+/// Space required on top of the estimated output size, in percent: encodes
+/// can come out larger than their inputs.
+const SAFETY_MARGIN_PERCENT: u64 = 20;
+
+/// Returned when a batch is not expected to fit on its output volume.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSpaceShortfall {
+    pub path: PathBuf,
+    /// Estimated output size of the batch, safety margin included
+    pub required_bytes: u64,
+    pub free_bytes: u64,
+}
+
+/// Estimates the space the outputs of `files` take, from their input sizes
+/// plus a safety margin, and compares it with the free space on the volume of
+/// `output_dir`. Passes when the platform does not report free space.
+pub fn check(
+    files: &[FileItem],
+    config: &WatermarkConfig,
+    output_dir: &Path,
+) -> Result<(), DiskSpaceShortfall> {
+    let Some(free_bytes) = output_dir.ancestors().find(|dir| dir.exists()).and_then(free_space)
+    else {
+        return Ok(());
+    };
+    let estimated: u64 = files
+        .iter()
+        .filter_map(|file| {
+            let input_bytes = std::fs::metadata(&file.path).ok()?.len();
+            Some(estimate_output_bytes(input_bytes, is_audio_file(&file.path), config))
+        })
+        .sum();
+    let required_bytes = estimated.saturating_add(estimated * SAFETY_MARGIN_PERCENT / 100);
+    if required_bytes <= free_bytes {
+        return Ok(());
+    }
+    Err(DiskSpaceShortfall {
+        path: output_dir.to_path_buf(),
+        required_bytes,
+        free_bytes,
+    })
+}
+
+/// Space available to this user on the volume holding `dir`, which must
+/// exist; `None` when the platform does not report it.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+pub fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statvfs` is plain data, so an all-zero value is valid
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stats` outlives the call
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    Some((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+}
+
+#[cfg(windows)]
+pub fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `path` is NUL-terminated and the totals may be null
+    let result = unsafe {
+        GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (result != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
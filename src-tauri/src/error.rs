@@ -0,0 +1,196 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::commands::ProcessingError;
+use crate::disk_space::DiskSpaceShortfall;
+use crate::ffmpeg::FfmpegError;
+use crate::path_policy::OutputLocationConfirmation;
+
+/// Machine-readable error kind, serialized as `SCREAMING_SNAKE_CASE` so the
+/// frontend can branch on it without parsing messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    FfmpegMissing,
+    FfmpegFailed,
+    FfmpegStalled,
+    /// FFmpeg ran past the per-file time limit and was killed
+    Timeout,
+    UnsupportedFormat,
+    InvalidConfig,
+    InvalidInput,
+    NotFound,
+    PermissionDenied,
+    DiskFull,
+    /// The outputs of a batch are not expected to fit on the output volume,
+    /// so it was not started
+    DiskFullPredicted,
+    /// The user has to confirm writing to a new output directory first
+    ConfirmationRequired,
+    Io,
+    Processing,
+    Internal,
+}
+
+/// Error returned by every command. Serialized as
+/// `{ code, message, details }`: `message` is meant for the user, `details`
+/// carries the underlying cause (such as FFmpeg output) when there is one.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<String>,
+    /// Set with `ConfirmationRequired`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation: Option<OutputLocationConfirmation>,
+    /// Set with `DiskFullPredicted`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_space: Option<DiskSpaceShortfall>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+            confirmation: None,
+            disk_space: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub fn invalid_config(err: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::InvalidConfig, err.to_string())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    /// Asks for approval of a new output directory; the command can be
+    /// retried once `approve_output_location` was called for it.
+    pub fn confirmation_required(confirmation: OutputLocationConfirmation) -> Self {
+        let message = format!(
+            "Confirm writing to {}, which has not been used as an output folder before",
+            confirmation.path.display()
+        );
+        Self {
+            confirmation: Some(confirmation),
+            ..Self::new(ErrorCode::ConfirmationRequired, message)
+        }
+    }
+
+    pub fn disk_full_predicted(shortfall: DiskSpaceShortfall) -> Self {
+        let megabytes = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        let message = format!(
+            "The batch needs about {:.1} MB but only {:.1} MB are free on {}",
+            megabytes(shortfall.required_bytes),
+            megabytes(shortfall.free_bytes),
+            shortfall.path.display()
+        );
+        Self {
+            disk_space: Some(shortfall),
+            ..Self::new(ErrorCode::DiskFullPredicted, message)
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+}
+
+impl From<&std::io::Error> for ErrorCode {
+    fn from(err: &std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            std::io::ErrorKind::StorageFull => ErrorCode::DiskFull,
+            _ => ErrorCode::Io,
+        }
+    }
+}
+
+impl From<&FfmpegError> for ErrorCode {
+    fn from(err: &FfmpegError) -> Self {
+        match err {
+            FfmpegError::MissingBinary(_) | FfmpegError::Spawn(_) => ErrorCode::FfmpegMissing,
+            FfmpegError::InvalidConfig(_) => ErrorCode::InvalidConfig,
+            FfmpegError::UnsupportedFormat(_) => ErrorCode::UnsupportedFormat,
+            FfmpegError::Path(_) => ErrorCode::Io,
+            FfmpegError::Stalled(_) => ErrorCode::FfmpegStalled,
+            FfmpegError::Timeout(_) => ErrorCode::Timeout,
+            FfmpegError::Execution(output) if output.contains("No space left on device") => {
+                ErrorCode::DiskFull
+            }
+            FfmpegError::Execution(_) => ErrorCode::FfmpegFailed,
+        }
+    }
+}
+
+/// Code a failed file is reported with in its `FileResult`.
+impl From<&ProcessingError> for ErrorCode {
+    fn from(err: &ProcessingError) -> Self {
+        match err {
+            ProcessingError::Message(_) => ErrorCode::Processing,
+            ProcessingError::Ffmpeg(err) => err.into(),
+            ProcessingError::Io(err) => err.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(ErrorCode::from(&err), err.to_string())
+    }
+}
+
+impl From<FfmpegError> for AppError {
+    fn from(err: FfmpegError) -> Self {
+        let code = ErrorCode::from(&err);
+        match err {
+            FfmpegError::Execution(output) => {
+                Self::new(code, "FFmpeg failed to process the file").with_details(output)
+            }
+            err => Self::new(code, err.to_string()),
+        }
+    }
+}
+
+impl From<ProcessingError> for AppError {
+    fn from(err: ProcessingError) -> Self {
+        match err {
+            ProcessingError::Message(message) => Self::new(ErrorCode::Processing, message),
+            ProcessingError::Ffmpeg(err) => err.into(),
+            ProcessingError::Io(err) => err.into(),
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(err: tauri::Error) -> Self {
+        Self::new(ErrorCode::Internal, err.to_string())
+    }
+}
@@ -1,10 +1,25 @@
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use tauri::AppHandle;
+use tauri_plugin_shell::process::{Command, CommandEvent};
 use tauri_plugin_shell::ShellExt;
 use thiserror::Error;
+use tracing::warn;
 
-use crate::types::{WatermarkConfig, WatermarkPosition, WatermarkType};
+use crate::affinity::pin_process;
+use crate::ffmpeg_download;
+use crate::file_log;
+use crate::hwaccel;
+use crate::priority::lower_priority;
+use crate::sniff::{has_alpha, is_animated, sniff, MediaKind};
+use crate::system_ffmpeg;
+use crate::transform::is_jpeg;
+use crate::types::{
+    AudioHandling, EncoderPreset, EncodingOptions, FfmpegSource, HardwareAcceleration, HdrMode,
+    ImageOutputOptions, ImageScaleMode, MarginUnit, MotionMode, OutputResize, ResizeMode,
+    SourceColor, VideoCodec, WatermarkConfig, WatermarkPosition, WatermarkType,
+};
 
 #[derive(Debug, Error)]
 pub enum FfmpegError {
@@ -20,9 +35,121 @@ pub enum FfmpegError {
     Execution(String),
     #[error("Path error: {0}")]
     Path(String),
+    #[error("FFmpeg stalled: {0}")]
+    Stalled(String),
+    #[error("FFmpeg timed out: {0}")]
+    Timeout(String),
 }
 
-pub fn get_ffmpeg_sidecar_path(_app: &AppHandle) -> Result<PathBuf, FfmpegError> {
+/// Builds a palette from every frame and maps the frames onto it; GIF's
+/// default fixed palette bands and dithers badly.
+const GIF_PALETTE_FILTER: &str = concat!(
+    "split[gif_frames][gif_source];[gif_source]palettegen[gif_palette];",
+    "[gif_frames][gif_palette]paletteuse"
+);
+
+/// Converts PQ or HLG video to 8-bit BT.709 before the watermark is drawn.
+const TONE_MAP_FILTER: &str = concat!(
+    "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,",
+    "tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p"
+);
+
+/// Outputs whose container defaults to H.264, which HDR players do not expect.
+const HDR_H265_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "mkv"];
+
+/// Containers that get the web-optimized encoding; other outputs are only
+/// tone mapped to SDR.
+const WEB_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov"];
+
+/// Bitrate of audio that is converted rather than copied.
+const AUDIO_BITRATE: &str = "160k";
+
+/// Audio codecs each container holds, by ffprobe name, and the encoder used
+/// for other codecs. Audio is copied into containers that are not listed.
+const CONTAINER_AUDIO: &[(&[&str], &[&str], &str)] = &[
+    (&["mp4", "m4v", "mov"], &["aac", "mp3", "alac", "ac3", "eac3"], "aac"),
+    (&["webm"], &["opus", "vorbis"], "libopus"),
+    (&["flv"], &["aac", "mp3"], "aac"),
+];
+
+/// H.264 level of web-optimized video, which every phone and browser decodes,
+/// covering up to 1080p at 30 fps.
+const WEB_H264_LEVEL: &str = "4.1";
+
+/// Width of filmstrip frames, small enough for a file list row.
+const FILMSTRIP_FRAME_WIDTH: u32 = 160;
+
+/// How long FFmpeg may go without advancing a frame before it is considered stalled.
+pub const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A binary picked by `resolve_binary`.
+enum FfmpegBinary {
+    Bundled(PathBuf),
+    /// Installed with `download_ffmpeg`
+    Downloaded(PathBuf),
+    System(PathBuf),
+}
+
+/// Path of the FFmpeg that runs, bundled or from the system per the
+/// `FfmpegSource` setting.
+pub fn get_ffmpeg_sidecar_path(app: &AppHandle) -> Result<PathBuf, FfmpegError> {
+    resolve_binary(app, "ffmpeg").map(FfmpegBinary::into_path)
+}
+
+/// Path of the ffprobe that runs, used to read media details as JSON.
+pub fn get_ffprobe_sidecar_path(app: &AppHandle) -> Result<PathBuf, FfmpegError> {
+    resolve_binary(app, "ffprobe").map(FfmpegBinary::into_path)
+}
+
+/// Command running `name` ("ffmpeg" or "ffprobe") from the configured source.
+pub fn ffmpeg_command(app: &AppHandle, name: &str) -> Result<Command, FfmpegError> {
+    match resolve_binary(app, name)? {
+        FfmpegBinary::Bundled(_) => app
+            .shell()
+            .sidecar(name)
+            .map_err(|e| FfmpegError::Spawn(e.to_string())),
+        FfmpegBinary::Downloaded(path) | FfmpegBinary::System(path) => {
+            Ok(app.shell().command(path))
+        }
+    }
+}
+
+impl FfmpegBinary {
+    fn into_path(self) -> PathBuf {
+        match self {
+            FfmpegBinary::Bundled(path)
+            | FfmpegBinary::Downloaded(path)
+            | FfmpegBinary::System(path) => path,
+        }
+    }
+}
+
+/// Whether the bundled FFmpeg is present, whatever the configured source.
+pub fn has_bundled_ffmpeg(app: &AppHandle) -> bool {
+    sidecar_path(app, "ffmpeg").is_ok()
+}
+
+/// Picks the bundled, downloaded or system binary. A download stands in for
+/// a missing bundled binary; `Auto` falls back to the system one after that,
+/// and reports the bundled error when none is usable.
+fn resolve_binary(app: &AppHandle, name: &str) -> Result<FfmpegBinary, FfmpegError> {
+    let system = || system_ffmpeg::find(app, name).map(|binary| FfmpegBinary::System(binary.path));
+    let bundled = || match sidecar_path(app, name) {
+        Ok(path) => Ok(FfmpegBinary::Bundled(path)),
+        Err(bundled_error) => ffmpeg_download::installed_path(app, name)
+            .map(FfmpegBinary::Downloaded)
+            .ok_or(bundled_error),
+    };
+    match system_ffmpeg::load_source(app) {
+        FfmpegSource::Bundled => bundled(),
+        FfmpegSource::System => system(),
+        FfmpegSource::Auto => {
+            bundled().or_else(|bundled_error| system().map_err(|_| bundled_error))
+        }
+    }
+}
+
+fn sidecar_path(_app: &AppHandle, name: &str) -> Result<PathBuf, FfmpegError> {
     let exe = std::env::current_exe()
         .map_err(|e| FfmpegError::Path(format!("failed to resolve current executable: {e}")))?;
     let Some(dir) = exe.parent() else {
@@ -31,23 +158,24 @@ pub fn get_ffmpeg_sidecar_path(_app: &AppHandle) -> Result<PathBuf, FfmpegError>
         ));
     };
 
-    let filename = if cfg!(target_os = "windows") {
-        "ffmpeg-x86_64-pc-windows-msvc.exe"
+    let target = if cfg!(target_os = "windows") {
+        "x86_64-pc-windows-msvc.exe"
     } else if cfg!(target_os = "linux") {
-        "ffmpeg-x86_64-unknown-linux-gnu"
+        "x86_64-unknown-linux-gnu"
     } else if cfg!(target_os = "macos") {
         if cfg!(target_arch = "aarch64") {
-            "ffmpeg-aarch64-apple-darwin"
+            "aarch64-apple-darwin"
         } else {
-            "ffmpeg-x86_64-apple-darwin"
+            "x86_64-apple-darwin"
         }
     } else {
-        return Err(FfmpegError::MissingBinary(
-            "unsupported operating system for bundled FFmpeg".into(),
-        ));
+        return Err(FfmpegError::MissingBinary(format!(
+            "unsupported operating system for bundled {}",
+            name
+        )));
     };
 
-    let path = dir.join(filename);
+    let path = dir.join(format!("{}-{}", name, target));
     if !path.exists() {
         return Err(FfmpegError::MissingBinary(format!(
             "{} (place the binary in src-tauri/binaries/ before building)",
@@ -60,7 +188,8 @@ pub fn get_ffmpeg_sidecar_path(_app: &AppHandle) -> Result<PathBuf, FfmpegError>
 
 pub fn build_text_watermark_filter(
     config: &WatermarkConfig,
-    _is_video: bool,
+    is_video: bool,
+    is_jpeg: bool,
 ) -> Result<String, FfmpegError> {
     if config.text.trim().is_empty() {
         return Err(FfmpegError::InvalidConfig(
@@ -87,6 +216,12 @@ pub fn build_text_watermark_filter(
     
     let font_color = normalize_color(&config.text_color, config.opacity);
     let (x_expr, y_expr) = text_position_expression(config);
+    let (x_expr, y_expr) = if is_video {
+        jitter_position_expression(config, "w", "h", "text_w", "text_h")
+            .unwrap_or_else(|| text_motion_expression(config, x_expr, y_expr))
+    } else {
+        (x_expr, y_expr)
+    };
 
     // Wrap x and y expressions in quotes if they contain commas (for complex expressions)
     let x_param = if x_expr.contains(',') {
@@ -101,52 +236,202 @@ pub fn build_text_watermark_filter(
     };
 
     let filter = format!(
-        "drawtext=text='{}':font='{}':fontsize={}:fontcolor={}:shadowcolor=black@0.5:shadowx=2:shadowy=2:{}:{}",
+        "drawtext=text='{}':font='{}':fontsize={}:fontcolor={}:shadowcolor=black@0.5:shadowx=2:shadowy=2:{}:{}{}",
         escaped_text,
         escaped_font,
         config.font_size,
         font_color,
         x_param,
-        y_param
+        y_param,
+        enable_option(config, is_video)
     );
+    let filter = match fade_alpha_expression(config, is_video) {
+        Some(alpha) => format!("{}:alpha='{}'", filter, alpha),
+        None => filter,
+    };
 
-    Ok(filter)
+    match transform_filter(config, is_jpeg) {
+        Some(transform) => Ok(format!("{},{}", transform, filter)),
+        None => Ok(filter),
+    }
 }
 
 pub fn build_image_watermark_filter(
     config: &WatermarkConfig,
     watermark_image_path: &str,
+    is_video: bool,
+    is_jpeg: bool,
+    keep_alpha: bool,
 ) -> Result<String, FfmpegError> {
-    if watermark_image_path.trim().is_empty() {
-        return Err(FfmpegError::InvalidConfig(
-            "image watermark requires an image path".into(),
-        ));
+    // Fading needs a frame stream, so the image is looped with `-loop 1`
+    let looped = is_video && config.has_fade();
+    build_overlay_filter(
+        config,
+        watermark_image_path,
+        "image",
+        looped,
+        is_video,
+        is_jpeg,
+        keep_alpha,
+    )
+}
+
+/// Like an image watermark, but the watermark input is a clip looped with
+/// `-stream_loop`, so the overlay has to stop when the source ends.
+pub fn build_video_watermark_filter(
+    config: &WatermarkConfig,
+    watermark_video_path: &str,
+    is_video: bool,
+    is_jpeg: bool,
+    keep_alpha: bool,
+) -> Result<String, FfmpegError> {
+    build_overlay_filter(
+        config,
+        watermark_video_path,
+        "video",
+        true,
+        is_video,
+        is_jpeg,
+        keep_alpha,
+    )
+}
+
+fn build_overlay_filter(
+    config: &WatermarkConfig,
+    watermark_path: &str,
+    kind: &str,
+    looped: bool,
+    is_video: bool,
+    is_jpeg: bool,
+    keep_alpha: bool,
+) -> Result<String, FfmpegError> {
+    if watermark_path.trim().is_empty() {
+        return Err(FfmpegError::InvalidConfig(format!(
+            "{} watermark requires a {} path",
+            kind, kind
+        )));
     }
 
-    let watermark_path = Path::new(watermark_image_path);
-    if !watermark_path.exists() {
+    if !Path::new(watermark_path).exists() {
         return Err(FfmpegError::InvalidConfig(format!(
-            "watermark image not found at {}",
-            watermark_image_path
+            "watermark {} not found at {}",
+            kind, watermark_path
         )));
     }
 
-    let (x_expr, y_expr) = overlay_position_expression(config);
+    let (x_expr, y_expr) = is_video
+        .then(|| jitter_position_expression(config, "W", "H", "w", "h"))
+        .flatten()
+        .unwrap_or_else(|| overlay_position_expression(config));
     let opacity = (config.opacity as f32 / 100.0).clamp(0.0, 1.0);
-    
-    // Scale watermark as percentage of source width (default 20%)
-    let scale_percent = config.image_scale.unwrap_or(20);
-    let scale_expr = format!("iw*{}/ 100:-1", scale_percent);
+    let (width_expr, height_expr) = image_scale_expression(config);
+
+    // Rotate/crop the source first so the overlay is positioned on the final frame
+    let (base_chain, source_label) = match transform_filter(config, is_jpeg) {
+        Some(transform) => (format!("[0:v]{}[src];", transform), "[src]"),
+        None => (String::new(), "[0:v]"),
+    };
+
+    // Key out the background at the image's own resolution, before scaling
+    // softens its edges
+    let watermark_chain = match &config.color_key {
+        Some(key) => format!(
+            "[1:v]format=rgba,colorkey={}:{:.3}:{:.3}[wm_keyed];[wm_keyed]",
+            normalize_key_color(&key.color),
+            key.similarity.clamp(0.01, 1.0),
+            key.blend.clamp(0.0, 1.0)
+        ),
+        None => "[1:v]".to_string(),
+    };
 
+    // scale2ref sizes the watermark against the main frame rather than itself
+    let scale_chain = format!(
+        "{}{}{}scale2ref=w='{}':h='{}'[wm][base]",
+        base_chain, watermark_chain, source_label, width_expr, height_expr
+    );
+
+    // An endlessly looped watermark would otherwise keep the output running
+    let shortest = if looped { ":shortest=1" } else { "" };
+    let enable = enable_option(config, is_video);
+    let fade = fade_filters(config, is_video)
+        .map(|filters| format!(",{}", filters))
+        .unwrap_or_default();
+
+    let Some(blend) = config.blend_mode.ffmpeg_name() else {
+        // overlay writes 8-bit YUV by default; in RGB it keeps the main alpha
+        // plane. Blend modes always work in 8 bits.
+        let overlay_format = if keep_alpha {
+            ":format=rgb"
+        } else if is_video && config.keeps_high_bit_depth() {
+            ":format=yuv420p10"
+        } else {
+            ""
+        };
+        return Ok(format!(
+            "{};[wm]format=rgba,colorchannelmixer=aa={:.3}{}[wm_alpha];[base][wm_alpha]overlay=x='{}':y='{}'{}{}{}",
+            scale_chain, opacity, fade, x_expr, y_expr, overlay_format, shortest, enable
+        ));
+    };
+
+    // blend would mix the alpha planes too, so the source alpha is set aside
+    // and merged back afterwards
+    let (base_input, alpha_merge) = if keep_alpha {
+        (
+            concat!(
+                "[base]format=rgba,split[base_color][base_mask];",
+                "[base_mask]alphaextract[base_alpha];[base_color]"
+            ),
+            "[blended];[blended][base_alpha]alphamerge",
+        )
+    } else {
+        ("[base]", "")
+    };
+
+    // Left in gbrp, encoders pick a 4:4:4 format most players cannot decode;
+    // 10-bit outputs get their format from -pix_fmt instead
+    let output_format = if is_video && !keep_alpha && !config.keeps_high_bit_depth() {
+        ",format=yuv420p"
+    } else {
+        ""
+    };
+
+    // blend needs two full-size inputs, so the watermark is first placed on a
+    // canvas filled with the mode's neutral color (no effect where it shows)
     Ok(format!(
-        "[1:v]scale={}[wm];[wm]format=rgba,colorchannelmixer=aa={:.3}[wm_alpha];[0:v][wm_alpha]overlay={}:{}",
-        scale_expr,
-        opacity,
+        "{};[wm]format=rgba{}[wm_rgba];{}format=gbrp,split[base_main][base_canvas];[base_canvas]drawbox=c={}:t=fill[canvas];[canvas][wm_rgba]overlay=x='{}':y='{}':format=gbrp{}[layer];[base_main][layer]blend=all_mode={}:all_opacity={:.3}{}{}{}{}",
+        scale_chain,
+        fade,
+        base_input,
+        config.blend_mode.neutral_color(),
         x_expr,
-        y_expr
+        y_expr,
+        shortest,
+        blend,
+        opacity,
+        shortest,
+        enable,
+        alpha_merge,
+        output_format
     ))
 }
 
+/// Watermark size as scale2ref width/height expressions. `main_w`/`main_h` are
+/// the source frame, `a` is the watermark's own aspect ratio.
+fn image_scale_expression(config: &WatermarkConfig) -> (String, String) {
+    let scale = config.image_scale.unwrap_or(20);
+    let fraction = scale as f32 / 100.0;
+
+    match config.image_scale_mode {
+        ImageScaleMode::Width => (format!("main_w*{:.4}", fraction), "ow/a".to_string()),
+        ImageScaleMode::Height => ("oh*a".to_string(), format!("main_h*{:.4}", fraction)),
+        ImageScaleMode::ShorterSide => (
+            format!("min(main_w,main_h)*{:.4}", fraction),
+            "ow/a".to_string(),
+        ),
+        ImageScaleMode::Pixels => (scale.to_string(), "ow/a".to_string()),
+    }
+}
+
 pub fn build_ffmpeg_command(
     app: &AppHandle,
     input_path: &Path,
@@ -156,61 +441,743 @@ pub fn build_ffmpeg_command(
 ) -> Result<Vec<String>, FfmpegError> {
     let _ = get_ffmpeg_sidecar_path(app)?;
 
+    let threads = config.background_mode.as_ref().and_then(|mode| mode.threads);
     let mut args = Vec::new();
+    if let Some(threads) = threads {
+        // Before the input it limits the decoder; the encoder is limited below
+        args.extend(["-threads".to_string(), threads.to_string()]);
+    }
     args.push("-i".into());
     args.push(input_path.to_string_lossy().into_owned());
 
+    // Transparent PNG/WebP inputs keep their alpha when the output can hold it
+    let keep_alpha = !is_video && alpha_pix_fmt(output_path).is_some() && has_alpha(input_path);
+    push_watermark_args(&mut args, config, is_video, is_jpeg(input_path), keep_alpha)?;
+
+    if is_video {
+        if config.tone_maps() {
+            prepend_source_filter(&mut args, TONE_MAP_FILTER);
+        }
+        let web = config.web_optimized
+            && WEB_EXTENSIONS.iter().any(|ext| has_extension(output_path, ext));
+        // Hardware H.264 encoders are 8-bit only
+        let preserve_color = config.hdr_mode == HdrMode::Preserve
+            && !config.web_optimized
+            && config.source_color.is_some()
+            && !has_extension(output_path, "gif");
+        let encoder = if preserve_color {
+            if config.hardware_acceleration == HardwareAcceleration::Force {
+                return Err(FfmpegError::InvalidConfig(
+                    "hardware encoding cannot keep 10-bit or HDR video; use tone mapping".into(),
+                ));
+            }
+            None
+        } else {
+            hwaccel::select(app, config, output_path)?
+        };
+        match encoder {
+            Some(encoder) => hwaccel::apply(encoder, config.encoding.as_ref(), &mut args),
+            None if web => {
+                let encoding = EncodingOptions {
+                    codec: VideoCodec::H264,
+                    ..config.encoding.clone().unwrap_or_default()
+                };
+                push_encoding_args(&mut args, &encoding, output_path);
+                args.extend(["-pix_fmt", "yuv420p", "-level:v", WEB_H264_LEVEL].map(String::from));
+            }
+            None => {
+                let color = config.source_color.as_ref().filter(|_| preserve_color);
+                let encoding = config
+                    .encoding
+                    .clone()
+                    .or_else(|| color.and_then(|color| hdr_default_encoding(color, output_path)));
+                if let Some(encoding) = &encoding {
+                    push_encoding_args(&mut args, encoding, output_path);
+                }
+                if let Some(color) = color {
+                    let codec = encoding.as_ref().map(|encoding| encoding.codec);
+                    push_color_args(&mut args, color, codec);
+                }
+            }
+        }
+        if web {
+            // The index goes first so playback starts while the file downloads
+            args.extend(["-profile:v", "high", "-movflags", "+faststart"].map(String::from));
+        }
+        if config.preserve_streams {
+            push_stream_mapping(&mut args, output_path, config.strip_metadata);
+        }
+        push_audio_args(&mut args, config, output_path, web);
+    } else {
+        // Animated GIF/WebP inputs keep every frame when the output can animate
+        let animated = is_animated_output(output_path) && is_animated(input_path);
+        if !animated {
+            args.push("-frames:v".into());
+            args.push("1".into());
+        } else if has_extension(output_path, "gif") {
+            append_filter(&mut args, GIF_PALETTE_FILTER);
+        }
+        push_image_quality_args(&mut args, &config.image_output, output_path);
+        if animated {
+            // Play forever, like the source
+            args.push("-loop".into());
+            args.push("0".into());
+        }
+        if let Some(pix_fmt) = alpha_pix_fmt(output_path).filter(|_| keep_alpha) {
+            args.push("-pix_fmt".into());
+            args.push(pix_fmt.into());
+        }
+    }
+
+    if config.strip_metadata {
+        // Drops the location, device and serial number tags FFmpeg copies by
+        // default, and the encoder version tag
+        args.extend(["-map_metadata", "-1", "-fflags", "+bitexact"].map(String::from));
+    }
+    if let Some(threads) = threads {
+        args.extend([
+            "-threads".to_string(),
+            threads.to_string(),
+            "-filter_threads".to_string(),
+            threads.to_string(),
+        ]);
+    }
+
+    args.push("-y".into());
+    args.push(output_path.to_string_lossy().into_owned());
+
+    Ok(args)
+}
+
+/// Copies, converts or drops the audio of a video according to
+/// `audio_handling`.
+fn push_audio_args(
+    args: &mut Vec<String>,
+    config: &WatermarkConfig,
+    output_path: &Path,
+    web: bool,
+) {
+    let codec = match config.audio_handling {
+        AudioHandling::Remove => None,
+        AudioHandling::Aac => Some("aac"),
+        AudioHandling::Opus => Some("libopus"),
+        // Browsers cannot play most of the audio codecs MKV sources carry
+        _ if web => Some("aac"),
+        AudioHandling::Copy => Some("copy"),
+        AudioHandling::Auto if has_extension(output_path, "gif") => None,
+        AudioHandling::Auto => Some(auto_audio_codec(&config.source_audio_codecs, output_path)),
+    };
+    match codec {
+        None => args.push("-an".into()),
+        Some("copy") => args.extend(["-c:a", "copy"].map(String::from)),
+        Some(codec) => args.extend(["-c:a", codec, "-b:a", AUDIO_BITRATE].map(String::from)),
+    }
+}
+
+/// Copies the audio when the output container holds every source codec, and
+/// otherwise converts it to the container's usual codec.
+fn auto_audio_codec(source_codecs: &[String], output_path: &Path) -> &'static str {
+    let container = CONTAINER_AUDIO
+        .iter()
+        .find(|(extensions, _, _)| extensions.iter().any(|ext| has_extension(output_path, ext)));
+    match container {
+        Some((_, supported, encoder))
+            if source_codecs.iter().any(|codec| !supported.contains(&codec.as_str())) =>
+        {
+            encoder
+        }
+        _ => "copy",
+    }
+}
+
+/// Adds software encoder, quality and preset flags for video output.
+fn push_encoding_args(args: &mut Vec<String>, encoding: &EncodingOptions, output_path: &Path) {
+    let codec = encoding.codec;
+    args.push("-c:v".into());
+    args.push(codec.ffmpeg_encoder().into());
+
+    match codec {
+        VideoCodec::H264 | VideoCodec::H265 => {
+            args.push("-preset".into());
+            args.push(encoding.preset.ffmpeg_name().into());
+        }
+        VideoCodec::Vp9 | VideoCodec::Av1 => {
+            args.push("-cpu-used".into());
+            args.push(encoding.preset.cpu_used().to_string());
+        }
+    }
+
+    if let Some(bitrate) = encoding.bitrate_kbps {
+        args.push("-b:v".into());
+        args.push(format!("{}k", bitrate));
+    } else if let Some(crf) = encoding.crf {
+        args.push("-crf".into());
+        args.push(crf.to_string());
+        // libvpx and libaom only run in constant quality mode without a bitrate cap
+        if matches!(codec, VideoCodec::Vp9 | VideoCodec::Av1) {
+            args.push("-b:v".into());
+            args.push("0".into());
+        }
+    }
+
+    // Apple players only recognize HEVC in MP4/MOV when tagged hvc1
+    let is_quicktime = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "mp4" | "m4v" | "mov"));
+    if codec == VideoCodec::H265 && is_quicktime {
+        args.push("-tag:v".into());
+        args.push("hvc1".into());
+    }
+}
+
+/// H.265 for HDR sources written to a container that defaults to H.264.
+fn hdr_default_encoding(color: &SourceColor, output_path: &Path) -> Option<EncodingOptions> {
+    let defaults_to_h264 = HDR_H265_EXTENSIONS.iter().any(|ext| has_extension(output_path, ext));
+    (color.is_hdr() && defaults_to_h264).then(|| EncodingOptions {
+        codec: VideoCodec::H265,
+        ..EncodingOptions::default()
+    })
+}
+
+/// Keeps the bit depth and color tags of a 10-bit or HDR source, plus its
+/// HDR10 mastering metadata when encoding with x265.
+fn push_color_args(args: &mut Vec<String>, color: &SourceColor, codec: Option<VideoCodec>) {
+    if color.bit_depth > 8 {
+        args.extend(["-pix_fmt", "yuv420p10le"].map(String::from));
+    }
+    let tags = [
+        ("-color_primaries", &color.primaries),
+        ("-color_trc", &color.transfer),
+        ("-colorspace", &color.matrix),
+    ];
+    for (flag, value) in tags {
+        if let Some(value) = value {
+            args.extend([flag.to_string(), value.clone()]);
+        }
+    }
+
+    if codec != Some(VideoCodec::H265) || color.transfer.as_deref() != Some("smpte2084") {
+        return;
+    }
+    let mut params = vec!["hdr10=1".to_string(), "repeat-headers=1".to_string()];
+    if let Some(master_display) = &color.master_display {
+        params.push(format!("master-display={}", master_display));
+    }
+    if let Some(max_cll) = &color.max_cll {
+        params.push(format!("max-cll={}", max_cll));
+    }
+    args.extend(["-x265-params".to_string(), params.join(":")]);
+}
+
+/// Maps every audio and subtitle track of the main input next to the
+/// watermarked video, along with its chapters and, unless `strip_metadata`,
+/// its metadata. Without explicit maps FFmpeg keeps only one audio track and
+/// drops subtitles.
+fn push_stream_mapping(args: &mut Vec<String>, output_path: &Path, strip_metadata: bool) {
+    let video = match args.iter().position(|arg| arg == "-filter_complex") {
+        Some(index) => {
+            args[index + 1].push_str("[vout]");
+            "[vout]"
+        }
+        None => "0:v:0",
+    };
+    args.extend(["-map", video, "-map", "0:a?", "-map", "0:s?"].map(String::from));
+
+    let extension = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    // MP4 and WebM only hold text subtitles in their own format
+    let subtitle_codec = match extension.as_str() {
+        "mp4" | "m4v" | "mov" => "mov_text",
+        "webm" => "webvtt",
+        _ => "copy",
+    };
+    if extension == "mkv" {
+        // Fonts embedded for styled subtitles
+        args.extend(["-map", "0:t?"].map(String::from));
+    }
+    args.extend(["-c:s", subtitle_codec, "-map_chapters", "0"].map(String::from));
+    if !strip_metadata {
+        args.extend(["-map_metadata", "0"].map(String::from));
+    }
+}
+
+/// Adds the quality flags for the image format `output_path` is written in;
+/// FFmpeg's own JPEG default is visibly blocky.
+fn push_image_quality_args(
+    args: &mut Vec<String>,
+    options: &ImageOutputOptions,
+    output_path: &Path,
+) {
+    let extension = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => {
+            // The MJPEG encoder's -q:v runs from 2 (best) to 31 (worst)
+            let quality = u32::from(options.jpeg_quality.clamp(1, 100));
+            args.push("-q:v".into());
+            args.push((2 + (100 - quality) * 29 / 99).to_string());
+        }
+        "png" => {
+            args.push("-compression_level".into());
+            args.push(options.png_compression.min(9).to_string());
+        }
+        "webp" => {
+            args.push("-c:v".into());
+            args.push("libwebp".into());
+            args.push("-quality".into());
+            args.push(options.webp_quality.min(100).to_string());
+        }
+        _ => {}
+    }
+}
+
+/// Adds `filter` after the last filter of already built arguments, or as a
+/// new `-vf` when there is none.
+pub(crate) fn append_filter(args: &mut Vec<String>, filter: &str) {
+    let filter_flag = args.iter().position(|arg| arg == "-vf" || arg == "-filter_complex");
+    match filter_flag.and_then(|index| args.get_mut(index + 1)) {
+        Some(existing) => {
+            existing.push(',');
+            existing.push_str(filter);
+        }
+        None => args.extend(["-vf".to_string(), filter.to_string()]),
+    }
+}
+
+/// Adds `filter` in front of the watermark filters of already built
+/// arguments, so the watermark is drawn on its output.
+fn prepend_source_filter(args: &mut Vec<String>, filter: &str) {
+    let filter_flag = args.iter().position(|arg| arg == "-vf" || arg == "-filter_complex");
+    match filter_flag {
+        Some(index) if args[index] == "-vf" => {
+            args[index + 1] = format!("{},{}", filter, args[index + 1]);
+        }
+        Some(index) => {
+            let graph = args[index + 1].replace("[0:v]", "[source]");
+            args[index + 1] = format!("[0:v]{}[source];{}", filter, graph);
+        }
+        None => args.extend(["-vf".to_string(), filter.to_string()]),
+    }
+}
+
+/// Image outputs that can hold an animation.
+fn is_animated_output(output_path: &Path) -> bool {
+    has_extension(output_path, "gif") || has_extension(output_path, "webp")
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Pixel format with an alpha plane for image outputs that support one.
+fn alpha_pix_fmt(output_path: &Path) -> Option<&'static str> {
+    let extension = output_path.extension().and_then(|ext| ext.to_str())?;
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => Some("rgba"),
+        "webp" => Some("yuva420p"),
+        _ => None,
+    }
+}
+
+/// Joins `program` and `args` into a command line that can be pasted into a
+/// shell, quoting arguments that contain spaces or shell metacharacters.
+pub fn format_command_line(program: &Path, args: &[String]) -> String {
+    std::iter::once(program.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|arg| shell_quote(&arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shell_quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else if cfg!(target_os = "windows") {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Builds a command that renders the single frame at `timestamp` seconds with
+/// the watermark applied. `-copyts` keeps the original timestamps so
+/// time-based watermark expressions see the same `t` as in a full encode.
+pub fn build_preview_frame_command(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    timestamp: f64,
+) -> Result<Vec<String>, FfmpegError> {
+    let _ = get_ffmpeg_sidecar_path(app)?;
+
+    let mut args: Vec<String> = vec![
+        "-ss".into(),
+        format!("{:.3}", timestamp),
+        "-copyts".into(),
+        "-i".into(),
+        input_path.to_string_lossy().into_owned(),
+    ];
+
+    push_watermark_args(&mut args, config, true, false, false)?;
+
+    args.extend([
+        "-an".to_string(),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-q:v".to_string(),
+        "3".to_string(),
+        "-y".to_string(),
+        output_path.to_string_lossy().into_owned(),
+    ]);
+
+    Ok(args)
+}
+
+/// Builds a command that watermarks the first `duration` seconds of a video
+/// into a small web-playable MP4 no taller than `max_height`. The watermark is
+/// drawn at full resolution before scaling, so it looks as in a full encode.
+pub fn build_preview_clip_command(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+    duration: f64,
+    max_height: u32,
+) -> Result<Vec<String>, FfmpegError> {
+    let mut clip_config = config.clone();
+    clip_config.web_optimized = true;
+    clip_config.hardware_acceleration = HardwareAcceleration::Off;
+    clip_config.encoding = Some(EncodingOptions {
+        codec: VideoCodec::H264,
+        preset: EncoderPreset::Veryfast,
+        ..EncodingOptions::default()
+    });
+    clip_config.preserve_streams = false;
+
+    let mut args = build_ffmpeg_command(app, input_path, output_path, &clip_config, true)?;
+    append_filter(&mut args, &format!("scale=w=-2:h='min(ih,{})'", max_height));
+    // `-t` limits the output, so it goes in front of `-y <output>`
+    let output_index = args.len() - 2;
+    args.splice(output_index..output_index, ["-t".to_string(), format!("{:.3}", duration)]);
+    Ok(args)
+}
+
+/// Builds a command that renders the frame at `timestamp` seconds without a
+/// watermark, matching what `build_preview_frame_command` renders.
+pub fn build_original_frame_command(
+    app: &AppHandle,
+    input_path: &Path,
+    output_path: &Path,
+    timestamp: f64,
+) -> Result<Vec<String>, FfmpegError> {
+    let _ = get_ffmpeg_sidecar_path(app)?;
+
+    Ok(vec![
+        "-ss".into(),
+        format!("{:.3}", timestamp),
+        "-i".into(),
+        input_path.to_string_lossy().into_owned(),
+        "-an".into(),
+        "-frames:v".into(),
+        "1".into(),
+        "-q:v".into(),
+        "3".into(),
+        "-y".into(),
+        output_path.to_string_lossy().into_owned(),
+    ])
+}
+
+/// Builds a command that places two frames of the same height next to each
+/// other in a single image.
+pub fn build_side_by_side_command(
+    app: &AppHandle,
+    left_path: &Path,
+    right_path: &Path,
+    output_path: &Path,
+) -> Result<Vec<String>, FfmpegError> {
+    let _ = get_ffmpeg_sidecar_path(app)?;
+
+    Ok(vec![
+        "-i".into(),
+        left_path.to_string_lossy().into_owned(),
+        "-i".into(),
+        right_path.to_string_lossy().into_owned(),
+        "-filter_complex".into(),
+        "[0:v][1:v]hstack=inputs=2".into(),
+        "-frames:v".into(),
+        "1".into(),
+        "-q:v".into(),
+        "3".into(),
+        "-y".into(),
+        output_path.to_string_lossy().into_owned(),
+    ])
+}
+
+/// Appends the watermark input (for image and video watermarks) and filter
+/// arguments. `keep_alpha` keeps the main input's transparency through the
+/// overlay; `is_jpeg` applies the JPEG rotate/crop.
+fn push_watermark_args(
+    args: &mut Vec<String>,
+    config: &WatermarkConfig,
+    is_video: bool,
+    is_jpeg: bool,
+    keep_alpha: bool,
+) -> Result<(), FfmpegError> {
     match config.watermark_type {
         WatermarkType::Image => {
             let image_path = config.image_path.as_ref().ok_or_else(|| {
                 FfmpegError::InvalidConfig("image watermark requires image_path".into())
             })?;
+            if is_video && config.has_fade() {
+                args.push("-loop".into());
+                args.push("1".into());
+            }
             args.push("-i".into());
             args.push(Path::new(image_path).to_string_lossy().into_owned());
-            let filter = build_image_watermark_filter(config, image_path)?;
+            let filter =
+                build_image_watermark_filter(config, image_path, is_video, is_jpeg, keep_alpha)?;
+            args.push("-filter_complex".into());
+            args.push(filter);
+        }
+        WatermarkType::Video => {
+            let video_path = config.video_path.as_ref().ok_or_else(|| {
+                FfmpegError::InvalidConfig("video watermark requires video_path".into())
+            })?;
+            // FFmpeg's native VP9 decoder drops the alpha channel; libvpx keeps it
+            let is_webm = Path::new(video_path)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("webm"));
+            if is_webm {
+                args.push("-c:v".into());
+                args.push("libvpx-vp9".into());
+            }
+            args.push("-stream_loop".into());
+            args.push("-1".into());
+            args.push("-i".into());
+            args.push(Path::new(video_path).to_string_lossy().into_owned());
+            let filter =
+                build_video_watermark_filter(config, video_path, is_video, is_jpeg, keep_alpha)?;
             args.push("-filter_complex".into());
             args.push(filter);
         }
         WatermarkType::Text => {
-            let filter = build_text_watermark_filter(config, is_video)?;
+            let filter = build_text_watermark_filter(config, is_video, is_jpeg)?;
             args.push("-vf".into());
             args.push(filter);
         }
     }
 
-    if is_video {
-        args.push("-c:a".into());
-        args.push("copy".into());
-    } else {
-        args.push("-frames:v".into());
-        args.push("1".into());
+    Ok(())
+}
+
+pub async fn spawn_ffmpeg(app: &AppHandle, args: Vec<String>) -> Result<String, FfmpegError> {
+    spawn_ffmpeg_monitored(app, args, DEFAULT_STALL_TIMEOUT, None, None, false).await
+}
+
+/// Runs FFmpeg while watching its `-progress` output. If neither the frame
+/// count nor the output timestamp advances for `stall_timeout`, the process is
+/// killed and `FfmpegError::Stalled` is returned; if it is still running at
+/// `deadline`, it is killed and `FfmpegError::Timeout` is returned. With
+/// `cpu_affinity` the process is pinned to those cores, and with
+/// `low_priority` it runs below normal priority; failing to do either is not
+/// an error.
+pub async fn spawn_ffmpeg_monitored(
+    app: &AppHandle,
+    args: Vec<String>,
+    stall_timeout: Duration,
+    deadline: Option<Instant>,
+    cpu_affinity: Option<&[usize]>,
+    low_priority: bool,
+) -> Result<String, FfmpegError> {
+    let mut full_args: Vec<String> = vec!["-progress".into(), "pipe:1".into(), "-nostats".into()];
+    full_args.extend(args);
+
+    let (mut events, child) = ffmpeg_command(app, "ffmpeg")?
+        .args(full_args)
+        .spawn()
+        .map_err(|e| FfmpegError::Spawn(e.to_string()))?;
+
+    if let Some(cores) = cpu_affinity {
+        if let Err(e) = pin_process(child.pid(), cores) {
+            warn!(error = %e, "Failed to set CPU affinity of FFmpeg");
+        }
+    }
+    if low_priority {
+        if let Err(e) = lower_priority(child.pid()) {
+            warn!(error = %e, "Failed to lower the priority of FFmpeg");
+        }
     }
 
-    args.push("-y".into());
-    args.push(output_path.to_string_lossy().into_owned());
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    // A line can straddle two chunks; its start waits here for the rest
+    let mut stdout_line = String::new();
+    let mut stderr_line = String::new();
+    let mut last_frame = String::new();
+    let mut last_out_time = String::new();
+    let mut last_advance = Instant::now();
 
-    Ok(args)
+    loop {
+        let until_stall = stall_timeout.saturating_sub(last_advance.elapsed());
+        let until_deadline =
+            deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let remaining = until_deadline.map_or(until_stall, |left| left.min(until_stall));
+        let event = match tokio::time::timeout(remaining, events.recv()).await {
+            Ok(Some(event)) => event,
+            Ok(None) => {
+                return Err(FfmpegError::Execution(
+                    "FFmpeg exited without reporting a status".into(),
+                ))
+            }
+            Err(_) if until_deadline.is_some_and(|left| left <= until_stall) => {
+                let _ = child.kill();
+                return Err(FfmpegError::Timeout("the file took longer than its time limit".into()));
+            }
+            Err(_) => {
+                let _ = child.kill();
+                return Err(FfmpegError::Stalled(format!(
+                    "no progress for {} seconds",
+                    stall_timeout.as_secs()
+                )));
+            }
+        };
+
+        match event {
+            CommandEvent::Stdout(bytes) => {
+                let chunk = String::from_utf8_lossy(&bytes);
+                stdout_line.push_str(&chunk);
+                while let Some(end) = stdout_line.find('\n') {
+                    let line: String = stdout_line.drain(..=end).collect();
+                    let line = line.trim_end_matches(['\r', '\n']);
+                    let advanced = if let Some(frame) = line.strip_prefix("frame=") {
+                        update_marker(&mut last_frame, frame)
+                    } else if let Some(out_time) = line.strip_prefix("out_time_us=") {
+                        update_marker(&mut last_out_time, out_time)
+                    } else {
+                        false
+                    };
+                    if advanced {
+                        last_advance = Instant::now();
+                    }
+                }
+                stdout.push_str(&chunk);
+            }
+            CommandEvent::Stderr(bytes) => {
+                let chunk = String::from_utf8_lossy(&bytes);
+                stderr_line.push_str(&chunk);
+                while let Some(end) = stderr_line.find('\n') {
+                    let line: String = stderr_line.drain(..=end).collect();
+                    record_stderr_line(app, &line);
+                }
+                stderr.push_str(&chunk);
+            }
+            CommandEvent::Error(message) => {
+                return Err(FfmpegError::Execution(message));
+            }
+            CommandEvent::Terminated(payload) => {
+                record_stderr_line(app, &stderr_line);
+                if payload.code == Some(0) {
+                    return Ok(stdout);
+                }
+                return Err(FfmpegError::Execution(stderr));
+            }
+            _ => {}
+        }
+    }
 }
 
-pub async fn spawn_ffmpeg(app: &AppHandle, args: Vec<String>) -> Result<String, FfmpegError> {
-    let output = app
-        .shell()
-        .sidecar("ffmpeg")
-        .map_err(|e| FfmpegError::Spawn(e.to_string()))?
-        .args(args)
-        .output()
-        .await
-        .map_err(|e| FfmpegError::Execution(e.to_string()))?;
+/// Adds a complete stderr line, without its line ending, to the file log.
+fn record_stderr_line(app: &AppHandle, line: &str) {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if !line.trim().is_empty() {
+        file_log::record(app, line);
+    }
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        return Err(FfmpegError::Execution(stderr));
+/// Runs FFmpeg under the stall watchdog, retrying once in compatibility mode
+/// if it stalls. The retry has to finish by the same `deadline`.
+pub async fn spawn_ffmpeg_with_retry(
+    app: &AppHandle,
+    args: Vec<String>,
+    stall_timeout: Duration,
+    deadline: Option<Instant>,
+    cpu_affinity: Option<&[usize]>,
+    low_priority: bool,
+) -> Result<String, FfmpegError> {
+    let first = spawn_ffmpeg_monitored(
+        app,
+        args.clone(),
+        stall_timeout,
+        deadline,
+        cpu_affinity,
+        low_priority,
+    )
+    .await;
+    match first {
+        Err(FfmpegError::Stalled(reason)) => {
+            warn!(reason = %reason, "FFmpeg stalled, retrying in compatibility mode");
+            let args = compatibility_args(args);
+            spawn_ffmpeg_monitored(app, args, stall_timeout, deadline, cpu_affinity, low_priority)
+                .await
+        }
+        other => other,
     }
+}
 
-    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+/// Rewrites a command for a retry after a stall: single-threaded decoding that
+/// regenerates timestamps and skips corrupt packets instead of waiting on them.
+fn compatibility_args(args: Vec<String>) -> Vec<String> {
+    let mut compat: Vec<String> = vec![
+        "-threads".into(),
+        "1".into(),
+        "-err_detect".into(),
+        "ignore_err".into(),
+        "-fflags".into(),
+        "+genpts+discardcorrupt".into(),
+    ];
+    compat.extend(args);
+    compat
 }
 
+fn update_marker(marker: &mut String, value: &str) -> bool {
+    let value = value.trim();
+    if marker == value {
+        return false;
+    }
+    marker.clear();
+    marker.push_str(value);
+    true
+}
+
+/// Returns whether `path` is a video (`true`) or an image (`false`). The
+/// file's magic bytes decide; the extension is only used when the header is
+/// unreadable or not recognized.
 pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<bool, FfmpegError> {
+    if let Some(sniffed) = sniff(path.as_ref()) {
+        return match sniffed.kind {
+            MediaKind::Image => Ok(false),
+            MediaKind::Video => Ok(true),
+            MediaKind::Audio => Err(FfmpegError::UnsupportedFormat(
+                "audio file has no video or image stream".into(),
+            )),
+        };
+    }
+
     let extension = path
         .as_ref()
         .extension()
@@ -231,6 +1198,54 @@ pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<bool, FfmpegError> {
     }
 }
 
+/// Filter chain for the rotate/crop transform, applied to JPEG inputs only,
+/// followed by the output resize.
+fn transform_filter(config: &WatermarkConfig, is_jpeg: bool) -> Option<String> {
+    let mut filters = Vec::new();
+    let transform = config.jpeg_transform.as_ref().filter(|t| is_jpeg && !t.is_identity());
+    if let Some(transform) = transform {
+        match transform.rotate {
+            90 => filters.push("transpose=1".to_string()),
+            180 => filters.push("hflip,vflip".to_string()),
+            270 => filters.push("transpose=2".to_string()),
+            _ => {}
+        }
+        if let Some(crop) = &transform.crop {
+            filters.push(format!(
+                "crop={}:{}:{}:{}",
+                crop.width, crop.height, crop.x, crop.y
+            ));
+        }
+    }
+    if let Some(resize) = &config.resize {
+        filters.push(resize_filter(resize));
+    }
+
+    (!filters.is_empty()).then(|| filters.join(","))
+}
+
+/// Scale filter for `resize`. Fitted sizes are kept even, as video encoders
+/// need for chroma subsampling.
+fn resize_filter(resize: &OutputResize) -> String {
+    let flags = resize.filter.ffmpeg_name();
+    match (resize.mode, resize.width, resize.height) {
+        (ResizeMode::Exact, Some(width), Some(height)) => format!(
+            "scale={0}:{1}:force_original_aspect_ratio=increase:flags={2},crop={0}:{1}",
+            width, height, flags
+        ),
+        (_, Some(width), Some(height)) => format!(
+            concat!(
+                "scale=w='min(iw,{})':h='min(ih,{})':force_original_aspect_ratio=decrease:",
+                "force_divisible_by=2:flags={}"
+            ),
+            width, height, flags
+        ),
+        (_, Some(width), None) => format!("scale=w='min(iw,{})':h=-2:flags={}", width, flags),
+        (_, None, Some(height)) => format!("scale=w=-2:h='min(ih,{})':flags={}", height, flags),
+        (_, None, None) => "null".to_string(),
+    }
+}
+
 fn normalize_color(color: &str, opacity: u8) -> String {
     let alpha = (opacity as f32 / 100.0).clamp(0.0, 1.0);
     let base = if let Some(stripped) = color.strip_prefix('#') {
@@ -241,6 +1256,87 @@ fn normalize_color(color: &str, opacity: u8) -> String {
     format!("{}@{:.3}", base, alpha)
 }
 
+/// Timeline `enable` option limiting the watermark to `start_time..end_time`
+/// and, when intermittent, to the first `show_secs` of every cycle.
+/// Still images have no timeline, so they are always watermarked.
+fn enable_option(config: &WatermarkConfig, is_video: bool) -> String {
+    if !is_video {
+        return String::new();
+    }
+    let window = match (config.start_time, config.end_time) {
+        (Some(start), Some(end)) => Some(format!("between(t,{:.3},{:.3})", start, end)),
+        (Some(start), None) => Some(format!("gte(t,{:.3})", start)),
+        (None, Some(end)) => Some(format!("lte(t,{:.3})", end)),
+        (None, None) => None,
+    };
+    let mut conditions: Vec<String> = window.into_iter().collect();
+    // Cycles start with the window so the first showing lines up with it
+    if let Some(cycle) = &config.intermittent {
+        conditions.push(format!(
+            "lt(mod(t-{:.3},{:.3}),{:.3})",
+            config.start_time.unwrap_or(0.0),
+            cycle.every_secs,
+            cycle.show_secs
+        ));
+    }
+
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(":enable='{}'", conditions.join("*"))
+    }
+}
+
+/// `fade` filters for the watermark stream of an overlay. Fade-out is only
+/// possible once the end of the window is known.
+fn fade_filters(config: &WatermarkConfig, is_video: bool) -> Option<String> {
+    if !is_video {
+        return None;
+    }
+    let start = config.start_time.unwrap_or(0.0);
+    let mut filters = Vec::new();
+    if config.fade_in_secs > 0.0 {
+        filters.push(format!(
+            "fade=t=in:st={:.3}:d={:.3}:alpha=1",
+            start, config.fade_in_secs
+        ));
+    }
+    if let Some(end) = config.end_time.filter(|_| config.fade_out_secs > 0.0) {
+        filters.push(format!(
+            "fade=t=out:st={:.3}:d={:.3}:alpha=1",
+            (end - config.fade_out_secs as f64).max(start),
+            config.fade_out_secs
+        ));
+    }
+    (!filters.is_empty()).then(|| filters.join(","))
+}
+
+/// drawtext `alpha` expression ramping the text in and out, the text
+/// counterpart of `fade_filters`.
+fn fade_alpha_expression(config: &WatermarkConfig, is_video: bool) -> Option<String> {
+    if !is_video {
+        return None;
+    }
+    let start = config.start_time.unwrap_or(0.0);
+    let mut ramps = Vec::new();
+    if config.fade_in_secs > 0.0 {
+        ramps.push(format!("clip((t-{:.3})/{:.3},0,1)", start, config.fade_in_secs));
+    }
+    if let Some(end) = config.end_time.filter(|_| config.fade_out_secs > 0.0) {
+        ramps.push(format!("clip(({:.3}-t)/{:.3},0,1)", end, config.fade_out_secs));
+    }
+    ramps
+        .into_iter()
+        .reduce(|fade_in, fade_out| format!("min({},{})", fade_in, fade_out))
+}
+
+fn normalize_key_color(color: &str) -> String {
+    match color.trim().strip_prefix('#') {
+        Some(stripped) => format!("0x{}", stripped),
+        None => color.trim().to_string(),
+    }
+}
+
 fn text_position_expression(config: &WatermarkConfig) -> (String, String) {
     if config.is_custom_position() {
         if let Some(custom_pos) = &config.custom_position {
@@ -251,18 +1347,49 @@ fn text_position_expression(config: &WatermarkConfig) -> (String, String) {
     }
     
     // Fallback to preset positioning
-    let (x_static, y_static) = match config.position {
-        WatermarkPosition::TopLeft => ("20", "20"),
-        WatermarkPosition::TopCenter => ("(w-text_w)/2", "20"),
-        WatermarkPosition::TopRight => ("w-text_w-20", "20"),
-        WatermarkPosition::CenterLeft => ("20", "(h-text_h)/2"),
-        WatermarkPosition::Center => ("(w-text_w)/2", "(h-text_h)/2"),
-        WatermarkPosition::CenterRight => ("w-text_w-20", "(h-text_h)/2"),
-        WatermarkPosition::BottomLeft => ("20", "h-text_h-20"),
-        WatermarkPosition::BottomCenter => ("(w-text_w)/2", "h-text_h-20"),
-        WatermarkPosition::BottomRight => ("w-text_w-20", "h-text_h-20"),
+    preset_position_expression(config, "w", "h", "text_w", "text_h")
+}
+
+/// Time-based drawtext x/y expressions for `MotionMode`. Drift bounces on
+/// both axes with triangle waves of different speeds so the path does not
+/// repeat quickly.
+fn text_motion_expression(
+    config: &WatermarkConfig,
+    x_expr: String,
+    y_expr: String,
+) -> (String, String) {
+    let speed = config.motion_speed.max(1.0);
+    match config.motion {
+        MotionMode::Static => (x_expr, y_expr),
+        MotionMode::Ticker => (format!("w-mod(t*{:.3},w+text_w)", speed), y_expr),
+        MotionMode::Drift => (
+            format!("abs(mod(t*{:.3},2*(w-text_w))-(w-text_w))", speed),
+            format!("abs(mod(t*{:.3},2*(h-text_h))-(h-text_h))", speed * 0.7),
+        ),
+    }
+}
+
+/// Expressions that move the watermark to a new pseudo-random spot every
+/// `jitter_interval_secs`, so a static delogo region never covers it. The
+/// spot is a hash of the interval index, stable within the interval.
+fn jitter_position_expression(
+    config: &WatermarkConfig,
+    frame_w: &str,
+    frame_h: &str,
+    mark_w: &str,
+    mark_h: &str,
+) -> Option<(String, String)> {
+    let interval = config.jitter_interval_secs.filter(|secs| *secs > 0.0)?;
+    let hash = |seed: f32| {
+        format!(
+            "mod(abs(sin(floor(t/{:.3})*12.9898+{:.4}))*43758.5453,1)",
+            interval, seed
+        )
     };
-    (x_static.to_string(), y_static.to_string())
+    Some((
+        format!("({}-{})*{}", frame_w, mark_w, hash(78.233)),
+        format!("({}-{})*{}", frame_h, mark_h, hash(39.346)),
+    ))
 }
 
 fn overlay_position_expression(config: &WatermarkConfig) -> (String, String) {
@@ -275,24 +1402,52 @@ fn overlay_position_expression(config: &WatermarkConfig) -> (String, String) {
     }
     
     // Fallback to preset positioning
-    let (x_static, y_static) = match config.position {
-        WatermarkPosition::TopLeft => ("20", "20"),
-        WatermarkPosition::TopCenter => ("(W-w)/2", "20"),
-        WatermarkPosition::TopRight => ("W-w-20", "20"),
-        WatermarkPosition::CenterLeft => ("20", "(H-h)/2"),
-        WatermarkPosition::Center => ("(W-w)/2", "(H-h)/2"),
-        WatermarkPosition::CenterRight => ("W-w-20", "(H-h)/2"),
-        WatermarkPosition::BottomLeft => ("20", "H-h-20"),
-        WatermarkPosition::BottomCenter => ("(W-w)/2", "H-h-20"),
-        WatermarkPosition::BottomRight => ("W-w-20", "H-h-20"),
+    preset_position_expression(config, "W", "H", "w", "h")
+}
+
+/// Builds x/y expressions for a preset position. The variable names differ
+/// between drawtext (`w`, `text_w`) and overlay (`W`, `w`), so they are passed in.
+fn preset_position_expression(
+    config: &WatermarkConfig,
+    frame_w: &str,
+    frame_h: &str,
+    mark_w: &str,
+    mark_h: &str,
+) -> (String, String) {
+    let (margin_x, margin_y) = match config.margin_unit {
+        MarginUnit::Px => (config.margin_x.to_string(), config.margin_y.to_string()),
+        MarginUnit::Percent => (
+            format!("{}*{:.6}", frame_w, config.margin_x / 100.0),
+            format!("{}*{:.6}", frame_h, config.margin_y / 100.0),
+        ),
     };
-    (x_static.to_string(), y_static.to_string())
+
+    let left = margin_x.clone();
+    let center_x = format!("({}-{})/2", frame_w, mark_w);
+    let right = format!("{}-{}-{}", frame_w, mark_w, margin_x);
+    let top = margin_y.clone();
+    let center_y = format!("({}-{})/2", frame_h, mark_h);
+    let bottom = format!("{}-{}-{}", frame_h, mark_h, margin_y);
+
+    match config.position {
+        WatermarkPosition::TopLeft => (left, top),
+        WatermarkPosition::TopCenter => (center_x, top),
+        WatermarkPosition::TopRight => (right, top),
+        WatermarkPosition::CenterLeft => (left, center_y),
+        WatermarkPosition::Center => (center_x, center_y),
+        WatermarkPosition::CenterRight => (right, center_y),
+        WatermarkPosition::BottomLeft => (left, bottom),
+        WatermarkPosition::BottomCenter => (center_x, bottom),
+        WatermarkPosition::BottomRight => (right, bottom),
+    }
 }
 
+/// Extracts the frame at `timestamp` seconds, or the first frame, as a JPEG.
 pub async fn extract_video_thumbnail(
     app: &AppHandle,
     video_path: &Path,
     output_path: &Path,
+    timestamp: Option<f64>,
 ) -> Result<PathBuf, FfmpegError> {
     // Ensure FFmpeg is available
     let _ = get_ffmpeg_sidecar_path(app)?;
@@ -315,6 +1470,10 @@ pub async fn extract_video_thumbnail(
 
     // Build FFmpeg arguments for thumbnail extraction
     let mut args = Vec::new();
+    if let Some(timestamp) = timestamp {
+        args.push("-ss".into());
+        args.push(format!("{:.3}", timestamp));
+    }
     args.push("-i".into());
     args.push(video_path.to_string_lossy().into_owned());
     args.push("-frames:v".into());
@@ -329,3 +1488,126 @@ pub async fn extract_video_thumbnail(
 
     Ok(output_path.to_path_buf())
 }
+
+/// Renders `frame_count` evenly spaced frames, each labelled with its
+/// timestamp, into a single `columns`x`rows` tiled image.
+pub async fn extract_scrub_strip(
+    app: &AppHandle,
+    video_path: &Path,
+    output_path: &Path,
+    frame_count: u32,
+    duration: f64,
+    columns: u32,
+    rows: u32,
+) -> Result<PathBuf, FfmpegError> {
+    let _ = get_ffmpeg_sidecar_path(app)?;
+
+    if !detect_file_type(video_path)? {
+        return Err(FfmpegError::UnsupportedFormat(
+            "File is not a video".into(),
+        ));
+    }
+
+    let filter = format!(
+        "fps={}/{:.3},scale=320:-2,drawtext=text='%{{pts\\:hms}}':fontsize=18:fontcolor=white:box=1:boxcolor=black@0.6:x=6:y=h-th-6,tile={}x{}",
+        frame_count,
+        duration,
+        columns,
+        rows
+    );
+
+    let args = vec![
+        "-i".to_string(),
+        video_path.to_string_lossy().into_owned(),
+        "-vf".to_string(),
+        filter,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-q:v".to_string(),
+        "4".to_string(),
+        "-y".to_string(),
+        output_path.to_string_lossy().into_owned(),
+    ];
+
+    spawn_ffmpeg(app, args).await?;
+
+    Ok(output_path.to_path_buf())
+}
+
+/// Extracts the frame at `timestamp` seconds at filmstrip size.
+pub async fn extract_filmstrip_frame(
+    app: &AppHandle,
+    video_path: &Path,
+    output_path: &Path,
+    timestamp: f64,
+) -> Result<PathBuf, FfmpegError> {
+    let _ = get_ffmpeg_sidecar_path(app)?;
+
+    if !detect_file_type(video_path)? {
+        return Err(FfmpegError::UnsupportedFormat(
+            "File is not a video".into(),
+        ));
+    }
+
+    let args = vec![
+        "-ss".to_string(),
+        format!("{:.3}", timestamp),
+        "-i".to_string(),
+        video_path.to_string_lossy().into_owned(),
+        "-vf".to_string(),
+        format!("scale={}:-2", FILMSTRIP_FRAME_WIDTH),
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-q:v".to_string(),
+        "4".to_string(),
+        "-y".to_string(),
+        output_path.to_string_lossy().into_owned(),
+    ];
+
+    spawn_ffmpeg(app, args).await?;
+
+    Ok(output_path.to_path_buf())
+}
+
+/// Renders `frame_count` frames at filmstrip size into a single row, one from
+/// the middle of each equal part of the video.
+pub async fn extract_filmstrip_sheet(
+    app: &AppHandle,
+    video_path: &Path,
+    output_path: &Path,
+    frame_count: u32,
+    duration: f64,
+) -> Result<PathBuf, FfmpegError> {
+    let _ = get_ffmpeg_sidecar_path(app)?;
+
+    if !detect_file_type(video_path)? {
+        return Err(FfmpegError::UnsupportedFormat(
+            "File is not a video".into(),
+        ));
+    }
+
+    let interval = duration / frame_count as f64;
+    let filter = format!(
+        "fps={}/{:.3},scale={}:-2,tile={}x1",
+        frame_count, duration, FILMSTRIP_FRAME_WIDTH, frame_count
+    );
+
+    let args = vec![
+        "-ss".to_string(),
+        format!("{:.3}", interval / 2.0),
+        "-i".to_string(),
+        video_path.to_string_lossy().into_owned(),
+        "-vf".to_string(),
+        filter,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-q:v".to_string(),
+        "4".to_string(),
+        "-y".to_string(),
+        output_path.to_string_lossy().into_owned(),
+    ];
+
+    spawn_ffmpeg(app, args).await?;
+
+    Ok(output_path.to_path_buf())
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::AsyncWriteExt;
+
+use crate::types::DownloadedFfmpeg;
+
+/// Lists the static FFmpeg builds per platform, published on the rolling
+/// `ffmpeg` release next to the binaries themselves.
+const MANIFEST_URL: &str =
+    "https://github.com/kehhhh/Bulk-Watermark/releases/download/ffmpeg/ffmpeg.json";
+
+/// Folder in the app data directory the downloaded binaries are installed in.
+const INSTALL_DIR: &str = "ffmpeg";
+
+const BINARIES: [&str; 2] = ["ffmpeg", "ffprobe"];
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    version: String,
+    /// Builds by `{os}-{arch}`, as in `std::env::consts`
+    platforms: HashMap<String, HashMap<String, Asset>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    url: String,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress {
+    binary: String,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+/// Path of a downloaded `name` ("ffmpeg" or "ffprobe"), if it is installed.
+pub fn installed_path(app: &AppHandle, name: &str) -> Option<PathBuf> {
+    let path = install_dir(app)?.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+    path.is_file().then_some(path)
+}
+
+/// Downloads the FFmpeg and ffprobe builds for this platform into the app
+/// data directory, emitting `ffmpeg-download-progress` as it goes. Each
+/// binary is checked against the SHA-256 in the manifest before it replaces
+/// an earlier download.
+pub async fn download(app: &AppHandle) -> Result<DownloadedFfmpeg, String> {
+    let platform = format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+    let manifest: Manifest = reqwest::get(MANIFEST_URL)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch the FFmpeg manifest: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid FFmpeg manifest: {}", e))?;
+    let assets = manifest
+        .platforms
+        .get(&platform)
+        .ok_or_else(|| format!("No FFmpeg download is available for {}", platform))?;
+
+    let dir = install_dir(app).ok_or("Failed to resolve the app data directory")?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut paths = Vec::with_capacity(BINARIES.len());
+    for name in BINARIES {
+        let asset = assets
+            .get(name)
+            .ok_or_else(|| format!("The FFmpeg download for {} has no {}", platform, name))?;
+        let path = dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+        download_binary(app, name, asset, &path).await?;
+        paths.push(path);
+    }
+
+    let ffprobe_path = paths.pop().unwrap_or_default();
+    let ffmpeg_path = paths.pop().unwrap_or_default();
+    Ok(DownloadedFfmpeg {
+        version: manifest.version,
+        ffmpeg_path,
+        ffprobe_path,
+    })
+}
+
+fn install_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(INSTALL_DIR))
+}
+
+/// Streams `asset` to a partial file next to `target`, renaming it into place
+/// once the checksum matches.
+async fn download_binary(
+    app: &AppHandle,
+    name: &str,
+    asset: &Asset,
+    target: &Path,
+) -> Result<(), String> {
+    let mut response = reqwest::get(&asset.url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", name, e))?;
+    let total = response.content_length();
+
+    let partial = target.with_extension("part");
+    let mut file = tokio::fs::File::create(&partial)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut downloaded = 0u64;
+    let written: Result<(), String> = async {
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", name, e))?
+        {
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+            downloaded += chunk.len() as u64;
+            let progress = DownloadProgress {
+                binary: name.to_string(),
+                downloaded,
+                total,
+            };
+            let _ = app.emit_to("main", "ffmpeg-download-progress", progress);
+        }
+        file.flush()
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))
+    }
+    .await;
+    drop(file);
+
+    let verified = written.and_then(|()| {
+        let digest = format!("{:x}", hasher.finalize());
+        if digest.eq_ignore_ascii_case(&asset.sha256) {
+            Ok(())
+        } else {
+            Err(format!("The downloaded {} does not match its checksum", name))
+        }
+    });
+    if let Err(e) = verified {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755))
+            .await
+            .map_err(|e| format!("Failed to make {} executable: {}", name, e))?;
+    }
+    tokio::fs::rename(&partial, target)
+        .await
+        .map_err(|e| format!("Failed to install {}: {}", name, e))
+}
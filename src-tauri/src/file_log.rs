@@ -0,0 +1,108 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Files whose FFmpeg logs are kept; older logs are dropped first.
+const MAX_FILE_LOGS: usize = 200;
+
+tokio::task_local! {
+    static CURRENT: Arc<FileLog>;
+}
+
+/// FFmpeg output of the file being processed on the current task.
+#[derive(Debug)]
+pub struct FileLog {
+    input_path: PathBuf,
+    lines: Mutex<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileLogLine<'a> {
+    input_path: &'a Path,
+    line: &'a str,
+}
+
+/// Logs of the most recently processed files by input path, managed as
+/// Tauri state.
+#[derive(Debug, Default)]
+pub struct FileLogs {
+    logs: Mutex<FileLogEntries>,
+}
+
+#[derive(Debug, Default)]
+struct FileLogEntries {
+    by_path: HashMap<PathBuf, String>,
+    order: VecDeque<PathBuf>,
+}
+
+impl FileLogs {
+    /// The FFmpeg output of the last time `input_path` was processed.
+    pub fn get(&self, input_path: &Path) -> Option<String> {
+        let logs = self.logs.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        logs.by_path.get(input_path).cloned()
+    }
+
+    fn insert(&self, input_path: PathBuf, log: String) {
+        let mut logs = self.logs.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if logs.by_path.insert(input_path.clone(), log).is_some() {
+            logs.order.retain(|path| *path != input_path);
+        }
+        logs.order.push_back(input_path);
+        while logs.order.len() > MAX_FILE_LOGS {
+            if let Some(oldest) = logs.order.pop_front() {
+                logs.by_path.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Runs `future`, which processes `input_path`, recording the stderr of
+/// every FFmpeg it runs as that file's log.
+pub async fn capture<F: Future>(app: &AppHandle, input_path: &Path, future: F) -> F::Output {
+    let log = Arc::new(FileLog {
+        input_path: input_path.to_path_buf(),
+        lines: Mutex::new(Vec::new()),
+    });
+    let output = CURRENT.scope(log.clone(), future).await;
+    let lines = log.lines.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    app.state::<FileLogs>().insert(input_path.to_path_buf(), lines.join("\n"));
+    output
+}
+
+/// The log of the file processed on the current task, to carry into tasks it
+/// spawns with `within`.
+pub fn current() -> Option<Arc<FileLog>> {
+    CURRENT.try_with(Arc::clone).ok()
+}
+
+/// Runs `future` recording into `log`, when there is one.
+pub async fn within<F: Future>(log: Option<Arc<FileLog>>, future: F) -> F::Output {
+    match log {
+        Some(log) => CURRENT.scope(log, future).await,
+        None => future.await,
+    }
+}
+
+/// Adds an FFmpeg stderr line to the current file's log and emits it as
+/// `watermark-file-log`. Lines from FFmpeg runs outside a file are dropped.
+pub fn record(app: &AppHandle, line: &str) {
+    let _ = CURRENT.try_with(|log| {
+        log.lines
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(line.to_string());
+        let _ = app.emit_to(
+            "main",
+            "watermark-file-log",
+            FileLogLine {
+                input_path: &log.input_path,
+                line,
+            },
+        );
+    });
+}
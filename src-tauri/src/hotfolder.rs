@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::warn;
+
+use crate::cache_db::CacheDatabase;
+use crate::commands::{build_output_path, process_file_internal, processed_file_result};
+use crate::ffmpeg::detect_file_type;
+use crate::jobs::BatchHistory;
+use crate::types::WatermarkConfig;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// Screenshot tools write the file in several steps and screen recordings grow
+// until they stop; wait until it settles
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+struct ActiveWatch {
+    watch_dir: PathBuf,
+    stop: Arc<AtomicBool>,
+}
+
+/// The currently running hot-folder watcher, managed as Tauri state.
+#[derive(Default)]
+pub struct HotFolderState {
+    active: Mutex<Option<ActiveWatch>>,
+}
+
+impl HotFolderState {
+    /// Starts watching `watch_dir`, replacing any watcher that is already running.
+    pub fn start(
+        &self,
+        app: AppHandle,
+        watch_dir: PathBuf,
+        output_dir: PathBuf,
+        config: WatermarkConfig,
+    ) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let previous = self
+            .active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .replace(ActiveWatch {
+                watch_dir: watch_dir.clone(),
+                stop: stop.clone(),
+            });
+        if let Some(previous) = previous {
+            previous.stop.store(true, Ordering::Relaxed);
+        }
+
+        tauri::async_runtime::spawn(watch_loop(app, watch_dir, output_dir, config, stop));
+    }
+
+    /// Stops the running watcher, returning the directory it was watching.
+    pub fn stop(&self) -> Option<PathBuf> {
+        let active = self
+            .active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()?;
+        active.stop.store(true, Ordering::Relaxed);
+        Some(active.watch_dir)
+    }
+
+    pub fn watch_dir(&self) -> Option<PathBuf> {
+        self.active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+            .map(|active| active.watch_dir.clone())
+    }
+}
+
+/// Resolves the directory the OS saves screenshots to by default.
+pub fn default_screenshot_dir(app: &AppHandle) -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        // Respect a custom location set via `defaults write com.apple.screencapture location`
+        if let Ok(output) = std::process::Command::new("defaults")
+            .args(["read", "com.apple.screencapture", "location"])
+            .output()
+        {
+            if output.status.success() {
+                let location = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                if !location.is_empty() && Path::new(&location).is_dir() {
+                    return Some(PathBuf::from(location));
+                }
+            }
+        }
+        app.path().desktop_dir().ok()
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let pictures = app.path().picture_dir().ok()?;
+        let screenshots = pictures.join("Screenshots");
+        if screenshots.is_dir() {
+            Some(screenshots)
+        } else {
+            Some(pictures)
+        }
+    }
+}
+
+async fn watch_loop(
+    app: AppHandle,
+    watch_dir: PathBuf,
+    output_dir: PathBuf,
+    config: WatermarkConfig,
+    stop: Arc<AtomicBool>,
+) {
+    // Only captures taken after the watcher starts are watermarked
+    let mut seen: HashSet<PathBuf> = list_captures(&watch_dir).into_iter().collect();
+    // Everything one watcher processes is a single job in the history
+    let job_id = app.state::<BatchHistory>().next_job_id();
+
+    while !stop.load(Ordering::Relaxed) {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        for path in list_captures(&watch_dir) {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            if seen.contains(&path) || !is_settled(&path) {
+                continue;
+            }
+            seen.insert(path.clone());
+
+            let output_path = build_output_path(&output_dir, &path, &config, None);
+            let started = Instant::now();
+            let result = process_file_internal(&app, &path, &output_path, &config, None).await;
+            let file_result = processed_file_result(&path, &output_path, &config, &result, started);
+            app.state::<CacheDatabase>().record_file(&app, &job_id, &file_result, &config);
+
+            let _ = app.emit_to("main", "hot-folder-processed", &file_result);
+        }
+    }
+}
+
+/// Screenshots and screen recordings in `dir`; audio and unknown files are
+/// left alone.
+fn list_captures(dir: &Path) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(path = %dir.display(), error = %e, "Failed to read hot folder");
+            return Vec::new();
+        }
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && detect_file_type(path).is_ok())
+        .collect()
+}
+
+fn is_settled(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= SETTLE_TIME)
+}
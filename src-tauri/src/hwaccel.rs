@@ -0,0 +1,217 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use crate::ffmpeg::{append_filter, spawn_ffmpeg, FfmpegError};
+use crate::types::{EncodingOptions, HardwareAcceleration, VideoCodec, WatermarkConfig};
+
+const VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+/// Containers re-encoded with H.264, the codec every hardware encoder offers.
+/// Other outputs (WebM, GIF) stay on the CPU.
+const H264_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "mkv"];
+
+/// Hardware H.264 encoders, in the order they are preferred.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HardwareEncoder {
+    Nvenc,
+    VideoToolbox,
+    Qsv,
+    Amf,
+    Vaapi,
+}
+
+impl HardwareEncoder {
+    const ALL: [HardwareEncoder; 5] = [
+        HardwareEncoder::Nvenc,
+        HardwareEncoder::VideoToolbox,
+        HardwareEncoder::Qsv,
+        HardwareEncoder::Amf,
+        HardwareEncoder::Vaapi,
+    ];
+
+    fn codec(self) -> &'static str {
+        match self {
+            HardwareEncoder::Nvenc => "h264_nvenc",
+            HardwareEncoder::VideoToolbox => "h264_videotoolbox",
+            HardwareEncoder::Qsv => "h264_qsv",
+            HardwareEncoder::Amf => "h264_amf",
+            HardwareEncoder::Vaapi => "h264_vaapi",
+        }
+    }
+
+    /// Arguments placed before the main input: hardware decoding, plus the
+    /// device VAAPI uploads frames to.
+    fn input_args(self) -> Vec<String> {
+        let hwaccel = match self {
+            HardwareEncoder::Nvenc => "cuda",
+            HardwareEncoder::VideoToolbox => "videotoolbox",
+            HardwareEncoder::Qsv => "qsv",
+            HardwareEncoder::Amf => "d3d11va",
+            HardwareEncoder::Vaapi => "vaapi",
+        };
+        let mut args = vec!["-hwaccel".to_string(), hwaccel.to_string()];
+        if self == HardwareEncoder::Vaapi {
+            args.extend([
+                "-init_hw_device".to_string(),
+                format!("vaapi=hw:{}", VAAPI_DEVICE),
+                "-filter_hw_device".to_string(),
+                "hw".to_string(),
+            ]);
+        }
+        args
+    }
+
+    /// Filters appended to the watermark graph. The watermark is drawn in
+    /// system memory; only VAAPI needs the result uploaded to the GPU.
+    fn filter_suffix(self) -> Option<&'static str> {
+        match self {
+            HardwareEncoder::Vaapi => Some("format=nv12,hwupload"),
+            _ => None,
+        }
+    }
+
+    /// Encoder and its closest equivalent of `crf` (libx264's default 23
+    /// when unset), or a target bitrate.
+    fn output_args(self, encoding: Option<&EncodingOptions>) -> Vec<String> {
+        let mut args = vec!["-c:v".to_string(), self.codec().to_string()];
+        if let Some(bitrate) = encoding.and_then(|encoding| encoding.bitrate_kbps) {
+            args.extend(["-b:v".to_string(), format!("{}k", bitrate)]);
+            return args;
+        }
+
+        let crf = encoding.and_then(|encoding| encoding.crf).unwrap_or(23);
+        let qp = crf.to_string();
+        let quality: Vec<String> = match self {
+            HardwareEncoder::Nvenc => vec!["-rc".into(), "vbr".into(), "-cq".into(), qp],
+            // VideoToolbox quality runs from 1 to 100, higher is better
+            HardwareEncoder::VideoToolbox => {
+                let quality = 100 - u32::from(crf.min(51)) * 100 / 51;
+                vec!["-q:v".into(), quality.max(1).to_string()]
+            }
+            HardwareEncoder::Qsv => vec!["-global_quality".into(), qp],
+            HardwareEncoder::Amf => {
+                vec!["-rc".into(), "cqp".into(), "-qp_i".into(), qp.clone(), "-qp_p".into(), qp]
+            }
+            HardwareEncoder::Vaapi => vec!["-qp".into(), qp],
+        };
+        args.extend(quality);
+        args
+    }
+}
+
+/// Hardware encoders that work on this machine, detected once and managed as
+/// Tauri state.
+#[derive(Debug, Default)]
+pub struct HardwareEncoders {
+    detected: Mutex<Option<Vec<HardwareEncoder>>>,
+}
+
+impl HardwareEncoders {
+    fn cached(&self) -> Option<Vec<HardwareEncoder>> {
+        self.detected.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// Forgets the detected encoders, so the next `detect` probes again.
+    pub fn reset(&self) {
+        *self.detected.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+}
+
+/// Returns the hardware encoders FFmpeg can use, probing on first call. An
+/// encoder counts only if FFmpeg lists it and a short test encode succeeds,
+/// since builds list encoders whose driver or GPU is missing.
+pub async fn detect(app: &AppHandle) -> Vec<HardwareEncoder> {
+    let state = app.state::<HardwareEncoders>();
+    if let Some(encoders) = state.cached() {
+        return encoders;
+    }
+
+    let listed = match spawn_ffmpeg(app, vec!["-hide_banner".into(), "-encoders".into()]).await {
+        Ok(output) => output,
+        Err(e) => {
+            warn!(error = %e, "Failed to list FFmpeg encoders");
+            String::new()
+        }
+    };
+    let mut encoders = Vec::new();
+    for encoder in HardwareEncoder::ALL {
+        let is_listed = listed
+            .lines()
+            .any(|line| line.split_whitespace().nth(1) == Some(encoder.codec()));
+        if is_listed && test_encode(app, encoder).await {
+            encoders.push(encoder);
+        }
+    }
+
+    let mut detected = state.detected.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    *detected = Some(encoders.clone());
+    encoders
+}
+
+async fn test_encode(app: &AppHandle, encoder: HardwareEncoder) -> bool {
+    let mut args = encoder.input_args();
+    args.extend([
+        "-f".to_string(),
+        "lavfi".to_string(),
+        "-i".to_string(),
+        "color=c=black:s=256x256:d=0.1".to_string(),
+    ]);
+    if let Some(suffix) = encoder.filter_suffix() {
+        args.extend(["-vf".to_string(), suffix.to_string()]);
+    }
+    args.extend(encoder.output_args(None));
+    args.extend(["-frames:v", "1", "-f", "null", "-"].map(String::from));
+    spawn_ffmpeg(app, args).await.is_ok()
+}
+
+/// Picks the encoder for writing `output_path` from the encoders detected so
+/// far. `Force` fails when there is none; `Auto` falls back to the CPU.
+pub fn select(
+    app: &AppHandle,
+    config: &WatermarkConfig,
+    output_path: &Path,
+) -> Result<Option<HardwareEncoder>, FfmpegError> {
+    if config.hardware_acceleration == HardwareAcceleration::Off {
+        return Ok(None);
+    }
+    let is_h264 = output_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| H264_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        && config.encoding.as_ref().is_none_or(|encoding| encoding.codec == VideoCodec::H264);
+    let encoder = app
+        .try_state::<HardwareEncoders>()
+        .and_then(|state| state.cached())
+        .and_then(|encoders| encoders.first().copied())
+        .filter(|_| is_h264);
+
+    match (config.hardware_acceleration, encoder) {
+        (HardwareAcceleration::Force, None) if !is_h264 => Err(FfmpegError::UnsupportedFormat(
+            "hardware encoding only supports H.264 in MP4, MOV and MKV output".into(),
+        )),
+        (HardwareAcceleration::Force, None) => Err(FfmpegError::InvalidConfig(
+            "no working hardware encoder was found".into(),
+        )),
+        (_, encoder) => Ok(encoder),
+    }
+}
+
+/// Adds the encoder's flags around already built watermark arguments:
+/// decoding flags before the first input, an upload after the last filter
+/// and the encoder before the output options.
+pub fn apply(
+    encoder: HardwareEncoder,
+    encoding: Option<&EncodingOptions>,
+    args: &mut Vec<String>,
+) {
+    if let Some(suffix) = encoder.filter_suffix() {
+        append_filter(args, suffix);
+    }
+    args.extend(encoder.output_args(encoding));
+    args.splice(0..0, encoder.input_args());
+}
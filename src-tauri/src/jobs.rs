@@ -0,0 +1,106 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::mapping::TextMapping;
+use crate::types::{BatchResult, FileItem, WatermarkConfig};
+
+/// Finished batches kept for `get_batch_results`; older ones are dropped.
+const HISTORY_LIMIT: usize = 20;
+
+/// A sampled batch waiting for the user to approve or discard the sample output.
+#[derive(Debug, Clone)]
+pub struct PendingJob {
+    pub remaining: Vec<FileItem>,
+    /// Position of every remaining file in the batch, for `{counter}`
+    pub remaining_sequences: Vec<u64>,
+    pub config: WatermarkConfig,
+    pub text_mapping: Option<TextMapping>,
+    pub output_dir: PathBuf,
+    pub sample_result: BatchResult,
+    /// ID the whole batch is recorded under in `BatchHistory` and the
+    /// processing history, unlike the key of the pending job
+    pub batch_job_id: String,
+}
+
+/// Pending jobs keyed by job ID, managed as Tauri state.
+#[derive(Debug, Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, PendingJob>>,
+    next_id: AtomicU64,
+}
+
+impl JobRegistry {
+    pub fn insert(&self, job: PendingJob) -> String {
+        let job_id = self.generate_id();
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(job_id.clone(), job);
+        job_id
+    }
+
+    pub fn take(&self, job_id: &str) -> Option<PendingJob> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(job_id)
+    }
+
+    /// Puts back a job taken with `take` that could not be continued yet.
+    pub fn restore(&self, job_id: String, job: PendingJob) {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(job_id, job);
+    }
+
+    fn generate_id(&self) -> String {
+        generate_id(&self.next_id)
+    }
+}
+
+/// Results of the most recent finished batches keyed by job ID, managed as
+/// Tauri state, so huge results can be paged instead of sent in one piece.
+#[derive(Debug, Default)]
+pub struct BatchHistory {
+    batches: Mutex<VecDeque<(String, BatchResult)>>,
+    next_id: AtomicU64,
+}
+
+impl BatchHistory {
+    /// Hands out the job ID of a batch before it starts, so its files can be
+    /// added to the processing history as they finish.
+    pub fn next_job_id(&self) -> String {
+        generate_id(&self.next_id)
+    }
+
+    /// Stores a finished batch under `job_id`.
+    pub fn record(&self, job_id: &str, result: &BatchResult) {
+        let mut batches = self.batches.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if batches.len() >= HISTORY_LIMIT {
+            batches.pop_front();
+        }
+        batches.push_back((job_id.to_string(), result.clone()));
+    }
+
+    pub fn with_result<T>(&self, job_id: &str, read: impl FnOnce(&BatchResult) -> T) -> Option<T> {
+        self.batches
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .find(|(id, _)| id == job_id)
+            .map(|(_, result)| read(result))
+    }
+}
+
+fn generate_id(next_id: &AtomicU64) -> String {
+    let sequence = next_id.fetch_add(1, Ordering::Relaxed);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("job-{:x}-{}", millis, sequence)
+}
@@ -0,0 +1,362 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use crate::mapping::TextMapping;
+use crate::types::{FileItem, InterruptedBatch, InterruptedFile, ProcessingStatus, WatermarkConfig};
+
+/// One line of a batch journal. Entries are appended and flushed to disk
+/// before the step they describe is carried out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum JournalEntry {
+    #[serde(rename_all = "camelCase")]
+    BatchStarted {
+        started_at: String,
+        files: Vec<FileItem>,
+        config: WatermarkConfig,
+        text_mapping: Option<TextMapping>,
+        output_dir: PathBuf,
+        /// Position of every file within the whole batch, for `{counter}`
+        #[serde(default)]
+        sequences: Vec<u64>,
+        /// Position of the first file, written by versions before `sequences`
+        #[serde(default, skip_serializing)]
+        first_sequence: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    FileStarted {
+        index: usize,
+        /// Every output the file writes, one per export variant
+        #[serde(default)]
+        output_paths: Vec<PathBuf>,
+        /// The only output journaled by versions before `output_paths`
+        #[serde(default, skip_serializing)]
+        output_path: Option<PathBuf>,
+    },
+    #[serde(rename_all = "camelCase")]
+    FileFinished { index: usize, status: ProcessingStatus },
+    BatchFinished,
+}
+
+/// Batch IDs whose journals belong to a batch running in this process,
+/// managed as Tauri state so they are not reported as interrupted.
+#[derive(Debug, Default)]
+pub struct JournalRegistry {
+    active: Mutex<HashSet<String>>,
+    next_id: AtomicU64,
+}
+
+/// Append-only write-ahead journal for one `run_batch` call. The file is
+/// removed once the batch finishes; one left behind means the app stopped
+/// mid-batch.
+pub struct BatchJournal {
+    app: AppHandle,
+    batch_id: String,
+    path: PathBuf,
+    file: File,
+}
+
+impl BatchJournal {
+    pub fn create(
+        app: &AppHandle,
+        files: &[FileItem],
+        sequences: &[u64],
+        config: &WatermarkConfig,
+        text_mapping: Option<&TextMapping>,
+        output_dir: &Path,
+    ) -> std::io::Result<Self> {
+        let registry = app.state::<JournalRegistry>();
+        let batch_id = format!(
+            "batch-{:x}-{}",
+            Utc::now().timestamp_millis(),
+            registry.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+        let dir = journal_dir(app)?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.jsonl", batch_id));
+        let file = OpenOptions::new().create_new(true).append(true).open(&path)?;
+
+        registry
+            .active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(batch_id.clone());
+
+        let mut journal = Self {
+            app: app.clone(),
+            batch_id,
+            path,
+            file,
+        };
+        journal.append(&JournalEntry::BatchStarted {
+            started_at: Utc::now().to_rfc3339(),
+            files: files.to_vec(),
+            config: config.clone(),
+            text_mapping: text_mapping.cloned(),
+            output_dir: output_dir.to_path_buf(),
+            sequences: sequences.to_vec(),
+            first_sequence: 0,
+        })?;
+        Ok(journal)
+    }
+
+    pub fn file_started(&mut self, index: usize, output_paths: &[PathBuf]) {
+        self.record(&JournalEntry::FileStarted {
+            index,
+            output_paths: output_paths.to_vec(),
+            output_path: None,
+        });
+    }
+
+    pub fn file_finished(&mut self, index: usize, status: ProcessingStatus) {
+        self.record(&JournalEntry::FileFinished { index, status });
+    }
+
+    /// Marks the batch complete and deletes the journal.
+    pub fn finish(mut self) {
+        self.record(&JournalEntry::BatchFinished);
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!(path = %self.path.display(), error = %e, "Failed to remove batch journal");
+        }
+    }
+
+    fn record(&mut self, entry: &JournalEntry) {
+        if let Err(e) = self.append(entry) {
+            warn!(path = %self.path.display(), error = %e, "Failed to write batch journal");
+        }
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        // The entry must be on disk before the step it announces starts
+        self.file.sync_data()
+    }
+}
+
+impl Drop for BatchJournal {
+    fn drop(&mut self) {
+        self.app
+            .state::<JournalRegistry>()
+            .active
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(&self.batch_id);
+    }
+}
+
+/// State of an interrupted batch rebuilt from its journal.
+pub struct JournalReplay {
+    pub files: Vec<FileItem>,
+    pub config: WatermarkConfig,
+    pub text_mapping: Option<TextMapping>,
+    pub output_dir: PathBuf,
+    /// Position of every file within the whole batch, for `{counter}`
+    pub sequences: Vec<u64>,
+    pub started_at: String,
+    /// Final status of every file that finished, by index
+    pub finished: BTreeMap<usize, ProcessingStatus>,
+    /// Files that were started but never finished, with their output paths
+    pub in_progress: BTreeMap<usize, Vec<PathBuf>>,
+}
+
+impl JournalReplay {
+    /// Files that still need processing, in their original order.
+    pub fn unfinished_files(&self) -> Vec<FileItem> {
+        self.files
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !self.finished.contains_key(index))
+            .map(|(_, file)| file.clone())
+            .collect()
+    }
+
+    /// Sequences of `unfinished_files`, in the same order.
+    pub fn unfinished_sequences(&self) -> Vec<u64> {
+        self.sequences
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !self.finished.contains_key(index))
+            .map(|(_, sequence)| *sequence)
+            .collect()
+    }
+
+    /// Deletes the partial outputs of files that were mid-encode.
+    pub fn remove_partial_outputs(&self) {
+        for output_path in self.in_progress.values().flatten() {
+            if output_path.exists() {
+                if let Err(e) = std::fs::remove_file(output_path) {
+                    warn!(
+                        path = %output_path.display(),
+                        error = %e,
+                        "Failed to remove partial output"
+                    );
+                }
+            }
+        }
+    }
+
+    fn summary(&self, batch_id: String) -> InterruptedBatch {
+        let count = |wanted: &ProcessingStatus| {
+            self.finished.values().filter(|status| *status == wanted).count()
+        };
+        InterruptedBatch {
+            batch_id,
+            started_at: self.started_at.clone(),
+            output_dir: self.output_dir.clone(),
+            total: self.files.len(),
+            successful: count(&ProcessingStatus::Success) + count(&ProcessingStatus::Duplicate),
+            failed: count(&ProcessingStatus::Failed),
+            in_progress: self
+                .in_progress
+                .iter()
+                .map(|(index, output_paths)| InterruptedFile {
+                    input_path: self.files[*index].path.clone(),
+                    output_path: output_paths.first().cloned().unwrap_or_default(),
+                    partial_output_exists: output_paths.iter().any(|path| path.exists()),
+                })
+                .collect(),
+            pending: self.files.len() - self.finished.len() - self.in_progress.len(),
+        }
+    }
+}
+
+/// Lists journals left behind by batches that did not finish.
+pub fn list_interrupted(app: &AppHandle) -> Result<Vec<InterruptedBatch>, String> {
+    let dir = journal_dir(app).map_err(|e| e.to_string())?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let active = app
+        .state::<JournalRegistry>()
+        .active
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+
+    let entries =
+        std::fs::read_dir(&dir).map_err(|e| format!("Failed to read journal directory: {}", e))?;
+    let mut batches = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+            continue;
+        }
+        let Some(batch_id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if active.contains(batch_id) {
+            continue;
+        }
+        match replay(&path) {
+            Ok(Some(replay)) => batches.push(replay.summary(batch_id.to_string())),
+            Ok(None) => {}
+            Err(e) => warn!(path = %path.display(), error = %e, "Skipping batch journal"),
+        }
+    }
+
+    batches.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(batches)
+}
+
+/// Rebuilds an interrupted batch from its journal.
+pub fn load_interrupted(app: &AppHandle, batch_id: &str) -> Result<JournalReplay, String> {
+    replay(&journal_path(app, batch_id)?)?
+        .ok_or_else(|| format!("Batch {} already finished", batch_id))
+}
+
+/// Deletes the journal of an interrupted batch once it has been dealt with.
+pub fn remove(app: &AppHandle, batch_id: &str) -> Result<(), String> {
+    std::fs::remove_file(journal_path(app, batch_id)?)
+        .map_err(|e| format!("Failed to remove batch journal: {}", e))
+}
+
+/// Replays a journal. Returns `None` when the batch finished cleanly.
+fn replay(path: &Path) -> Result<Option<JournalReplay>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open batch journal: {}", e))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header = lines
+        .next()
+        .ok_or("Batch journal is empty")?
+        .map_err(|e| format!("Failed to read batch journal: {}", e))?;
+    let JournalEntry::BatchStarted {
+        started_at,
+        files,
+        config,
+        text_mapping,
+        output_dir,
+        sequences,
+        first_sequence,
+    } = serde_json::from_str(&header).map_err(|e| format!("Invalid batch journal: {}", e))?
+    else {
+        return Err("Batch journal does not start with a batch header".to_string());
+    };
+    let sequences = if sequences.len() == files.len() {
+        sequences
+    } else {
+        (first_sequence..).take(files.len()).collect()
+    };
+
+    let mut replay = JournalReplay {
+        files,
+        config,
+        text_mapping,
+        output_dir,
+        sequences,
+        started_at,
+        finished: BTreeMap::new(),
+        in_progress: BTreeMap::new(),
+    };
+
+    for line in lines {
+        let Ok(line) = line else { break };
+        // A torn final line means power was lost while appending it
+        let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) else {
+            break;
+        };
+        match entry {
+            JournalEntry::FileStarted {
+                index,
+                output_paths,
+                output_path,
+            } if index < replay.files.len() => {
+                let outputs = output_paths.into_iter().chain(output_path).collect();
+                replay.in_progress.insert(index, outputs);
+            }
+            JournalEntry::FileFinished { index, status } if index < replay.files.len() => {
+                replay.in_progress.remove(&index);
+                replay.finished.insert(index, status);
+            }
+            JournalEntry::BatchFinished => return Ok(None),
+            _ => {}
+        }
+    }
+    Ok(Some(replay))
+}
+
+fn journal_path(app: &AppHandle, batch_id: &str) -> Result<PathBuf, String> {
+    // Prevents path traversal out of the journal directory
+    if batch_id.is_empty() || !batch_id.chars().all(|c| c.is_alphanumeric() || c == '-') {
+        return Err("Invalid batch ID".to_string());
+    }
+    let dir = journal_dir(app).map_err(|e| e.to_string())?;
+    Ok(dir.join(format!("{}.jsonl", batch_id)))
+}
+
+fn journal_dir(app: &AppHandle) -> std::io::Result<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("journal"))
+        .map_err(std::io::Error::other)
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::types::WatermarkConfig;
+
+/// Store file shared with earlier frontend-only versions, so their saved
+/// settings carry over.
+pub(crate) const SETTINGS_STORE: &str = "watermark-settings.json";
+const CONFIG_KEY: &str = "watermarkConfig";
+const PRESET_KEY: &str = "activePresetId";
+
+/// The configuration the user last worked with, restored on launch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastConfig {
+    pub config: WatermarkConfig,
+    /// Preset the config was applied from, while it is unmodified
+    pub preset_id: Option<String>,
+}
+
+/// Reads the saved configuration, or `None` before anything was saved.
+/// Fields missing from older saves get their defaults.
+pub fn load(app: &AppHandle) -> Result<Option<LastConfig>, String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings store: {}", e))?;
+    let Some(saved) = store.get(CONFIG_KEY) else {
+        return Ok(None);
+    };
+    let mut config: WatermarkConfig = serde_json::from_value(saved)
+        .map_err(|e| format!("Invalid saved configuration: {}", e))?;
+    // Saves from before custom positioning used the preset positions
+    if config.position_mode.is_none() {
+        config.position_mode = Some("preset".to_string());
+    }
+    let preset_id = store
+        .get(PRESET_KEY)
+        .and_then(|value| value.as_str().map(str::to_string));
+    Ok(Some(LastConfig { config, preset_id }))
+}
+
+/// Saves `last` in full; the store writes it to disk shortly after.
+pub fn save(app: &AppHandle, last: &LastConfig) -> Result<(), String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings store: {}", e))?;
+    let config = serde_json::to_value(&last.config)
+        .map_err(|e| format!("Failed to serialize configuration: {}", e))?;
+    store.set(CONFIG_KEY, config);
+    match &last.preset_id {
+        Some(preset_id) => store.set(PRESET_KEY, preset_id.clone()),
+        None => {
+            store.delete(PRESET_KEY);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio::is_audio_file;
+use crate::ffmpeg::detect_file_type;
+
+/// Media files handed to the app by the OS ("Open With", double-click, drop
+/// on the dock icon) that the frontend has not picked up yet, managed as
+/// Tauri state. The frontend drains the queue on startup and whenever
+/// `files-opened` is emitted, so files opened before the window is ready are
+/// not lost.
+#[derive(Debug, Default)]
+pub struct OpenedFiles {
+    pending: Mutex<Vec<PathBuf>>,
+}
+
+impl OpenedFiles {
+    pub fn take(&self) -> Vec<PathBuf> {
+        std::mem::take(&mut *self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+/// Queues the supported media files among `paths` and notifies the frontend.
+pub fn open_files(app: &AppHandle, paths: impl IntoIterator<Item = PathBuf>) {
+    let files: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|path| path.is_file() && (detect_file_type(path).is_ok() || is_audio_file(path)))
+        .collect();
+    if files.is_empty() {
+        return;
+    }
+
+    app.state::<OpenedFiles>()
+        .pending
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .extend(files);
+    let _ = app.emit_to("main", "files-opened", ());
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Turns launch arguments into file paths. The first argument is the
+/// executable and flags are skipped; relative paths are resolved against the
+/// launching process' working directory.
+pub fn paths_from_args(args: &[String], cwd: &Path) -> Vec<PathBuf> {
+    args.iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| {
+            let path = PathBuf::from(arg);
+            if path.is_absolute() {
+                path
+            } else {
+                cwd.join(path)
+            }
+        })
+        .collect()
+}
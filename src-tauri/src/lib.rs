@@ -1,32 +1,184 @@
+mod affinity;
+mod analysis;
+mod audio;
+mod cache_db;
+mod chunked;
 mod commands;
+mod dedupe;
+mod disk_space;
+mod error;
 mod ffmpeg;
+mod ffmpeg_download;
+mod file_log;
+mod hotfolder;
+mod hwaccel;
+mod jobs;
+mod journal;
+mod last_config;
+mod launch;
+mod logging;
+mod mapping;
+mod metadata;
+mod path_policy;
+mod plan;
+mod postprocess;
+mod prefetch;
+mod preflight;
+mod presets;
+mod priority;
+mod probe;
+mod rpc;
+mod scan;
+mod self_test;
+mod sources;
+mod sidecar;
+mod sniff;
+mod system_ffmpeg;
+mod telemetry;
+mod throttle;
+mod tokens;
+mod transform;
 mod types;
+mod updates;
+
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+    let rpc_mode = rpc::requested();
+
+    // A second launch (e.g. "Open With" while running) forwards its files to
+    // this instance instead of opening another window. RPC servers are
+    // independent of the GUI and of each other.
+    #[cfg(desktop)]
+    if !rpc_mode {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            launch::open_files(app, launch::paths_from_args(&args, std::path::Path::new(&cwd)));
+        }));
+    }
+
+    builder
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .setup(|_app| {
+        .manage(jobs::JobRegistry::default())
+        .manage(jobs::BatchHistory::default())
+        .manage(hwaccel::HardwareEncoders::default())
+        .manage(journal::JournalRegistry::default())
+        .manage(hotfolder::HotFolderState::default())
+        .manage(postprocess::PostProcessorRegistry::with_builtins())
+        .manage(sources::WatermarkSourceRegistry::default())
+        .manage(presets::PresetRepository::default())
+        .manage(path_policy::PathPolicy::default())
+        .manage(telemetry::Telemetry::default())
+        .manage(updates::Updates::default())
+        .manage(launch::OpenedFiles::default())
+        .manage(cache_db::CacheDatabase::default())
+        .manage(system_ffmpeg::SystemFfmpeg::default())
+        .manage(file_log::FileLogs::default())
+        .setup(move |app| {
+            logging::init(app.handle());
+            if rpc_mode {
+                // Driven over stdin/stdout by another process; no GUI
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.hide();
+                }
+                rpc::start(app.handle().clone());
+            } else if let Ok(cwd) = std::env::current_dir() {
+                // Files passed on the command line by a double-click or "Open With"
+                let args: Vec<String> = std::env::args().collect();
+                launch::open_files(app.handle(), launch::paths_from_args(&args, &cwd));
+            }
+
             // Spawn async task to cleanup old thumbnails on startup
+            let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
-                // Clean thumbnails older than 7 days
-                let _ = commands::cleanup_thumbnail_cache(Some(7)).await;
+                // Clean thumbnails older than the configured retention
+                let _ = commands::cleanup_thumbnail_cache(handle, None).await;
             });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::process_batch,
+            commands::plan_batch,
+            commands::preflight_batch,
             commands::process_single_file,
+            commands::process_sample_batch,
+            commands::continue_sample_batch,
+            commands::get_batch_results,
+            commands::get_processing_history,
+            commands::get_history,
+            commands::search_history,
+            commands::clear_history,
+            commands::get_file_log,
+            commands::get_recent_logs,
+            commands::export_logs,
+            commands::get_hardware_encoders,
+            commands::get_ffmpeg_source,
+            commands::set_ffmpeg_source,
+            commands::download_ffmpeg,
+            commands::self_test,
+            commands::discard_sample_batch,
+            commands::list_interrupted_batches,
+            commands::resume_interrupted_batch,
+            commands::discard_interrupted_batch,
+            commands::approve_output_location,
+            commands::process_clipboard_image,
             commands::extract_video_thumbnail,
+            commands::extract_image_thumbnail,
+            commands::prefetch_thumbnails,
+            commands::extract_scrub_strip,
+            commands::extract_video_filmstrip,
+            commands::render_watermark_frames,
+            commands::render_comparison_preview,
+            commands::render_video_preview,
+            commands::nudge_watermark_position,
+            commands::preview_ffmpeg_command,
+            commands::get_media_info,
             commands::cleanup_thumbnail_cache,
+            commands::clear_thumbnail_cache,
+            commands::get_cache_settings,
+            commands::set_cache_settings,
+            commands::scan_folder,
             commands::open_folder_in_explorer,
+            commands::take_opened_files,
+            commands::list_post_processors,
+            commands::list_watermark_sources,
             commands::list_presets,
             commands::load_preset,
+            commands::set_default_preset,
+            commands::save_preset,
+            commands::delete_preset,
+            commands::rename_preset,
+            commands::export_preset,
+            commands::import_preset,
+            commands::load_last_config,
+            commands::save_last_config,
+            commands::get_telemetry_enabled,
+            commands::set_telemetry_enabled,
+            commands::export_telemetry,
+            commands::get_update_channel,
+            commands::set_update_channel,
+            commands::check_for_updates_now,
+            commands::install_update,
+            commands::get_screenshot_folder,
+            commands::start_hot_folder,
+            commands::stop_hot_folder,
+            commands::get_hot_folder_status,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, _event| {
+            // macOS delivers opened files as an event rather than arguments
+            #[cfg(any(target_os = "macos", target_os = "ios"))]
+            if let tauri::RunEvent::Opened { urls } = _event {
+                launch::open_files(
+                    _app,
+                    urls.into_iter().filter_map(|url| url.to_file_path().ok()),
+                );
+            }
+        });
 }
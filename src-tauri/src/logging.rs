@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Folder in the app data directory holding the log files.
+const LOG_DIR: &str = "logs";
+const LOG_PREFIX: &str = "bulk-watermark";
+const LOG_SUFFIX: &str = "log";
+
+/// Daily log files kept before the oldest is deleted.
+const MAX_LOG_FILES: usize = 7;
+
+/// Keeps the background log writer running, managed as Tauri state for the
+/// lifetime of the app; dropping it flushes and stops the writer.
+pub struct LogGuard {
+    _guard: WorkerGuard,
+}
+
+/// Logs to a new file in the app data directory each day, keeping the last
+/// `MAX_LOG_FILES`, and to stderr. `RUST_LOG` overrides the default `info`
+/// level. Without a usable log directory only stderr is written.
+pub fn init(app: &AppHandle) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let stderr_layer = fmt::layer().with_writer(std::io::stderr);
+
+    let appender = log_dir(app).and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(LOG_PREFIX)
+            .filename_suffix(LOG_SUFFIX)
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)
+            .map_err(|e| format!("Failed to open log file: {}", e))
+    });
+    match appender {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let file_layer = fmt::layer().with_ansi(false).with_writer(writer);
+            let _ = tracing_subscriber::registry()
+                .with(filter)
+                .with(stderr_layer)
+                .with(file_layer)
+                .try_init();
+            app.manage(LogGuard { _guard: guard });
+        }
+        Err(e) => {
+            let _ = tracing_subscriber::registry().with(filter).with(stderr_layer).try_init();
+            tracing::warn!(error = %e, "Logging to stderr only");
+        }
+    }
+}
+
+/// The last `limit` log lines, oldest first, across the kept log files.
+pub fn recent_lines(app: &AppHandle, limit: usize) -> Result<Vec<String>, String> {
+    let mut lines = Vec::new();
+    for file in log_files(&log_dir(app)?)?.iter().rev() {
+        let content = std::fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let mut file_lines: Vec<String> = content.lines().map(str::to_string).collect();
+        file_lines.append(&mut lines);
+        lines = file_lines;
+        if lines.len() >= limit {
+            break;
+        }
+    }
+    Ok(lines.split_off(lines.len().saturating_sub(limit)))
+}
+
+/// Writes every kept log file, oldest first, into the single file
+/// `destination`, for attaching to a bug report.
+pub fn export(app: &AppHandle, destination: &Path) -> Result<(), String> {
+    let mut exported = String::new();
+    for file in log_files(&log_dir(app)?)? {
+        let content = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        exported.push_str(&format!("==> {} <==\n", name));
+        exported.push_str(&content);
+        if !content.ends_with('\n') {
+            exported.push('\n');
+        }
+    }
+    std::fs::write(destination, exported)
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))
+}
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join(LOG_DIR))
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create log directory: {}", e))?;
+    Ok(dir)
+}
+
+/// Log files in `dir`, oldest first; their names end in the date.
+fn log_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read log directory: {}", e))?;
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_PREFIX) && name.ends_with(LOG_SUFFIX))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
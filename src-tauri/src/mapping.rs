@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::TextRule;
+
+/// Per-file watermark text loaded from a CSV or JSON file, keyed by input
+/// file name.
+///
+/// CSV files have two columns (file name, text) with an optional header row
+/// whose first column reads `filename`. JSON files are a single object mapping
+/// file names to text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextMapping {
+    entries: HashMap<String, String>,
+}
+
+impl TextMapping {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let is_json = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+
+        let entries = if is_json {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read text mapping: {}", e))?;
+            serde_json::from_str::<HashMap<String, String>>(&content)
+                .map_err(|e| format!("Invalid text mapping JSON: {}", e))?
+        } else {
+            read_csv(path)?
+        };
+
+        Ok(Self { entries })
+    }
+
+    /// Looks up the text for a file by full file name, then by file stem.
+    pub fn text_for(&self, input_path: &Path) -> Option<&str> {
+        let by_name = input_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| self.entries.get(name));
+        let by_stem = || {
+            input_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| self.entries.get(stem))
+        };
+
+        by_name.or_else(by_stem).map(String::as_str)
+    }
+}
+
+/// Compiled `TextRule`s, matched against file names in order.
+#[derive(Debug, Clone, Default)]
+pub struct TextRules {
+    rules: Vec<(Regex, String)>,
+}
+
+impl TextRules {
+    pub fn compile(rules: &[TextRule]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (regex, rule.text.clone()))
+                    .map_err(|e| format!("Invalid text rule pattern {:?}: {}", rule.pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Text from the first rule whose pattern matches the file name.
+    pub fn text_for(&self, input_path: &Path) -> Option<String> {
+        let name = input_path.file_name()?.to_str()?;
+        self.rules.iter().find_map(|(regex, template)| {
+            let captures = regex.captures(name)?;
+            let mut text = String::new();
+            captures.expand(template, &mut text);
+            Some(text)
+        })
+    }
+}
+
+fn read_csv(path: &Path) -> Result<HashMap<String, String>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .map_err(|e| format!("Failed to read text mapping: {}", e))?;
+
+    let mut entries = HashMap::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("Invalid text mapping CSV: {}", e))?;
+        let (Some(file_name), Some(text)) = (record.get(0), record.get(1)) else {
+            continue;
+        };
+        if row == 0 && file_name.eq_ignore_ascii_case("filename") {
+            continue;
+        }
+        if !file_name.is_empty() {
+            entries.insert(file_name.to_string(), text.to_string());
+        }
+    }
+
+    Ok(entries)
+}
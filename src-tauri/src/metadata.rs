@@ -0,0 +1,174 @@
+use std::path::Path;
+
+const SOI: [u8; 2] = [0xFF, 0xD8];
+const APP1: u8 = 0xE1;
+const APP2: u8 = 0xE2;
+const APP13: u8 = 0xED;
+/// Start of scan; the compressed image data follows and no more headers
+const SOS: u8 = 0xDA;
+const EOI: u8 = 0xD9;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+
+const TAG_THUMBNAIL_OFFSET: u16 = 0x0201;
+const TAG_THUMBNAIL_LENGTH: u16 = 0x0202;
+
+/// A marker segment: `marker` and the bytes after its length field.
+struct Segment<'a> {
+    marker: u8,
+    payload: &'a [u8],
+}
+
+/// Copies the EXIF, XMP, ICC profile and IPTC segments of a JPEG input into
+/// a JPEG output, replacing any the output has. FFmpeg writes JPEGs without
+/// them, losing capture dates, copyright fields and color profiles. The EXIF
+/// thumbnail is removed since it would show the image without the watermark.
+///
+/// Returns `Ok(false)` without touching the output when either file is not a
+/// JPEG.
+pub fn copy_jpeg_metadata(input_path: &Path, output_path: &Path) -> Result<bool, String> {
+    let input = std::fs::read(input_path)
+        .map_err(|e| format!("Failed to read {}: {}", input_path.display(), e))?;
+    let output = std::fs::read(output_path)
+        .map_err(|e| format!("Failed to read {}: {}", output_path.display(), e))?;
+
+    let (Some((input_segments, _)), Some((output_segments, image_data))) =
+        (read_segments(&input), read_segments(&output))
+    else {
+        return Ok(false);
+    };
+
+    let mut merged = Vec::with_capacity(output.len() + input.len().min(256 * 1024));
+    merged.extend_from_slice(&SOI);
+    let (jfif, rest): (Vec<_>, Vec<_>) = output_segments
+        .into_iter()
+        .filter(|segment| !is_metadata(segment))
+        .partition(|segment| segment.marker == 0xE0);
+    // JFIF must stay the first segment
+    for segment in &jfif {
+        write_segment(&mut merged, segment.marker, segment.payload)?;
+    }
+    for segment in input_segments.iter().filter(|segment| is_metadata(segment)) {
+        if segment.marker == APP1 && segment.payload.starts_with(EXIF_HEADER) {
+            let mut exif = segment.payload.to_vec();
+            strip_exif_thumbnail(&mut exif[EXIF_HEADER.len()..]);
+            write_segment(&mut merged, segment.marker, &exif)?;
+        } else {
+            write_segment(&mut merged, segment.marker, segment.payload)?;
+        }
+    }
+    for segment in &rest {
+        write_segment(&mut merged, segment.marker, segment.payload)?;
+    }
+    merged.extend_from_slice(image_data);
+
+    std::fs::write(output_path, merged)
+        .map_err(|e| format!("Failed to write {}: {}", output_path.display(), e))?;
+    Ok(true)
+}
+
+/// EXIF and XMP (APP1), ICC profiles (APP2) and IPTC (APP13).
+fn is_metadata(segment: &Segment) -> bool {
+    match segment.marker {
+        APP1 | APP13 => true,
+        APP2 => segment.payload.starts_with(ICC_HEADER),
+        _ => false,
+    }
+}
+
+/// Splits a JPEG into its header segments and the remaining bytes, starting
+/// at the start-of-scan marker. `None` when `bytes` is not a JPEG.
+fn read_segments(bytes: &[u8]) -> Option<(Vec<Segment<'_>>, &[u8])> {
+    if !bytes.starts_with(&SOI) {
+        return None;
+    }
+    let mut segments = Vec::new();
+    let mut pos = SOI.len();
+    loop {
+        if *bytes.get(pos)? != 0xFF {
+            return None;
+        }
+        // Any number of 0xFF fill bytes may precede a marker
+        let mut marker_pos = pos + 1;
+        while *bytes.get(marker_pos)? == 0xFF {
+            marker_pos += 1;
+        }
+        let marker = bytes[marker_pos];
+        if marker == SOS || marker == EOI {
+            return Some((segments, &bytes[pos..]));
+        }
+        // Restart and TEM markers carry no length
+        if matches!(marker, 0x01 | 0xD0..=0xD7) {
+            pos = marker_pos + 1;
+            continue;
+        }
+        let length = bytes.get(marker_pos + 1..marker_pos + 3)?;
+        let end = marker_pos + 1 + u16::from_be_bytes([length[0], length[1]]) as usize;
+        let payload = bytes.get(marker_pos + 3..end)?;
+        segments.push(Segment { marker, payload });
+        pos = end;
+    }
+}
+
+fn write_segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) -> Result<(), String> {
+    let length = u16::try_from(payload.len() + 2)
+        .map_err(|_| "JPEG metadata segment is too large".to_string())?;
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&length.to_be_bytes());
+    out.extend_from_slice(payload);
+    Ok(())
+}
+
+/// Blanks the thumbnail stored in the second IFD of a TIFF structure and
+/// unlinks that IFD. Malformed data is left as is.
+fn strip_exif_thumbnail(tiff: &mut [u8]) {
+    let little_endian = match tiff.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let read_u16 = |data: &[u8], at: usize| -> Option<u16> {
+        let bytes = [*data.get(at)?, *data.get(at + 1)?];
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let read_u32 = |data: &[u8], at: usize| -> Option<usize> {
+        let bytes: [u8; 4] = data.get(at..at + 4)?.try_into().ok()?;
+        let value = if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        };
+        Some(value as usize)
+    };
+
+    let Some(ifd0) = read_u32(tiff, 4) else { return };
+    let Some(entries) = read_u16(tiff, ifd0) else { return };
+    let next_ifd_pos = ifd0 + 2 + 12 * entries as usize;
+    let Some(ifd1) = read_u32(tiff, next_ifd_pos).filter(|offset| *offset != 0) else {
+        return;
+    };
+
+    let mut thumbnail_offset = None;
+    let mut thumbnail_length = None;
+    for index in 0..read_u16(tiff, ifd1).unwrap_or(0) as usize {
+        let entry = ifd1 + 2 + 12 * index;
+        match read_u16(tiff, entry) {
+            Some(TAG_THUMBNAIL_OFFSET) => thumbnail_offset = read_u32(tiff, entry + 8),
+            Some(TAG_THUMBNAIL_LENGTH) => thumbnail_length = read_u32(tiff, entry + 8),
+            _ => {}
+        }
+    }
+    if let (Some(offset), Some(length)) = (thumbnail_offset, thumbnail_length) {
+        if let Some(thumbnail) = tiff.get_mut(offset..offset.saturating_add(length)) {
+            thumbnail.fill(0);
+        }
+    }
+    if let Some(next_ifd) = tiff.get_mut(next_ifd_pos..next_ifd_pos + 4) {
+        next_ifd.fill(0);
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::disk_space::free_space;
+
+const OUTPUT_LOCATIONS_FILE: &str = "output_locations.json";
+
+/// What the user is asked before a batch writes to a new output directory.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputLocationConfirmation {
+    pub path: PathBuf,
+    /// Whether the directory exists yet; batches create it when it does not
+    pub exists: bool,
+    /// Space available on the volume, when the platform reports it
+    pub free_bytes: Option<u64>,
+    /// Files directly inside the directory, which outputs may overwrite
+    pub existing_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+struct OutputLocations {
+    approved: Vec<PathBuf>,
+}
+
+/// Output directories the user has approved, persisted in the app data
+/// directory and managed as Tauri state. A directory approves everything
+/// below it.
+#[derive(Default)]
+pub struct PathPolicy {
+    locations: Mutex<Option<OutputLocations>>,
+}
+
+impl PathPolicy {
+    /// Returns the confirmation to show when `dir` has not been approved yet.
+    pub fn check_output_dir(
+        &self,
+        app: &AppHandle,
+        dir: &Path,
+    ) -> Result<(), OutputLocationConfirmation> {
+        let dir = normalize(dir);
+        let approved = self.with_locations(app, |locations| {
+            locations.approved.iter().any(|approved| dir.starts_with(approved))
+        });
+        if approved {
+            return Ok(());
+        }
+        Err(OutputLocationConfirmation {
+            exists: dir.is_dir(),
+            free_bytes: dir.ancestors().find(|ancestor| ancestor.exists()).and_then(free_space),
+            existing_files: std::fs::read_dir(&dir)
+                .map(|entries| {
+                    entries.flatten().filter(|entry| entry.path().is_file()).count()
+                })
+                .unwrap_or(0),
+            path: dir,
+        })
+    }
+
+    pub fn approve(&self, app: &AppHandle, dir: &Path) -> Result<(), String> {
+        let dir = normalize(dir);
+        self.with_locations(app, |locations| {
+            if locations.approved.iter().any(|approved| dir.starts_with(approved)) {
+                return Ok(());
+            }
+            // The new entry covers any approved directory below it
+            locations.approved.retain(|approved| !approved.starts_with(&dir));
+            locations.approved.push(dir);
+            save(app, locations)
+        })
+    }
+
+    fn with_locations<T>(
+        &self,
+        app: &AppHandle,
+        update: impl FnOnce(&mut OutputLocations) -> T,
+    ) -> T {
+        let mut guard = self.locations.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        update(guard.get_or_insert_with(|| load(app)))
+    }
+}
+
+/// Makes `dir` absolute with `.` and `..` resolved, so `approved/../elsewhere`
+/// is not taken for a directory below `approved`. The part of the path that
+/// exists is canonicalized to resolve links; the rest is appended as is, so
+/// this also works for directories that do not exist yet.
+fn normalize(dir: &Path) -> PathBuf {
+    let absolute = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            // `pop` keeps the root, as `..` at the root is the root itself
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+
+    let canonical = resolved.ancestors().find_map(|ancestor| {
+        let rest = resolved.strip_prefix(ancestor).ok()?;
+        ancestor.canonicalize().ok().map(|canonical| canonical.join(rest))
+    });
+    canonical.unwrap_or(resolved)
+}
+
+fn locations_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(OUTPUT_LOCATIONS_FILE))
+}
+
+fn load(app: &AppHandle) -> OutputLocations {
+    locations_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<OutputLocations>(&content).ok())
+        .map(|locations| OutputLocations {
+            // Entries saved before `..` was resolved may still contain it
+            approved: locations.approved.iter().map(|dir| normalize(dir)).collect(),
+        })
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, locations: &OutputLocations) -> Result<(), String> {
+    let path = locations_path(app)
+        .ok_or_else(|| "Failed to resolve the output locations file".to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(locations)
+        .map_err(|e| format!("Failed to serialize output locations: {}", e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write output locations: {}", e))
+}
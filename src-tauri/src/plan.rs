@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::audio::is_audio_file;
+use crate::commands::{build_output_path, is_same_file, output_paths};
+use crate::ffmpeg::detect_file_type;
+use crate::types::{
+    AudioWatermarkMode, BatchPlan, FileItem, PlanIssue, PlannedFile, WatermarkConfig,
+};
+
+/// A waveform render is a full video track, several times larger than the
+/// audio it is made from.
+const WAVEFORM_SIZE_FACTOR: u64 = 4;
+
+/// Works out where every file of a batch would be written, and what would go
+/// wrong, without touching FFmpeg or the output folder. Output sizes are
+/// estimated from the input sizes.
+pub fn plan_batch(files: &[FileItem], config: &WatermarkConfig, output_dir: &Path) -> BatchPlan {
+    let mut plan = BatchPlan::default();
+    // Keyed case-insensitively: Windows and macOS treat `A.jpg` and `a.jpg` as one file
+    let mut claimed_outputs: HashMap<String, usize> = HashMap::new();
+
+    for (index, file) in files.iter().enumerate() {
+        let mut planned = PlannedFile {
+            input_path: file.path.clone(),
+            output_path: None,
+            issues: Vec::new(),
+            estimated_bytes: 0,
+        };
+
+        let metadata = match std::fs::metadata(&file.path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => {
+                planned.issues.push(PlanIssue::Missing);
+                plan.files.push(planned);
+                continue;
+            }
+        };
+        let is_audio = is_audio_file(&file.path);
+        if !is_audio && detect_file_type(&file.path).is_err() {
+            planned.issues.push(PlanIssue::Unsupported);
+            plan.files.push(planned);
+            continue;
+        }
+
+        let output_path = build_output_path(output_dir, &file.path, config, Some(index as u64));
+        let key = output_path.to_string_lossy().to_lowercase();
+        if let Some(&first) = claimed_outputs.get(&key) {
+            planned.issues.push(PlanIssue::Collision { with_index: first });
+        } else {
+            claimed_outputs.insert(key, index);
+        }
+        // With export variants only the variant outputs are written, never the base path
+        let outputs = output_paths(&output_path, config);
+        if outputs.iter().any(|output| is_same_file(output, &file.path)) {
+            planned.issues.push(PlanIssue::OverwritesInput);
+        } else if outputs.iter().any(|output| output.exists()) {
+            planned.issues.push(PlanIssue::OverwritesExisting);
+        }
+
+        planned.estimated_bytes = estimate_output_bytes(metadata.len(), is_audio, config);
+        planned.output_path = outputs.into_iter().next();
+        plan.files.push(planned);
+    }
+
+    for planned in &plan.files {
+        plan.estimated_bytes += planned.estimated_bytes;
+        for issue in &planned.issues {
+            match issue {
+                PlanIssue::Missing | PlanIssue::Unsupported => plan.skipped += 1,
+                PlanIssue::Collision { .. } => plan.collisions += 1,
+                PlanIssue::OverwritesExisting | PlanIssue::OverwritesInput => plan.overwrites += 1,
+            }
+        }
+    }
+    plan
+}
+
+/// Bytes a file of `input_bytes` is expected to add to the output folder,
+/// every export variant and the archive copy included.
+pub fn estimate_output_bytes(input_bytes: u64, is_audio: bool, config: &WatermarkConfig) -> u64 {
+    let size_factor = if is_audio && config.audio_mode == AudioWatermarkMode::Waveform {
+        WAVEFORM_SIZE_FACTOR
+    } else {
+        1
+    };
+    let outputs = config.export_variants.len().max(1) as u64;
+    let mut estimated = input_bytes * size_factor * outputs;
+    if config.archive_copy.is_some() {
+        estimated += input_bytes;
+    }
+    estimated
+}
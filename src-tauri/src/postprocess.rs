@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+
+use crate::types::{PostProcessorInfo, WatermarkConfig};
+
+/// Everything a post-processor needs to know about a finished output.
+#[derive(Debug, Clone)]
+pub struct PostProcessContext {
+    pub input_path: PathBuf,
+    pub output_path: PathBuf,
+    pub config: WatermarkConfig,
+}
+
+/// A step that runs on each output after FFmpeg has written it, e.g. lossless
+/// optimization or an upload. Processors run on a blocking thread, so they may
+/// do synchronous IO or spawn external tools.
+pub trait PostProcessor: Send + Sync {
+    fn id(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// Whether this processor handles the given output; others are skipped.
+    fn applies_to(&self, _output_path: &Path) -> bool {
+        true
+    }
+
+    fn process(&self, context: &PostProcessContext) -> Result<(), String>;
+}
+
+/// Registered post-processors keyed by ID, managed as Tauri state. Batches opt
+/// into processors by listing their IDs in `WatermarkConfig::post_processors`.
+#[derive(Default)]
+pub struct PostProcessorRegistry {
+    processors: RwLock<HashMap<String, Arc<dyn PostProcessor>>>,
+}
+
+impl PostProcessorRegistry {
+    pub fn with_builtins() -> Self {
+        let registry = Self::default();
+        registry.register(Arc::new(JpegOptimizer));
+        registry.register(Arc::new(PngOptimizer));
+        registry
+    }
+
+    pub fn register(&self, processor: Arc<dyn PostProcessor>) {
+        self.processors
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(processor.id().to_string(), processor);
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn PostProcessor>> {
+        self.processors
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    pub fn list(&self) -> Vec<PostProcessorInfo> {
+        let mut infos: Vec<_> = self
+            .processors
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .map(|processor| PostProcessorInfo {
+                id: processor.id().to_string(),
+                description: processor.description().to_string(),
+            })
+            .collect();
+        infos.sort_by(|a, b| a.id.cmp(&b.id));
+        infos
+    }
+}
+
+/// Losslessly optimizes JPEG Huffman tables with jpegtran, keeping metadata.
+struct JpegOptimizer;
+
+impl PostProcessor for JpegOptimizer {
+    fn id(&self) -> &str {
+        "optimize-jpeg"
+    }
+
+    fn description(&self) -> &str {
+        "Losslessly shrink JPEG outputs with jpegtran"
+    }
+
+    fn applies_to(&self, output_path: &Path) -> bool {
+        has_extension(output_path, &["jpg", "jpeg"])
+    }
+
+    fn process(&self, context: &PostProcessContext) -> Result<(), String> {
+        let mut optimized = context.output_path.as_os_str().to_os_string();
+        optimized.push(".opt");
+        let optimized = PathBuf::from(optimized);
+
+        let result = run_tool(
+            Command::new("jpegtran")
+                .args(["-copy", "all", "-optimize", "-progressive", "-outfile"])
+                .arg(&optimized)
+                .arg(&context.output_path),
+        )
+        .and_then(|_| {
+            std::fs::rename(&optimized, &context.output_path)
+                .map_err(|e| format!("Failed to replace output: {}", e))
+        });
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(&optimized);
+        }
+        result
+    }
+}
+
+/// Recompresses PNG outputs in place with oxipng.
+struct PngOptimizer;
+
+impl PostProcessor for PngOptimizer {
+    fn id(&self) -> &str {
+        "optimize-png"
+    }
+
+    fn description(&self) -> &str {
+        "Losslessly shrink PNG outputs with oxipng"
+    }
+
+    fn applies_to(&self, output_path: &Path) -> bool {
+        has_extension(output_path, &["png"])
+    }
+
+    fn process(&self, context: &PostProcessContext) -> Result<(), String> {
+        run_tool(
+            Command::new("oxipng")
+                .args(["-o", "2", "--strip", "safe"])
+                .arg(&context.output_path),
+        )
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+fn run_tool(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use crate::commands::{image_thumbnail, video_thumbnail};
+use crate::types::FileItem;
+
+/// Thumbnails generated at the same time; each video thumbnail is an FFmpeg
+/// run and each image one a full decode.
+const PREFETCH_PARALLELISM: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThumbnailReady {
+    path: PathBuf,
+    thumbnail_path: Option<PathBuf>,
+    error: Option<String>,
+}
+
+/// Generates the thumbnails of `files` on a background task, emitting
+/// `thumbnail-ready` per file as it finishes. Audio files have no thumbnail
+/// and are skipped.
+pub fn prefetch_thumbnails(app: &AppHandle, files: Vec<FileItem>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let semaphore = Arc::new(Semaphore::new(PREFETCH_PARALLELISM));
+        let mut tasks = JoinSet::new();
+        for file in files {
+            let is_video = match file.r#type.as_str() {
+                "video" => true,
+                "image" => false,
+                _ => continue,
+            };
+            let app = app.clone();
+            let semaphore = semaphore.clone();
+            tasks.spawn(async move {
+                let Ok(_permit) = semaphore.acquire_owned().await else {
+                    return;
+                };
+                let path = file.path.to_string_lossy().into_owned();
+                let result = if is_video {
+                    video_thumbnail(&app, &path, None).await
+                } else {
+                    image_thumbnail(&app, &path).await
+                };
+                let (thumbnail_path, error) = match result {
+                    Ok(thumbnail_path) => (Some(thumbnail_path), None),
+                    Err(e) => (None, Some(e.message)),
+                };
+                let _ = app.emit(
+                    "thumbnail-ready",
+                    ThumbnailReady {
+                        path: file.path,
+                        thumbnail_path,
+                        error,
+                    },
+                );
+            });
+        }
+        // Dropping the set would cancel the remaining thumbnails
+        while let Some(joined) = tasks.join_next().await {
+            if let Err(e) = joined {
+                warn!(error = %e, "Thumbnail prefetch failed");
+            }
+        }
+    });
+}
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use crate::audio::is_audio_file;
+use crate::ffmpeg::detect_file_type;
+use crate::probe::probe_stream_health;
+use crate::types::{ExcludedFile, ExclusionReason, FileItem, PreflightReport};
+
+/// Files probed at the same time; each video or audio probe is an FFmpeg run.
+const PREFLIGHT_PARALLELISM: usize = 4;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PreflightProgress {
+    checked: usize,
+    total: usize,
+}
+
+/// Reads the header of every file in the batch and reports the ones that are
+/// missing, empty, unsupported or cannot be decoded, emitting
+/// `preflight-progress` as files are checked.
+pub async fn preflight(app: &AppHandle, files: &[FileItem]) -> PreflightReport {
+    let total = files.len();
+    let semaphore = Arc::new(Semaphore::new(PREFLIGHT_PARALLELISM));
+    let mut tasks = JoinSet::new();
+    for file in files {
+        let app = app.clone();
+        let path = file.path.clone();
+        let semaphore = semaphore.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.ok()?;
+            check_file(&app, path).await
+        });
+    }
+
+    let mut report = PreflightReport {
+        checked: total,
+        problems: Vec::new(),
+    };
+    let mut checked = 0;
+    while let Some(joined) = tasks.join_next().await {
+        checked += 1;
+        match joined {
+            Ok(Some(problem)) => report.problems.push(problem),
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "Preflight check failed"),
+        }
+        let _ = app.emit_to("main", "preflight-progress", PreflightProgress { checked, total });
+    }
+
+    report.problems.sort_by(|a, b| a.path.cmp(&b.path));
+    report
+}
+
+async fn check_file(app: &AppHandle, path: PathBuf) -> Option<ExcludedFile> {
+    let problem = |reason, detail: Option<String>| {
+        Some(ExcludedFile {
+            path: path.clone(),
+            reason,
+            detail,
+        })
+    };
+
+    let metadata = match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        Ok(_) => return problem(ExclusionReason::Unreadable, Some("Not a file".to_string())),
+        Err(e) => return problem(ExclusionReason::Unreadable, Some(e.to_string())),
+    };
+    if metadata.len() == 0 {
+        return problem(ExclusionReason::ZeroBytes, None);
+    }
+
+    let is_image = if is_audio_file(&path) {
+        false
+    } else {
+        match detect_file_type(&path) {
+            Ok(is_video) => !is_video,
+            Err(e) => return problem(ExclusionReason::UnsupportedExtension, Some(e.to_string())),
+        }
+    };
+
+    // Image headers are cheap to read in process; FFmpeg is the fallback for
+    // formats the image crate does not know
+    if is_image && image::image_dimensions(&path).is_ok() {
+        return None;
+    }
+    match probe_stream_health(app, &path).await {
+        Ok(()) => None,
+        Err(e) => problem(ExclusionReason::Corrupt, Some(e.to_string())),
+    }
+}
@@ -0,0 +1,640 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+use tracing::warn;
+
+use crate::last_config::SETTINGS_STORE;
+use crate::types::{AssetField, EmbeddedAsset, PresetMetadata, PresetSource, WatermarkPreset};
+
+const LOCK_FILE: &str = ".presets.lock";
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(25);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+// A lock file this old was left behind by a crashed process
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+/// Longest `base` chain followed when resolving a preset.
+const MAX_INHERITANCE_DEPTH: usize = 8;
+/// Subdirectory of the user presets directory holding imported images
+const ASSETS_DIR: &str = "assets";
+const DEFAULT_PRESET_KEY: &str = "defaultPresetId";
+/// Bundled preset that is the default until another one is chosen
+const BUNDLED_DEFAULT_PRESET: &str = "default";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PresetsChanged<'a> {
+    id: &'a str,
+    deleted: bool,
+}
+
+/// Bundled read-only presets plus user presets in the app data directory,
+/// managed as Tauri state. Access to the user directory is serialized within
+/// the process by a mutex and across processes by a lock file, and every
+/// write goes through a temporary file that is renamed into place.
+#[derive(Default)]
+pub struct PresetRepository {
+    guard: Mutex<()>,
+}
+
+impl PresetRepository {
+    /// Lists bundled and user presets sorted by name. A user preset with the
+    /// same ID as a bundled one replaces it. Only presets in `filter.category`
+    /// carrying every tag in `filter.tags` are listed; both compare without
+    /// regard to case.
+    pub fn list(
+        &self,
+        app: &AppHandle,
+        filter: &PresetFilter,
+    ) -> Result<Vec<PresetMetadata>, String> {
+        let mut presets = read_preset_dir(&bundled_dir(app)?, PresetSource::Bundled)?;
+
+        let _guard = self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let user_dir = user_dir(app)?;
+        if user_dir.is_dir() {
+            let _lock = DirLock::acquire(&user_dir)?;
+            for preset in read_preset_dir(&user_dir, PresetSource::User)? {
+                presets.retain(|existing| existing.id != preset.id);
+                presets.push(preset);
+            }
+        }
+
+        let default_id = self.default_id(app);
+        for preset in &mut presets {
+            preset.is_default = preset.id == default_id;
+        }
+        presets.retain(|preset| filter.matches(preset));
+        presets.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(presets)
+    }
+
+    /// Reads a preset, preferring a user preset over a bundled one. When it
+    /// declares a `base`, its config and output overrides are applied on top
+    /// of the resolved base preset.
+    pub fn load(&self, app: &AppHandle, preset_id: &str) -> Result<WatermarkPreset, String> {
+        validate_preset_id(preset_id)?;
+        let dirs = PresetDirs::resolve(app)?;
+
+        let _guard = self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _lock = dirs.lock_user()?;
+        let preset = resolve_preset(&dirs, preset_id, &mut Vec::new())?;
+        serde_json::from_value(preset).map_err(|e| format!("Invalid preset format: {}", e))
+    }
+
+    /// Creates or replaces a user preset and notifies every window. A preset
+    /// with a `base` is stored as the config and output fields that differ
+    /// from the base, so later changes to the base carry over.
+    pub fn save(
+        &self,
+        app: &AppHandle,
+        preset_id: &str,
+        preset: &WatermarkPreset,
+    ) -> Result<(), String> {
+        validate_preset_id(preset_id)?;
+        let dirs = PresetDirs::resolve(app)?;
+        let mut stored = serde_json::to_value(preset)
+            .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+
+        {
+            let _guard = self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            std::fs::create_dir_all(&dirs.user)
+                .map_err(|e| format!("Failed to create presets directory: {}", e))?;
+            let _lock = DirLock::acquire(&dirs.user)?;
+
+            if let Some(base_id) = &preset.base {
+                validate_preset_id(base_id)?;
+                // Starting the chain at this preset rejects bases that inherit from it
+                let base = resolve_preset(&dirs, base_id, &mut vec![preset_id.to_string()])?;
+                let base: WatermarkPreset = serde_json::from_value(base)
+                    .map_err(|e| format!("Invalid base preset {}: {}", base_id, e))?;
+                let base_config = serde_json::to_value(&base.config)
+                    .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+                let config = stored["config"].take();
+                stored["config"] = config_overrides(&base_config, config);
+                // Without its own output settings the preset inherits the base's
+                if let Some(output) = stored.get_mut("output") {
+                    let base_output = serde_json::to_value(&base.output)
+                        .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+                    *output = config_overrides(&base_output, output.take());
+                }
+            }
+
+            let content = serde_json::to_string_pretty(&stored)
+                .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+            write_atomic(&dirs.user.join(format!("{}.json", preset_id)), content.as_bytes())
+                .map_err(|e| format!("Failed to write preset: {}", e))?;
+        }
+
+        emit_changed(app, preset_id, false);
+        Ok(())
+    }
+
+    /// Deletes a user preset and notifies every window. Bundled presets
+    /// cannot be deleted.
+    pub fn delete(&self, app: &AppHandle, preset_id: &str) -> Result<(), String> {
+        validate_preset_id(preset_id)?;
+
+        {
+            let _guard = self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let user_dir = user_dir(app)?;
+            let path = user_dir.join(format!("{}.json", preset_id));
+            if !path.is_file() {
+                return Err(format!("User preset not found: {}", preset_id));
+            }
+            let _lock = DirLock::acquire(&user_dir)?;
+            std::fs::remove_file(&path).map_err(|e| format!("Failed to delete preset: {}", e))?;
+        }
+        self.discard_assets(app, preset_id);
+        // A bundled preset with the same ID can stay the default
+        if self.default_id(app) == preset_id && self.load(app, preset_id).is_err() {
+            self.set_default(app, None)?;
+        }
+
+        emit_changed(app, preset_id, true);
+        Ok(())
+    }
+
+    /// Changes the display name of a user preset and notifies every window.
+    /// The ID stays the same, so presets based on it and saved selections
+    /// keep working. Bundled presets cannot be renamed.
+    pub fn rename(&self, app: &AppHandle, preset_id: &str, name: &str) -> Result<(), String> {
+        validate_preset_id(preset_id)?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Preset name cannot be empty".to_string());
+        }
+
+        {
+            let _guard = self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let user_dir = user_dir(app)?;
+            let path = user_dir.join(format!("{}.json", preset_id));
+            if !path.is_file() {
+                return Err(format!("User preset not found: {}", preset_id));
+            }
+            let _lock = DirLock::acquire(&user_dir)?;
+            // Edited as JSON so override-only configs of derived presets stay as they are
+            let mut preset = read_preset_value(&path)?;
+            preset["name"] = Value::String(name.to_string());
+            let content = serde_json::to_string_pretty(&preset)
+                .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+            write_atomic(&path, content.as_bytes())
+                .map_err(|e| format!("Failed to write preset: {}", e))?;
+        }
+
+        emit_changed(app, preset_id, false);
+        Ok(())
+    }
+
+    /// Writes a preset to `path` as a standalone file with its base chain
+    /// resolved. With `embed_assets` the watermark images are included and
+    /// their local paths left out, so the file works on another machine.
+    pub fn export(
+        &self,
+        app: &AppHandle,
+        preset_id: &str,
+        path: &Path,
+        embed_assets: bool,
+    ) -> Result<(), String> {
+        let mut preset = self.load(app, preset_id)?;
+        preset.base = None;
+        if embed_assets {
+            for field in AssetField::ALL {
+                let Some(asset_path) = field.path_mut(&mut preset.config).take() else {
+                    continue;
+                };
+                let asset_path = PathBuf::from(asset_path);
+                let data = std::fs::read(&asset_path)
+                    .map_err(|e| format!("Failed to read {}: {}", asset_path.display(), e))?;
+                preset.assets.push(EmbeddedAsset {
+                    field,
+                    file_name: asset_file_name(&asset_path.to_string_lossy()),
+                    data: BASE64.encode(data),
+                });
+            }
+        }
+
+        let content = serde_json::to_string_pretty(&preset)
+            .map_err(|e| format!("Failed to serialize preset: {}", e))?;
+        std::fs::write(path, content).map_err(|e| format!("Failed to export preset: {}", e))
+    }
+
+    /// Reads an exported preset file and picks an unused ID for it from the
+    /// file name. Embedded images, and referenced images that exist on this
+    /// machine, are stored with the user presets so the preset does not
+    /// depend on outside files. The preset itself is saved with `save`.
+    pub fn prepare_import(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+    ) -> Result<(String, WatermarkPreset), String> {
+        let mut preset = read_preset(path)?;
+        let dirs = PresetDirs::resolve(app)?;
+        let preset_id = {
+            let _guard = self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let _lock = dirs.lock_user()?;
+            unused_preset_id(&dirs, path)
+        };
+
+        let asset_dir = dirs.assets(&preset_id);
+        let stored = (|| {
+            for asset in std::mem::take(&mut preset.assets) {
+                let data = BASE64
+                    .decode(&asset.data)
+                    .map_err(|e| format!("Invalid embedded asset {}: {}", asset.file_name, e))?;
+                let target = store_asset(&asset_dir, asset.field, &asset.file_name)?;
+                std::fs::write(&target, data)
+                    .map_err(|e| format!("Failed to store {}: {}", asset.file_name, e))?;
+                *asset.field.path_mut(&mut preset.config) =
+                    Some(target.to_string_lossy().into_owned());
+            }
+            for field in AssetField::ALL {
+                let slot = field.path_mut(&mut preset.config);
+                let Some(source) = slot.as_deref().map(PathBuf::from) else {
+                    continue;
+                };
+                if source.starts_with(&asset_dir) || !source.is_file() {
+                    continue;
+                }
+                let target = store_asset(&asset_dir, field, &source.to_string_lossy())?;
+                std::fs::copy(&source, &target)
+                    .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+                *slot = Some(target.to_string_lossy().into_owned());
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = stored {
+            self.discard_assets(app, &preset_id);
+            return Err(e);
+        }
+        Ok((preset_id, preset))
+    }
+
+    /// ID of the preset to start from when none is named: the one chosen with
+    /// `set_default`, or the bundled default.
+    pub fn default_id(&self, app: &AppHandle) -> String {
+        app.store(SETTINGS_STORE)
+            .ok()
+            .and_then(|store| store.get(DEFAULT_PRESET_KEY))
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_else(|| BUNDLED_DEFAULT_PRESET.to_string())
+    }
+
+    /// Makes an existing preset the default and notifies every window; `None`
+    /// goes back to the bundled default.
+    pub fn set_default(&self, app: &AppHandle, preset_id: Option<&str>) -> Result<(), String> {
+        let store = app
+            .store(SETTINGS_STORE)
+            .map_err(|e| format!("Failed to open settings store: {}", e))?;
+        match preset_id {
+            Some(preset_id) => {
+                self.load(app, preset_id)?;
+                store.set(DEFAULT_PRESET_KEY, preset_id);
+            }
+            None => {
+                store.delete(DEFAULT_PRESET_KEY);
+            }
+        }
+        emit_changed(app, preset_id.unwrap_or(BUNDLED_DEFAULT_PRESET), false);
+        Ok(())
+    }
+
+    /// Removes the images stored for a user preset, if any.
+    pub fn discard_assets(&self, app: &AppHandle, preset_id: &str) {
+        if let Ok(dirs) = PresetDirs::resolve(app) {
+            let asset_dir = dirs.assets(preset_id);
+            if asset_dir.is_dir() {
+                if let Err(e) = std::fs::remove_dir_all(&asset_dir) {
+                    warn!(
+                        path = %asset_dir.display(),
+                        error = %e,
+                        "Failed to remove preset assets"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Narrows `PresetRepository::list` to a category and set of tags.
+#[derive(Debug, Default)]
+pub struct PresetFilter {
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl PresetFilter {
+    fn matches(&self, preset: &PresetMetadata) -> bool {
+        let category_matches = self.category.as_ref().is_none_or(|category| {
+            preset.category.as_ref().is_some_and(|own| own.eq_ignore_ascii_case(category))
+        });
+        category_matches
+            && self
+                .tags
+                .iter()
+                .all(|tag| preset.tags.iter().any(|own| own.eq_ignore_ascii_case(tag)))
+    }
+}
+
+/// The bundled and user presets directories.
+struct PresetDirs {
+    bundled: PathBuf,
+    user: PathBuf,
+}
+
+impl PresetDirs {
+    fn resolve(app: &AppHandle) -> Result<Self, String> {
+        Ok(Self {
+            bundled: bundled_dir(app)?,
+            user: user_dir(app)?,
+        })
+    }
+
+    /// File of a preset, preferring a user preset over a bundled one.
+    fn path(&self, preset_id: &str) -> PathBuf {
+        let file_name = format!("{}.json", preset_id);
+        let user_path = self.user.join(&file_name);
+        if user_path.is_file() {
+            user_path
+        } else {
+            self.bundled.join(file_name)
+        }
+    }
+
+    /// Directory holding the images of an imported user preset.
+    fn assets(&self, preset_id: &str) -> PathBuf {
+        self.user.join(ASSETS_DIR).join(preset_id)
+    }
+
+    fn lock_user(&self) -> Result<Option<DirLock>, String> {
+        if self.user.is_dir() {
+            DirLock::acquire(&self.user).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Exclusive lock on a presets directory, held while the guard is alive.
+struct DirLock {
+    path: PathBuf,
+}
+
+impl DirLock {
+    fn acquire(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(LOCK_FILE);
+        let deadline = SystemTime::now() + LOCK_TIMEOUT;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if is_stale(&path) {
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    if SystemTime::now() >= deadline {
+                        return Err("Timed out waiting for the presets directory lock".to_string());
+                    }
+                    std::thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(e) => return Err(format!("Failed to lock presets directory: {}", e)),
+            }
+        }
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn is_stale(lock_path: &Path) -> bool {
+    std::fs::metadata(lock_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map_or(false, |age| age > STALE_LOCK_AGE)
+}
+
+/// Writes `content` to a sibling temporary file and renames it over `path`,
+/// so readers never see a partially written preset.
+fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn read_preset_dir(dir: &Path, source: PresetSource) -> Result<Vec<PresetMetadata>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read presets directory: {}", e))?;
+
+    let mut presets = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                warn!(error = %e, "Failed to read directory entry");
+                continue;
+            }
+        };
+
+        let path = entry.path();
+
+        // Only process .json files; leftover .json.tmp files are skipped too
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+
+        let id = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(s) => s.to_string(),
+            None => continue,
+        };
+
+        match read_preset(&path) {
+            Ok(preset) => presets.push(PresetMetadata {
+                id,
+                name: preset.name,
+                description: preset.description,
+                source,
+                category: preset.category,
+                tags: preset.tags,
+                is_default: false,
+            }),
+            Err(e) => warn!(path = %path.display(), error = %e, "Skipping preset"),
+        }
+    }
+    Ok(presets)
+}
+
+fn read_preset(path: &Path) -> Result<WatermarkPreset, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Preset not found: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid preset format: {}", e))
+}
+
+/// Reads a preset as JSON and applies it on top of its `base` chain. `chain`
+/// holds the presets that inherit from this one, to detect cycles.
+fn resolve_preset(
+    dirs: &PresetDirs,
+    preset_id: &str,
+    chain: &mut Vec<String>,
+) -> Result<Value, String> {
+    if chain.iter().any(|id| id == preset_id) {
+        return Err(format!(
+            "Preset inheritance cycle: {} -> {}",
+            chain.join(" -> "),
+            preset_id
+        ));
+    }
+    if chain.len() >= MAX_INHERITANCE_DEPTH {
+        return Err(format!("Preset {} has too many base presets", chain[0]));
+    }
+
+    let mut preset = read_preset_value(&dirs.path(preset_id))?;
+    let Some(base_id) = preset.get("base").and_then(Value::as_str).map(str::to_string) else {
+        return Ok(preset);
+    };
+    validate_preset_id(&base_id)?;
+
+    chain.push(preset_id.to_string());
+    let base = resolve_preset(dirs, &base_id, chain)?;
+    chain.pop();
+
+    for key in ["config", "output"] {
+        let mut merged = base
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| Value::Object(Map::new()));
+        if let Some(overrides) = preset.get_mut(key) {
+            merge_overrides(&mut merged, overrides.take());
+        }
+        preset[key] = merged;
+    }
+    Ok(preset)
+}
+
+/// Applies `overrides` on top of `base`: objects are merged key by key, any
+/// other value (including `null`) replaces the base value.
+fn merge_overrides(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge_overrides(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Top-level fields of `config` whose value differs from `base_config`.
+fn config_overrides(base_config: &Value, config: Value) -> Value {
+    match (base_config.as_object(), config) {
+        (Some(base_config), Value::Object(config)) => Value::Object(
+            config
+                .into_iter()
+                .filter(|(key, value)| base_config.get(key) != Some(value))
+                .collect(),
+        ),
+        (_, config) => config,
+    }
+}
+
+fn read_preset_value(path: &Path) -> Result<Value, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Preset not found: {}", e))?;
+    let preset: Value =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid preset format: {}", e))?;
+    if !preset.is_object() {
+        return Err("Invalid preset format: expected an object".to_string());
+    }
+    Ok(preset)
+}
+
+/// An ID for an imported preset: the file name with invalid characters
+/// replaced, plus a number when a preset with that ID exists.
+fn unused_preset_id(dirs: &PresetDirs, path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut base: String = stem
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    if base.is_empty() {
+        base = "imported".to_string();
+    }
+
+    let taken = |id: &str| {
+        let file_name = format!("{}.json", id);
+        dirs.user.join(&file_name).is_file() || dirs.bundled.join(&file_name).is_file()
+    };
+    let mut id = base.clone();
+    let mut suffix = 2;
+    while taken(&id) {
+        id = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+    id
+}
+
+/// Last component of an asset path, as an exported file should name it.
+fn asset_file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "watermark".to_string())
+}
+
+/// Creates the preset's asset directory and returns where `file_name` is
+/// stored in it. Only the last path component is used, so an imported file
+/// cannot write outside the directory.
+fn store_asset(asset_dir: &Path, field: AssetField, file_name: &str) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(asset_dir)
+        .map_err(|e| format!("Failed to create preset assets directory: {}", e))?;
+    Ok(asset_dir.join(format!("{}-{}", field.name(), asset_file_name(file_name))))
+}
+
+fn validate_preset_id(preset_id: &str) -> Result<(), String> {
+    // Prevents path traversal out of the presets directories
+    if preset_id.is_empty()
+        || !preset_id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Invalid preset ID".to_string());
+    }
+    Ok(())
+}
+
+fn bundled_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .resolve("resources/presets", tauri::path::BaseDirectory::Resource)
+        .map_err(|e| format!("Failed to resolve presets directory: {}", e))
+}
+
+fn user_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("presets"))
+        .map_err(|e| format!("Failed to resolve user presets directory: {}", e))
+}
+
+fn emit_changed(app: &AppHandle, id: &str, deleted: bool) {
+    // Broadcast so preset lists in every open window refresh
+    let _ = app.emit("presets-changed", PresetsChanged { id, deleted });
+}
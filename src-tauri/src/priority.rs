@@ -0,0 +1,73 @@
+use std::io;
+
+/// Nice value FFmpeg runs at in background mode; the highest nice value is 19.
+#[cfg(unix)]
+const BACKGROUND_NICE: libc::c_int = 10;
+
+/// Lowers the scheduling priority of process `pid`, so other programs stay
+/// responsive while it runs. Unix renices it; on Linux every thread the
+/// process has so far, as nice values there are per thread and later threads
+/// inherit them. Windows moves the process to the below-normal class.
+pub fn lower_priority(pid: u32) -> io::Result<()> {
+    set_low_priority(pid)
+}
+
+#[cfg(target_os = "linux")]
+fn set_low_priority(pid: u32) -> io::Result<()> {
+    match std::fs::read_dir(format!("/proc/{}/task", pid)) {
+        Ok(tasks) => {
+            for tid in tasks
+                .flatten()
+                .filter_map(|task| task.file_name().to_str()?.parse::<libc::id_t>().ok())
+            {
+                renice(tid)?;
+            }
+            Ok(())
+        }
+        Err(_) => renice(pid as libc::id_t),
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn set_low_priority(pid: u32) -> io::Result<()> {
+    renice(pid as libc::id_t)
+}
+
+#[cfg(unix)]
+fn renice(id: libc::id_t) -> io::Result<()> {
+    // SAFETY: setpriority only reads its arguments
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, id, BACKGROUND_NICE) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+fn set_low_priority(pid: u32) -> io::Result<()> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, PROCESS_SET_INFORMATION,
+    };
+
+    // SAFETY: the handle is checked before use and closed exactly once
+    unsafe {
+        let handle = OpenProcess(PROCESS_SET_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let result = SetPriorityClass(handle, BELOW_NORMAL_PRIORITY_CLASS);
+        let error = (result == 0).then(io::Error::last_os_error);
+        CloseHandle(handle);
+        error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn set_low_priority(_pid: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "process priority is not supported on this platform",
+    ))
+}
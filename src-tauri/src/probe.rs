@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use tauri::AppHandle;
+
+use crate::ffmpeg::{detect_file_type, ffmpeg_command, get_ffprobe_sidecar_path, FfmpegError};
+use crate::types::{AudioStreamInfo, MediaInfo, SourceColor};
+
+/// The parts of `ffprobe -show_format -show_streams` JSON output that are used.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ProbeOutput {
+    streams: Vec<ProbeStream>,
+    format: ProbeFormat,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ProbeStream {
+    index: u32,
+    codec_type: String,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    pix_fmt: Option<String>,
+    bits_per_raw_sample: Option<String>,
+    color_space: Option<String>,
+    color_transfer: Option<String>,
+    color_primaries: Option<String>,
+    channels: Option<u32>,
+    sample_rate: Option<String>,
+    tags: HashMap<String, String>,
+    disposition: HashMap<String, i64>,
+    side_data_list: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ProbeFormat {
+    duration: Option<String>,
+    format_name: Option<String>,
+}
+
+/// Reads dimensions, duration, codecs, frame rate, rotation, color format
+/// and audio streams with ffprobe.
+pub async fn probe_media_info(app: &AppHandle, path: &Path) -> Result<MediaInfo, FfmpegError> {
+    let probe = run_ffprobe(app, path).await?;
+    let video = main_video_stream(&probe);
+
+    let width = video.and_then(|stream| stream.width);
+    let height = video.and_then(|stream| stream.height);
+    let rotation = video.map_or(0, stream_rotation);
+    let (display_width, display_height) = match (width, height) {
+        (Some(width), Some(height)) => {
+            let (width, height) = displayed_size(width, height, rotation);
+            (Some(width), Some(height))
+        }
+        _ => (None, None),
+    };
+
+    Ok(MediaInfo {
+        width,
+        height,
+        display_width,
+        display_height,
+        duration: probe
+            .format
+            .duration
+            .as_deref()
+            .and_then(|duration| duration.parse::<f64>().ok())
+            .filter(|duration| *duration > 0.0),
+        format: probe.format.format_name,
+        video_codec: video.and_then(|stream| stream.codec_name.clone()),
+        frame_rate: video.and_then(|stream| {
+            [&stream.avg_frame_rate, &stream.r_frame_rate]
+                .into_iter()
+                .find_map(|rate| parse_frame_rate(rate.as_deref()?))
+        }),
+        rotation,
+        pixel_format: video.and_then(|stream| stream.pix_fmt.clone()),
+        bit_depth: video.and_then(stream_bit_depth),
+        hdr: video.is_some_and(|stream| {
+            matches!(stream.color_transfer.as_deref(), Some("smpte2084" | "arib-std-b67"))
+        }),
+        audio_streams: probe
+            .streams
+            .iter()
+            .filter(|stream| stream.codec_type == "audio")
+            .map(|stream| AudioStreamInfo {
+                index: stream.index,
+                codec: stream.codec_name.clone(),
+                channels: stream.channels,
+                sample_rate: stream.sample_rate.as_deref().and_then(|rate| rate.parse().ok()),
+                language: stream.tags.get("language").cloned(),
+            })
+            .collect(),
+    })
+}
+
+/// Returns the color format of a video with more than 8 bits per component
+/// or an HDR transfer, and `None` for ordinary 8-bit SDR video.
+pub async fn probe_source_color(
+    app: &AppHandle,
+    path: &Path,
+) -> Result<Option<SourceColor>, FfmpegError> {
+    let probe = run_ffprobe(app, path).await?;
+    let Some(video) = main_video_stream(&probe) else {
+        return Ok(None);
+    };
+
+    let side_data = |kind: &str| {
+        video.side_data_list.iter().find(|side_data| {
+            side_data.get("side_data_type").and_then(serde_json::Value::as_str) == Some(kind)
+        })
+    };
+    let color = SourceColor {
+        bit_depth: stream_bit_depth(video).unwrap_or(8),
+        primaries: video.color_primaries.clone().filter(|name| name != "unknown"),
+        transfer: video.color_transfer.clone().filter(|name| name != "unknown"),
+        matrix: video.color_space.clone().filter(|name| name != "unknown"),
+        master_display: side_data("Mastering display metadata").and_then(master_display),
+        max_cll: side_data("Content light level metadata").and_then(max_cll),
+    };
+    Ok((color.bit_depth > 8 || color.is_hdr()).then_some(color))
+}
+
+/// Returns the codec of every audio stream, in stream order.
+pub async fn probe_audio_codecs(app: &AppHandle, path: &Path) -> Result<Vec<String>, FfmpegError> {
+    let probe = run_ffprobe(app, path).await?;
+    Ok(probe
+        .streams
+        .into_iter()
+        .filter(|stream| stream.codec_type == "audio")
+        .filter_map(|stream| stream.codec_name)
+        .collect())
+}
+
+/// Runs ffprobe for the format and streams of `path`.
+async fn run_ffprobe(app: &AppHandle, path: &Path) -> Result<ProbeOutput, FfmpegError> {
+    let _ = get_ffprobe_sidecar_path(app)?;
+    let output = ffmpeg_command(app, "ffprobe")?
+        .args([
+            "-v".to_string(),
+            "error".to_string(),
+            "-print_format".to_string(),
+            "json".to_string(),
+            "-show_format".to_string(),
+            "-show_streams".to_string(),
+            path.to_string_lossy().into_owned(),
+        ])
+        .output()
+        .await
+        .map_err(|e| FfmpegError::Execution(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(FfmpegError::UnsupportedFormat(format!(
+            "{} cannot be read: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| FfmpegError::Execution(format!("invalid ffprobe output: {}", e)))
+}
+
+/// The first video stream that is not cover art; embedded cover art shows up
+/// as a one-frame video stream.
+fn main_video_stream(probe: &ProbeOutput) -> Option<&ProbeStream> {
+    probe.streams.iter().find(|stream| {
+        stream.codec_type == "video" && stream.disposition.get("attached_pic") != Some(&1)
+    })
+}
+
+/// Returns the pixel dimensions of an image or video as FFmpeg filters see
+/// them. Images are read from the file header; videos are probed with
+/// ffprobe, falling back to parsing FFmpeg's stream summary.
+pub async fn probe_dimensions(app: &AppHandle, path: &Path) -> Result<(u32, u32), FfmpegError> {
+    if matches!(detect_file_type(path), Ok(false)) {
+        if let Ok(dimensions) = image::image_dimensions(path) {
+            return Ok(dimensions);
+        }
+    }
+
+    if let Ok(info) = probe_media_info(app, path).await {
+        if let (Some(width), Some(height)) = (info.display_width, info.display_height) {
+            return Ok((width, height));
+        }
+    }
+
+    let summary = read_stream_summary(app, path).await?;
+    let (width, height) = summary
+        .lines()
+        .filter(|line| line.contains("Video:"))
+        .find_map(parse_dimensions)
+        .ok_or_else(|| {
+            FfmpegError::UnsupportedFormat(format!(
+                "could not determine dimensions of {}",
+                path.display()
+            ))
+        })?;
+    Ok(displayed_size(width, height, summary_rotation(&summary)))
+}
+
+/// Returns the clockwise rotation a player applies to the video, and FFmpeg
+/// applies before filtering: 0, 90, 180 or 270.
+pub async fn probe_rotation(app: &AppHandle, path: &Path) -> Result<i32, FfmpegError> {
+    if let Ok(info) = probe_media_info(app, path).await {
+        return Ok(info.rotation);
+    }
+    let summary = read_stream_summary(app, path).await?;
+    Ok(summary_rotation(&summary))
+}
+
+/// Returns the container duration in seconds.
+pub async fn probe_duration(app: &AppHandle, path: &Path) -> Result<f64, FfmpegError> {
+    let summary = read_stream_summary(app, path).await?;
+    summary
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Duration:"))
+        .and_then(parse_duration)
+        .filter(|duration| *duration > 0.0)
+        .ok_or_else(|| {
+            FfmpegError::UnsupportedFormat(format!(
+                "could not determine duration of {}",
+                path.display()
+            ))
+        })
+}
+
+/// Checks that FFmpeg can open `path` and find at least one stream in it,
+/// without decoding any frames.
+pub async fn probe_stream_health(app: &AppHandle, path: &Path) -> Result<(), FfmpegError> {
+    let summary = read_stream_summary(app, path).await?;
+    if summary.lines().any(|line| line.trim_start().starts_with("Stream #")) {
+        return Ok(());
+    }
+
+    // The last line names the reason, e.g. "moov atom not found" or
+    // "Invalid data found when processing input"
+    let reason = summary
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("At least one output file"))
+        .unwrap_or("no streams found");
+    Err(FfmpegError::UnsupportedFormat(format!(
+        "{} cannot be read: {}",
+        path.display(),
+        reason
+    )))
+}
+
+/// Runs `ffmpeg -i` and returns the stream summary it prints to stderr.
+async fn read_stream_summary(app: &AppHandle, path: &Path) -> Result<String, FfmpegError> {
+    // `ffmpeg -i` without an output exits with an error but still prints the
+    // stream summary, so the exit status is ignored here
+    let output = ffmpeg_command(app, "ffmpeg")?
+        .args(["-hide_banner".to_string(), "-i".to_string(), path.to_string_lossy().into_owned()])
+        .output()
+        .await
+        .map_err(|e| FfmpegError::Execution(e.to_string()))?;
+
+    Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+/// Parses an ffprobe rational such as `30000/1001`; `0/0` means unknown.
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (numerator, denominator) = rate.split_once('/')?;
+    let numerator = numerator.parse::<f64>().ok()?;
+    let denominator = denominator.parse::<f64>().ok()?;
+    (numerator > 0.0 && denominator > 0.0).then(|| numerator / denominator)
+}
+
+/// Clockwise rotation in degrees (0, 90, 180 or 270) a player applies when
+/// displaying the stream. Newer files carry it in a display matrix, older
+/// ones in a `rotate` tag.
+fn stream_rotation(stream: &ProbeStream) -> i32 {
+    let degrees = stream
+        .side_data_list
+        .iter()
+        .find_map(|side_data| side_data.get("rotation")?.as_f64())
+        // The display matrix rotation is counter-clockwise
+        .map(|rotation| -rotation)
+        .or_else(|| stream.tags.get("rotate")?.parse::<f64>().ok())
+        .unwrap_or(0.0);
+    (degrees.round() as i32).rem_euclid(360)
+}
+
+/// Bits per color component, from `bits_per_raw_sample` or the pixel format
+/// name, e.g. `yuv420p10le` or `p010le`.
+fn stream_bit_depth(stream: &ProbeStream) -> Option<u32> {
+    if let Some(bits) = stream.bits_per_raw_sample.as_deref().and_then(|bits| bits.parse().ok()) {
+        return Some(bits);
+    }
+    let pix_fmt = stream.pix_fmt.as_deref()?;
+    let name = pix_fmt.strip_suffix("le").or_else(|| pix_fmt.strip_suffix("be")).unwrap_or(pix_fmt);
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    // Packed formats such as rgb24 name the bits per pixel instead
+    match name[name.len() - digits..].parse::<u32>() {
+        Ok(bits) if (9..=16).contains(&bits) => Some(bits),
+        _ => Some(8),
+    }
+}
+
+/// Formats mastering display side data for x265: chromaticities in units of
+/// 0.00002 and luminance in units of 0.0001 cd/m², ordered G, B, R.
+fn master_display(side_data: &serde_json::Value) -> Option<String> {
+    let value = |key: &str, unit: f64| -> Option<u64> {
+        let rational = side_data.get(key)?.as_str()?;
+        Some((parse_rational(rational)? * unit).round() as u64)
+    };
+    let point = |color: &str| -> Option<String> {
+        let x = value(&format!("{}_x", color), 50_000.0)?;
+        let y = value(&format!("{}_y", color), 50_000.0)?;
+        Some(format!("({},{})", x, y))
+    };
+    Some(format!(
+        "G{}B{}R{}WP{}L({},{})",
+        point("green")?,
+        point("blue")?,
+        point("red")?,
+        point("white_point")?,
+        value("max_luminance", 10_000.0)?,
+        value("min_luminance", 10_000.0)?
+    ))
+}
+
+/// Formats content light level side data for x265 as `max-cll,max-fall`.
+fn max_cll(side_data: &serde_json::Value) -> Option<String> {
+    let max_content = side_data.get("max_content")?.as_u64()?;
+    let max_average = side_data.get("max_average")?.as_u64()?;
+    Some(format!("{},{}", max_content, max_average))
+}
+
+/// Parses an ffprobe rational such as `34000/50000`.
+fn parse_rational(value: &str) -> Option<f64> {
+    let (numerator, denominator) = value.split_once('/')?;
+    let denominator = denominator.parse::<f64>().ok().filter(|d| *d != 0.0)?;
+    Some(numerator.parse::<f64>().ok()? / denominator)
+}
+
+/// FFmpeg auto-rotates before filtering, so filters see the displayed size.
+fn displayed_size(width: u32, height: u32, rotation: i32) -> (u32, u32) {
+    if rotation % 180 == 90 {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+/// Rotation of the first video stream in an `ffmpeg -i` summary, from its
+/// `displaymatrix` side data line or, on older FFmpeg, its `rotate` tag.
+fn summary_rotation(summary: &str) -> i32 {
+    let degrees = summary.lines().map(str::trim).find_map(|line| {
+        if let Some(rest) = line.strip_prefix("displaymatrix: rotation of") {
+            // The display matrix rotation is counter-clockwise
+            let degrees = rest.trim().trim_end_matches("degrees").trim();
+            return degrees.parse::<f64>().ok().map(|rotation| -rotation);
+        }
+        let (key, value) = line.split_once(':')?;
+        if key.trim() != "rotate" {
+            return None;
+        }
+        value.trim().parse::<f64>().ok()
+    });
+    (degrees.unwrap_or(0.0).round() as i32).rem_euclid(360)
+}
+
+/// Parses the `HH:MM:SS.ss` value that starts a `Duration:` line.
+fn parse_duration(value: &str) -> Option<f64> {
+    let timestamp = value.split(',').next()?.trim();
+    let mut parts = timestamp.split(':');
+    let hours = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next()?.parse::<f64>().ok()?;
+    let seconds = parts.next()?.parse::<f64>().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+fn parse_dimensions(line: &str) -> Option<(u32, u32)> {
+    line.split(|c: char| c.is_whitespace() || c == ',')
+        .filter_map(|token| {
+            let (width, height) = token.split_once('x')?;
+            let width = width.parse::<u32>().ok()?;
+            let height = height.parse::<u32>().ok()?;
+            // Skips codec tags such as 0x31637661
+            (width > 0 && height > 0).then_some((width, height))
+        })
+        .next()
+}
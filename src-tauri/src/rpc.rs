@@ -0,0 +1,441 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Listener, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinSet;
+
+use crate::commands;
+use crate::error::{AppError, ErrorCode};
+
+/// Command-line flag that starts the app as a JSON-RPC server on stdio.
+pub const RPC_FLAG: &str = "--rpc";
+
+/// Events forwarded to the client as `event` notifications.
+const FORWARDED_EVENTS: &[&str] = &[
+    "watermark-progress",
+    "watermark-complete",
+    "preflight-progress",
+    "hot-folder-processed",
+    "presets-changed",
+    "update-progress",
+    "watermark-file-log",
+    "ffmpeg-download-progress",
+    "thumbnail-ready",
+];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Errors returned by a command; `data` holds the `AppError`
+const COMMAND_ERROR: i64 = -32000;
+
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == RPC_FLAG)
+}
+
+enum Outgoing {
+    Message(Value),
+    /// Sent once every request has been answered; exits the app
+    Exit,
+}
+
+enum RpcError {
+    MethodNotFound(String),
+    InvalidParams(String),
+    Command(AppError),
+}
+
+/// Serves JSON-RPC 2.0 on stdin/stdout, one message per line, exposing the
+/// same commands and parameter names as the invoke layer. Requests run
+/// concurrently; the app exits once stdin closes and they have finished.
+pub fn start(app: AppHandle) {
+    let (sender, receiver) = unbounded_channel();
+
+    for event in FORWARDED_EVENTS {
+        let sender = sender.clone();
+        app.listen_any(*event, move |emitted| {
+            let payload = serde_json::from_str(emitted.payload()).unwrap_or(Value::Null);
+            let _ = sender.send(Outgoing::Message(json!({
+                "jsonrpc": "2.0",
+                "method": "event",
+                "params": { "event": event, "payload": payload },
+            })));
+        });
+    }
+
+    tauri::async_runtime::spawn(write_messages(app.clone(), receiver));
+    tauri::async_runtime::spawn(async move {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut requests = JoinSet::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let app = app.clone();
+            let sender = sender.clone();
+            requests.spawn(async move {
+                if let Some(response) = handle_line(&app, &line).await {
+                    let _ = sender.send(Outgoing::Message(response));
+                }
+            });
+        }
+        while requests.join_next().await.is_some() {}
+        let _ = sender.send(Outgoing::Exit);
+    });
+}
+
+/// Writes messages in the order they were queued, so a batch's progress
+/// notifications precede its response.
+async fn write_messages(app: AppHandle, mut receiver: UnboundedReceiver<Outgoing>) {
+    let mut stdout = tokio::io::stdout();
+    while let Some(outgoing) = receiver.recv().await {
+        match outgoing {
+            Outgoing::Message(message) => {
+                let mut line = message.to_string();
+                line.push('\n');
+                if stdout.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+                let _ = stdout.flush().await;
+            }
+            Outgoing::Exit => break,
+        }
+    }
+    app.exit(0);
+}
+
+/// Answers one request line. Notifications (requests without an `id`) get
+/// no response.
+async fn handle_line(app: &AppHandle, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, e.to_string(), None)),
+    };
+    let id = request.get("id").cloned();
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        let message = "Request has no method".to_string();
+        return Some(error_response(id.unwrap_or(Value::Null), INVALID_REQUEST, message, None));
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = dispatch(app, method, &params).await;
+    let id = id?;
+    Some(match result {
+        Ok(value) => json!({ "jsonrpc": "2.0", "id": id, "result": value }),
+        Err(RpcError::MethodNotFound(method)) => {
+            error_response(id, METHOD_NOT_FOUND, format!("Unknown method: {}", method), None)
+        }
+        Err(RpcError::InvalidParams(message)) => error_response(id, INVALID_PARAMS, message, None),
+        Err(RpcError::Command(err)) => {
+            let data = serde_json::to_value(&err).ok();
+            error_response(id, COMMAND_ERROR, err.message.clone(), data)
+        }
+    })
+}
+
+fn error_response(id: Value, code: i64, message: String, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+/// Reads a named parameter; a missing one reads as `null`, so optional
+/// parameters can be left out.
+fn arg<T: DeserializeOwned>(params: &Value, name: &str) -> Result<T, RpcError> {
+    let value = params.get(name).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| RpcError::InvalidParams(format!("{}: {}", name, e)))
+}
+
+fn reply<T: Serialize>(result: Result<T, AppError>) -> Result<Value, RpcError> {
+    let value = result.map_err(RpcError::Command)?;
+    serde_json::to_value(value)
+        .map_err(|e| RpcError::Command(AppError::new(ErrorCode::Internal, e.to_string())))
+}
+
+async fn dispatch(app: &AppHandle, method: &str, p: &Value) -> Result<Value, RpcError> {
+    match method {
+        "process_single_file" => reply(
+            commands::process_single_file(
+                app.clone(),
+                arg(p, "inputPath")?,
+                arg(p, "outputPath")?,
+                arg(p, "config")?,
+            )
+            .await,
+        ),
+        "process_batch" => reply(
+            commands::process_batch(
+                app.clone(),
+                app.state(),
+                arg(p, "files")?,
+                arg(p, "config")?,
+                arg(p, "outputDir")?,
+                arg(p, "textMappingPath")?,
+                arg(p, "summaryOnly")?,
+                arg(p, "presetId")?,
+                arg(p, "simulate")?,
+            )
+            .await,
+        ),
+        "preflight_batch" => reply(commands::preflight_batch(app.clone(), arg(p, "files")?).await),
+        "plan_batch" => reply(
+            commands::plan_batch(arg(p, "files")?, arg(p, "config")?, arg(p, "outputDir")?).await,
+        ),
+        "process_sample_batch" => reply(
+            commands::process_sample_batch(
+                app.clone(),
+                app.state(),
+                arg(p, "files")?,
+                arg(p, "config")?,
+                arg(p, "outputDir")?,
+                arg(p, "sampleSize")?,
+                arg(p, "textMappingPath")?,
+            )
+            .await,
+        ),
+        "continue_sample_batch" => reply(
+            commands::continue_sample_batch(app.clone(), app.state(), arg(p, "jobId")?).await,
+        ),
+        "discard_sample_batch" => {
+            reply(commands::discard_sample_batch(app.state(), arg(p, "jobId")?).await)
+        }
+        "get_batch_results" => reply(
+            commands::get_batch_results(
+                app.state(),
+                arg(p, "jobId")?,
+                arg(p, "filter")?,
+                arg(p, "offset")?,
+                arg(p, "limit")?,
+            )
+            .await,
+        ),
+        "get_recent_logs" => reply(commands::get_recent_logs(app.clone(), arg(p, "lines")?).await),
+        "export_logs" => reply(commands::export_logs(app.clone(), arg(p, "destination")?).await),
+        "get_file_log" => reply(commands::get_file_log(app.state(), arg(p, "inputPath")?).await),
+        "get_processing_history" => reply(
+            commands::get_processing_history(
+                app.clone(),
+                app.state(),
+                arg(p, "inputPath")?,
+                arg(p, "limit")?,
+            )
+            .await,
+        ),
+        "get_history" => reply(
+            commands::get_history(app.clone(), app.state(), arg(p, "limit")?, arg(p, "offset")?)
+                .await,
+        ),
+        "search_history" => reply(
+            commands::search_history(
+                app.clone(),
+                app.state(),
+                arg(p, "query")?,
+                arg(p, "from")?,
+                arg(p, "to")?,
+                arg(p, "limit")?,
+            )
+            .await,
+        ),
+        "clear_history" => {
+            reply(commands::clear_history(app.clone(), app.state(), arg(p, "before")?).await)
+        }
+        "get_hardware_encoders" => reply(commands::get_hardware_encoders(app.clone()).await),
+        "self_test" => reply(commands::self_test(app.clone()).await),
+        "download_ffmpeg" => reply(commands::download_ffmpeg(app.clone(), app.state()).await),
+        "get_ffmpeg_source" => reply(commands::get_ffmpeg_source(app.clone(), app.state()).await),
+        "set_ffmpeg_source" => reply(
+            commands::set_ffmpeg_source(app.clone(), app.state(), app.state(), arg(p, "source")?)
+                .await,
+        ),
+        "list_interrupted_batches" => reply(commands::list_interrupted_batches(app.clone()).await),
+        "resume_interrupted_batch" => {
+            reply(commands::resume_interrupted_batch(app.clone(), arg(p, "batchId")?).await)
+        }
+        "discard_interrupted_batch" => {
+            reply(commands::discard_interrupted_batch(app.clone(), arg(p, "batchId")?).await)
+        }
+        "approve_output_location" => reply(
+            commands::approve_output_location(app.clone(), app.state(), arg(p, "path")?).await,
+        ),
+        "process_clipboard_image" => reply(
+            commands::process_clipboard_image(app.clone(), arg(p, "config")?, arg(p, "outputDir")?)
+                .await,
+        ),
+        "extract_video_thumbnail" => reply(
+            commands::extract_video_thumbnail(
+                app.clone(),
+                arg(p, "videoPath")?,
+                arg(p, "timestamp")?,
+                arg(p, "timestampPercent")?,
+            )
+            .await,
+        ),
+        "extract_image_thumbnail" => {
+            reply(commands::extract_image_thumbnail(app.clone(), arg(p, "imagePath")?).await)
+        }
+        "prefetch_thumbnails" => {
+            reply(commands::prefetch_thumbnails(app.clone(), arg(p, "files")?).await)
+        }
+        "extract_scrub_strip" => reply(
+            commands::extract_scrub_strip(app.clone(), arg(p, "videoPath")?, arg(p, "frameCount")?)
+                .await,
+        ),
+        "extract_video_filmstrip" => reply(
+            commands::extract_video_filmstrip(
+                app.clone(),
+                arg(p, "videoPath")?,
+                arg(p, "frameCount")?,
+                arg(p, "tiled")?,
+            )
+            .await,
+        ),
+        "render_watermark_frames" => reply(
+            commands::render_watermark_frames(
+                app.clone(),
+                arg(p, "videoPath")?,
+                arg(p, "config")?,
+                arg(p, "frameCount")?,
+            )
+            .await,
+        ),
+        "render_comparison_preview" => reply(
+            commands::render_comparison_preview(
+                app.clone(),
+                arg(p, "inputPath")?,
+                arg(p, "config")?,
+                arg(p, "timestamp")?,
+                arg(p, "sideBySide")?,
+            )
+            .await,
+        ),
+        "render_video_preview" => reply(
+            commands::render_video_preview(
+                app.clone(),
+                arg(p, "videoPath")?,
+                arg(p, "config")?,
+                arg(p, "durationSecs")?,
+                arg(p, "maxHeight")?,
+            )
+            .await,
+        ),
+        "nudge_watermark_position" => reply(
+            commands::nudge_watermark_position(app.clone(), arg(p, "inputPath")?, arg(p, "config")?)
+                .await,
+        ),
+        "preview_ffmpeg_command" => reply(
+            commands::preview_ffmpeg_command(
+                app.clone(),
+                arg(p, "inputPath")?,
+                arg(p, "outputPath")?,
+                arg(p, "config")?,
+            )
+            .await,
+        ),
+        "get_media_info" => reply(commands::get_media_info(app.clone(), arg(p, "path")?).await),
+        "cleanup_thumbnail_cache" => {
+            reply(commands::cleanup_thumbnail_cache(app.clone(), arg(p, "maxAgeDays")?).await)
+        }
+        "clear_thumbnail_cache" => {
+            reply(commands::clear_thumbnail_cache(app.clone(), app.state()).await)
+        }
+        "get_cache_settings" => reply(commands::get_cache_settings(app.clone()).await),
+        "set_cache_settings" => reply(
+            commands::set_cache_settings(app.clone(), app.state(), arg(p, "settings")?).await,
+        ),
+        "scan_folder" => {
+            reply(commands::scan_folder(arg(p, "path")?, arg(p, "recursive")?).await)
+        }
+        "open_folder_in_explorer" => {
+            reply(commands::open_folder_in_explorer(arg(p, "path")?).await)
+        }
+        "take_opened_files" => reply(commands::take_opened_files(app.state()).await),
+        "list_post_processors" => reply(commands::list_post_processors(app.state()).await),
+        "list_watermark_sources" => reply(commands::list_watermark_sources(app.state()).await),
+        "list_presets" => reply(
+            commands::list_presets(app.clone(), app.state(), arg(p, "category")?, arg(p, "tags")?)
+                .await,
+        ),
+        "load_preset" => reply(
+            commands::load_preset(app.clone(), app.state(), arg(p, "presetId")?).await,
+        ),
+        "set_default_preset" => reply(
+            commands::set_default_preset(app.clone(), app.state(), arg(p, "presetId")?).await,
+        ),
+        "save_preset" => reply(
+            commands::save_preset(
+                app.clone(),
+                app.state(),
+                arg(p, "presetId")?,
+                arg(p, "name")?,
+                arg(p, "description")?,
+                arg(p, "base")?,
+                arg(p, "config")?,
+                arg(p, "output")?,
+                arg(p, "category")?,
+                arg(p, "tags")?,
+            )
+            .await,
+        ),
+        "delete_preset" => reply(
+            commands::delete_preset(app.clone(), app.state(), arg(p, "presetId")?).await,
+        ),
+        "export_preset" => reply(
+            commands::export_preset(
+                app.clone(),
+                app.state(),
+                arg(p, "presetId")?,
+                arg(p, "outputPath")?,
+                arg(p, "embedAssets")?,
+            )
+            .await,
+        ),
+        "import_preset" => {
+            reply(commands::import_preset(app.clone(), app.state(), arg(p, "path")?).await)
+        }
+        "rename_preset" => reply(
+            commands::rename_preset(app.clone(), app.state(), arg(p, "presetId")?, arg(p, "name")?)
+                .await,
+        ),
+        "load_last_config" => reply(commands::load_last_config(app.clone()).await),
+        "save_last_config" => reply(
+            commands::save_last_config(app.clone(), arg(p, "config")?, arg(p, "presetId")?).await,
+        ),
+        "get_telemetry_enabled" => {
+            reply(commands::get_telemetry_enabled(app.clone(), app.state()).await)
+        }
+        "set_telemetry_enabled" => reply(
+            commands::set_telemetry_enabled(app.clone(), app.state(), arg(p, "enabled")?).await,
+        ),
+        "export_telemetry" => reply(
+            commands::export_telemetry(app.clone(), app.state(), arg(p, "outputPath")?).await,
+        ),
+        "get_update_channel" => {
+            reply(commands::get_update_channel(app.clone(), app.state()).await)
+        }
+        "set_update_channel" => reply(
+            commands::set_update_channel(app.clone(), app.state(), arg(p, "channel")?).await,
+        ),
+        "check_for_updates_now" => reply(commands::check_for_updates_now(app.clone()).await),
+        "install_update" => reply(commands::install_update(app.clone()).await),
+        "get_screenshot_folder" => reply(commands::get_screenshot_folder(app.clone()).await),
+        "start_hot_folder" => reply(
+            commands::start_hot_folder(
+                app.clone(),
+                app.state(),
+                app.state(),
+                arg(p, "outputDir")?,
+                arg(p, "watchDir")?,
+                arg(p, "config")?,
+            )
+            .await,
+        ),
+        "stop_hot_folder" => reply(commands::stop_hot_folder(app.state()).await),
+        "get_hot_folder_status" => reply(commands::get_hot_folder_status(app.state()).await),
+        _ => Err(RpcError::MethodNotFound(method.to_string())),
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::path::Path;
+
+use tracing::warn;
+
+use crate::audio::is_audio_file;
+use crate::ffmpeg::detect_file_type;
+use crate::types::{ExcludedFile, ExclusionReason, FileItem, FolderScan};
+
+/// Collects the media files in `dir` (and its subfolders when `recursive`),
+/// reporting every other file with the reason it was left out.
+pub fn scan_folder(dir: &Path, recursive: bool) -> Result<FolderScan, String> {
+    let mut scan = FolderScan::default();
+    scan_dir(dir, recursive, &mut scan)
+        .map_err(|e| format!("Failed to read folder {}: {}", dir.display(), e))?;
+    scan.files.sort_by(|a, b| a.path.cmp(&b.path));
+    scan.excluded.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(scan)
+}
+
+fn scan_dir(dir: &Path, recursive: bool, scan: &mut FolderScan) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!(error = %e, "Failed to read directory entry");
+                continue;
+            }
+        };
+        let path = entry.path();
+
+        // Hidden files are OS or editor metadata (.DS_Store, ._foo.jpg)
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                scan.excluded.push(ExcludedFile {
+                    path,
+                    reason: ExclusionReason::Unreadable,
+                    detail: Some(e.to_string()),
+                });
+                continue;
+            }
+        };
+
+        if metadata.is_dir() {
+            if recursive {
+                if let Err(e) = scan_dir(&path, recursive, scan) {
+                    scan.excluded.push(ExcludedFile {
+                        path,
+                        reason: ExclusionReason::Unreadable,
+                        detail: Some(e.to_string()),
+                    });
+                }
+            }
+            continue;
+        }
+
+        let file_type = if is_audio_file(&path) {
+            "audio"
+        } else {
+            match detect_file_type(&path) {
+                Ok(true) => "video",
+                Ok(false) => "image",
+                Err(e) => {
+                    scan.excluded.push(ExcludedFile {
+                        path,
+                        reason: ExclusionReason::UnsupportedExtension,
+                        detail: Some(e.to_string()),
+                    });
+                    continue;
+                }
+            }
+        };
+
+        if metadata.len() == 0 {
+            scan.excluded.push(ExcludedFile {
+                path,
+                reason: ExclusionReason::ZeroBytes,
+                detail: None,
+            });
+            continue;
+        }
+
+        if let Err(e) = File::open(&path) {
+            scan.excluded.push(ExcludedFile {
+                path,
+                reason: ExclusionReason::Unreadable,
+                detail: Some(e.to_string()),
+            });
+            continue;
+        }
+
+        scan.files.push(FileItem {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            path,
+            r#type: file_type.to_string(),
+            size: Some(metadata.len()),
+        });
+    }
+    Ok(())
+}
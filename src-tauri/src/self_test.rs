@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use tauri::{AppHandle, Manager};
+
+use crate::ffmpeg::{build_ffmpeg_command, get_ffmpeg_sidecar_path, spawn_ffmpeg, FfmpegError};
+use crate::types::{SelfTestReport, WatermarkConfig};
+
+const SAMPLE_IMAGE: &str = "resources/self-test/sample.png";
+
+/// Checks that FFmpeg runs, has the drawtext filter and can watermark the
+/// bundled sample image with the default text watermark. Later checks are
+/// skipped once one fails, and every failure is described in `problems`.
+pub async fn run(app: &AppHandle) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+
+    match get_ffmpeg_sidecar_path(app) {
+        Ok(path) => report.ffmpeg_path = Some(path),
+        Err(e) => {
+            report.problems.push(e.to_string());
+            return report;
+        }
+    }
+    match spawn_ffmpeg(app, vec!["-hide_banner".into(), "-version".into()]).await {
+        Ok(output) => {
+            report.binary_found = true;
+            report.ffmpeg_version = output
+                .lines()
+                .find_map(|line| line.strip_prefix("ffmpeg version "))
+                .and_then(|rest| rest.split_whitespace().next())
+                .map(str::to_string);
+        }
+        Err(e) => {
+            report.problems.push(format!("FFmpeg does not run: {}", e));
+            return report;
+        }
+    }
+
+    match spawn_ffmpeg(app, vec!["-hide_banner".into(), "-filters".into()]).await {
+        Ok(output) => {
+            report.drawtext_available = output
+                .lines()
+                .any(|line| line.split_whitespace().nth(1) == Some("drawtext"));
+            if !report.drawtext_available {
+                report.problems.push(
+                    "FFmpeg was built without the drawtext filter, so text watermarks fail".into(),
+                );
+                return report;
+            }
+        }
+        Err(e) => {
+            report.problems.push(format!("Failed to list FFmpeg filters: {}", e));
+            return report;
+        }
+    }
+
+    match render_sample(app).await {
+        Ok(()) => {
+            report.fonts_ok = true;
+            report.sample_rendered = true;
+        }
+        Err(e) => {
+            let message = e.to_string();
+            // drawtext reports missing fonts and a broken fontconfig this way
+            report.fonts_ok = !message.to_lowercase().contains("font");
+            report.problems.push(format!("Failed to watermark the sample image: {}", message));
+        }
+    }
+    report
+}
+
+async fn render_sample(app: &AppHandle) -> Result<(), FfmpegError> {
+    let sample = app
+        .path()
+        .resolve(SAMPLE_IMAGE, tauri::path::BaseDirectory::Resource)
+        .map_err(|e| FfmpegError::Path(format!("failed to resolve the sample image: {}", e)))?;
+    let output = sample_output_path();
+    let config = WatermarkConfig {
+        text: "Self-test".to_string(),
+        ..WatermarkConfig::default()
+    };
+
+    let args = build_ffmpeg_command(app, &sample, &output, &config, false)?;
+    let result = spawn_ffmpeg(app, args).await;
+    let rendered = output.exists();
+    let _ = std::fs::remove_file(&output);
+    result?;
+    if !rendered {
+        return Err(FfmpegError::Execution("FFmpeg did not write the sample output".into()));
+    }
+    Ok(())
+}
+
+fn sample_output_path() -> PathBuf {
+    std::env::temp_dir().join(format!("bulk-watermark-self-test-{}.png", std::process::id()))
+}
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::types::{OutputSidecar, WatermarkConfig};
+
+/// Writes `<output>.json` describing where the output came from and how it
+/// was produced, so downstream tools can trace provenance.
+pub fn write_output_sidecar(
+    input_path: &Path,
+    output_path: &Path,
+    config: &WatermarkConfig,
+) -> std::io::Result<PathBuf> {
+    let source_modified_at = std::fs::metadata(input_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339());
+
+    let sidecar = OutputSidecar {
+        source_path: input_path.to_path_buf(),
+        output_path: output_path.to_path_buf(),
+        source_sha256: sha256_file(input_path)?,
+        output_sha256: sha256_file(output_path)?,
+        config_hash: config_hash(config)?,
+        source_modified_at,
+        processed_at: Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+
+    let mut sidecar_name = output_path.as_os_str().to_os_string();
+    sidecar_name.push(".json");
+    let sidecar_path = PathBuf::from(sidecar_name);
+
+    let content = serde_json::to_string_pretty(&sidecar)
+        .map_err(std::io::Error::other)?;
+    std::fs::write(&sidecar_path, content)?;
+    Ok(sidecar_path)
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn config_hash(config: &WatermarkConfig) -> std::io::Result<String> {
+    let serialized = serde_json::to_vec(config)
+        .map_err(std::io::Error::other)?;
+    Ok(format!("{:x}", Sha256::digest(&serialized)))
+}
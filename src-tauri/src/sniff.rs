@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes read from the start of a file to recognize its container.
+const HEADER_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+}
+
+/// Container recognized from a file's magic bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Sniffed {
+    pub kind: MediaKind,
+    /// Extensions used for this container, the preferred one first
+    pub extensions: &'static [&'static str],
+}
+
+impl Sniffed {
+    const fn new(kind: MediaKind, extensions: &'static [&'static str]) -> Self {
+        Self { kind, extensions }
+    }
+
+    /// Whether the extension of `path` names this container.
+    pub fn matches_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.contains(&ext.to_ascii_lowercase().as_str()))
+    }
+}
+
+/// Recognizes the container of `path` from its first bytes. Returns `None`
+/// when the file cannot be read or its header is not one of the supported
+/// formats, in which case callers fall back to the extension.
+pub fn sniff(path: &Path) -> Option<Sniffed> {
+    let mut header = [0u8; HEADER_LEN];
+    let mut file = File::open(path).ok()?;
+    let mut len = 0;
+    // A single read may return fewer bytes than are available
+    while len < HEADER_LEN {
+        match file.read(&mut header[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(_) => return None,
+        }
+    }
+    sniff_header(&header[..len])
+}
+
+fn sniff_header(header: &[u8]) -> Option<Sniffed> {
+    use MediaKind::{Audio, Image, Video};
+
+    let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, b"\x89PNG\r\n\x1a\n") {
+        Some(Sniffed::new(Image, &["png"]))
+    } else if at(0, b"\xff\xd8\xff") {
+        Some(Sniffed::new(Image, &["jpg", "jpeg"]))
+    } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        Some(Sniffed::new(Image, &["gif"]))
+    } else if at(0, b"BM") && at(6, &[0, 0, 0, 0]) {
+        Some(Sniffed::new(Image, &["bmp"]))
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        Some(Sniffed::new(Image, &["webp"]))
+    } else if at(0, b"RIFF") && at(8, b"AVI ") {
+        Some(Sniffed::new(Video, &["avi"]))
+    } else if at(0, b"RIFF") && at(8, b"WAVE") {
+        Some(Sniffed::new(Audio, &["wav"]))
+    } else if at(4, b"ftyp") {
+        // The major brand tells audio-only MPEG-4 files apart from videos
+        if at(8, b"M4A ") || at(8, b"M4B ") {
+            Some(Sniffed::new(Audio, &["m4a"]))
+        } else if at(8, b"qt  ") {
+            Some(Sniffed::new(Video, &["mov", "mp4"]))
+        } else {
+            Some(Sniffed::new(Video, &["mp4", "mov"]))
+        }
+    } else if at(4, b"moov") || at(4, b"mdat") || at(4, b"wide") {
+        // QuickTime files written without an ftyp atom
+        Some(Sniffed::new(Video, &["mov", "mp4"]))
+    } else if at(0, b"\x1a\x45\xdf\xa3") {
+        Some(Sniffed::new(Video, &["mkv", "webm"]))
+    } else if at(0, b"FLV") {
+        Some(Sniffed::new(Video, &["flv"]))
+    } else if at(0, b"ID3") || is_mpeg_audio_frame(header) {
+        Some(Sniffed::new(Audio, &["mp3"]))
+    } else {
+        None
+    }
+}
+
+/// MP3 files without an ID3 tag start directly with an 11-bit frame sync.
+fn is_mpeg_audio_frame(header: &[u8]) -> bool {
+    matches!(header, [0xff, second, ..] if second & 0xe0 == 0xe0)
+}
+
+/// Whether the PNG or WebP image at `path` carries transparency: an alpha
+/// channel, or a PNG `tRNS` chunk. Other formats and unreadable files count
+/// as opaque.
+pub fn has_alpha(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut header = [0u8; 30];
+    if file.read_exact(&mut header).is_err() {
+        return false;
+    }
+    if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_has_alpha(&mut file, &header)
+    } else if header.starts_with(b"RIFF") && &header[8..12] == b"WEBP" {
+        match &header[12..16] {
+            // Extended format: the alpha flag of the VP8X header
+            b"VP8X" => header[20] & 0x10 != 0,
+            // Lossless: the alpha hint after the 14-bit width and height
+            b"VP8L" => header[24] & 0x10 != 0,
+            _ => false,
+        }
+    } else {
+        false
+    }
+}
+
+/// Whether the GIF or WebP image at `path` has more than one frame.
+pub fn is_animated(path: &Path) -> bool {
+    let Ok(bytes) = std::fs::read(path) else {
+        return false;
+    };
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        gif_frame_count(&bytes).is_some_and(|count| count > 1)
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..16) == Some(b"WEBPVP8X") {
+        // The animation flag of the VP8X header
+        bytes.get(20).is_some_and(|flags| flags & 0x02 != 0)
+    } else {
+        false
+    }
+}
+
+/// Counts image descriptors, stopping at the second. `None` when the block
+/// structure is truncated before any frame.
+fn gif_frame_count(bytes: &[u8]) -> Option<usize> {
+    let color_table_len = |flags: u8| {
+        if flags & 0x80 != 0 {
+            3 << ((flags & 0x07) + 1)
+        } else {
+            0
+        }
+    };
+    // Header and logical screen descriptor, then the global color table
+    let mut pos = 13 + color_table_len(*bytes.get(10)?);
+    let mut frames = 0;
+    while frames < 2 {
+        match bytes.get(pos) {
+            Some(0x2C) => {
+                frames += 1;
+                // Image descriptor, local color table and LZW code size
+                pos += 10 + color_table_len(*bytes.get(pos + 9)?) + 1;
+            }
+            // Extension: introducer and label
+            Some(0x21) => pos += 2,
+            _ => break,
+        }
+        // Data sub-blocks, each led by its length, up to a zero-length one
+        loop {
+            let Some(&length) = bytes.get(pos) else {
+                return (frames > 0).then_some(frames);
+            };
+            pos += 1 + length as usize;
+            if length == 0 {
+                break;
+            }
+        }
+    }
+    Some(frames)
+}
+
+/// Reads the IHDR color type, then walks the chunks up to the image data
+/// looking for `tRNS`, which gives palette and RGB images transparency.
+fn png_has_alpha(file: &mut File, header: &[u8]) -> bool {
+    // Grayscale with alpha, RGBA
+    if matches!(header[25], 4 | 6) {
+        return true;
+    }
+    // Signature, then the 25-byte IHDR chunk
+    if file.seek(SeekFrom::Start(33)).is_err() {
+        return false;
+    }
+    let mut chunk = [0u8; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        match &chunk[4..8] {
+            b"tRNS" => return true,
+            b"IDAT" | b"IEND" => return false,
+            _ => {}
+        }
+        // Skip the data and its CRC
+        let length = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        if file.seek(SeekFrom::Current(i64::from(length) + 4)).is_err() {
+            return false;
+        }
+    }
+    false
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use crate::types::{WatermarkConfig, WatermarkSourceInfo};
+
+/// What a watermark source knows about the file it generates a watermark for.
+#[derive(Debug, Clone)]
+pub struct SourceContext {
+    pub input_path: PathBuf,
+    /// Position of the file in its batch, when it is part of one
+    pub sequence: Option<u64>,
+    pub config: WatermarkConfig,
+}
+
+/// A per-file watermark produced by a source.
+#[derive(Debug, Clone)]
+pub enum GeneratedWatermark {
+    /// Image drawn as an image watermark, e.g. a rendered barcode. The source
+    /// owns the file and must keep it until the batch has finished.
+    Image(PathBuf),
+    /// Text drawn as a text watermark; `{token}` placeholders are expanded
+    Text(String),
+}
+
+/// Generates the watermark for each file, e.g. a barcode, a price label or a
+/// sponsor logo fetched from an API. Sources run on a blocking thread, so they
+/// may do synchronous IO or network requests.
+pub trait WatermarkSource: Send + Sync {
+    fn id(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    fn generate(&self, context: &SourceContext) -> Result<GeneratedWatermark, String>;
+}
+
+/// Registered watermark sources keyed by ID, managed as Tauri state. Sources
+/// can be registered at any time; a config picks one through
+/// `WatermarkConfig::watermark_source`.
+#[derive(Default)]
+pub struct WatermarkSourceRegistry {
+    sources: RwLock<HashMap<String, Arc<dyn WatermarkSource>>>,
+}
+
+impl WatermarkSourceRegistry {
+    pub fn register(&self, source: Arc<dyn WatermarkSource>) {
+        self.sources
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(source.id().to_string(), source);
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<dyn WatermarkSource>> {
+        self.sources
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(id)
+            .cloned()
+    }
+
+    pub fn list(&self) -> Vec<WatermarkSourceInfo> {
+        let mut infos: Vec<_> = self
+            .sources
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .map(|source| WatermarkSourceInfo {
+                id: source.id().to_string(),
+                description: source.description().to_string(),
+            })
+            .collect();
+        infos.sort_by(|a, b| a.id.cmp(&b.id));
+        infos
+    }
+}
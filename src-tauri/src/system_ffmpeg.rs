@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::ffmpeg::FfmpegError;
+use crate::last_config::SETTINGS_STORE;
+use crate::types::FfmpegSource;
+
+const FFMPEG_SOURCE_KEY: &str = "ffmpegSource";
+
+/// Oldest FFmpeg release whose filters and options the generated commands use.
+const MIN_VERSION: (u32, u32) = (5, 0);
+
+/// A usable FFmpeg binary found on `PATH`.
+#[derive(Debug, Clone)]
+pub struct SystemBinary {
+    pub path: PathBuf,
+    pub version: String,
+}
+
+/// System binaries looked up so far, by name, managed as Tauri state. Lookups
+/// run `-version`, so each name is looked up once until the source changes.
+#[derive(Debug, Default)]
+pub struct SystemFfmpeg {
+    found: Mutex<HashMap<String, Result<SystemBinary, String>>>,
+}
+
+impl SystemFfmpeg {
+    /// Forgets earlier lookups, so binaries installed since are found.
+    pub fn reset(&self) {
+        self.found.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+    }
+}
+
+/// Reads the FFmpeg source chosen with `save_source`, `Auto` by default.
+pub fn load_source(app: &AppHandle) -> FfmpegSource {
+    app.store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(FFMPEG_SOURCE_KEY))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_source(app: &AppHandle, source: FfmpegSource) -> Result<(), String> {
+    let store = app
+        .store(SETTINGS_STORE)
+        .map_err(|e| format!("Failed to open settings store: {}", e))?;
+    let value = serde_json::to_value(source)
+        .map_err(|e| format!("Failed to serialize FFmpeg source: {}", e))?;
+    store.set(FFMPEG_SOURCE_KEY, value);
+    Ok(())
+}
+
+/// Finds `name` ("ffmpeg" or "ffprobe") on `PATH`, accepting it only when it
+/// reports at least `MIN_VERSION`.
+pub fn find(app: &AppHandle, name: &str) -> Result<SystemBinary, FfmpegError> {
+    let state = app.state::<SystemFfmpeg>();
+    let mut found = state.found.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    found
+        .entry(name.to_string())
+        .or_insert_with(|| look_up(name))
+        .clone()
+        .map_err(FfmpegError::MissingBinary)
+}
+
+fn look_up(name: &str) -> Result<SystemBinary, String> {
+    let file_name = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    let path = std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+        .ok_or_else(|| format!("no system {} found on PATH", name))?;
+
+    let output = std::process::Command::new(&path)
+        .arg("-version")
+        .output()
+        .map_err(|e| format!("failed to run {}: {}", path.display(), e))?;
+    let banner = String::from_utf8_lossy(&output.stdout);
+    let version = banner
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(2))
+        .ok_or_else(|| format!("{} did not report a version", path.display()))?
+        .to_string();
+
+    if !meets_minimum(&version) {
+        return Err(format!(
+            "system {} {} at {} is older than {}.{}",
+            name,
+            version,
+            path.display(),
+            MIN_VERSION.0,
+            MIN_VERSION.1
+        ));
+    }
+    Ok(SystemBinary { path, version })
+}
+
+/// Checks a version such as `6.1.1-3ubuntu5` or `n7.0`. Builds from the
+/// development branch (`N-112345-g...`) are newer than any release.
+fn meets_minimum(version: &str) -> bool {
+    if version.starts_with("N-") {
+        return true;
+    }
+    let mut parts = version
+        .trim_start_matches('n')
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|part| part.parse::<u32>().ok());
+    match (parts.next().flatten(), parts.next().flatten()) {
+        (Some(major), minor) => (major, minor.unwrap_or(0)) >= MIN_VERSION,
+        (None, _) => false,
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+use crate::types::{
+    AudioHandling, HardwareAcceleration, HdrMode, MotionMode, ScheduleMode, SizeGrowthPolicy,
+    WatermarkConfig, WatermarkType,
+};
+
+const TELEMETRY_FILE: &str = "telemetry.json";
+
+/// Locally stored usage counters. Only feature names and failure categories
+/// are recorded: no paths, file names, watermark text or identifiers.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct TelemetryData {
+    pub enabled: bool,
+    /// Day recording started, `YYYY-MM-DD`
+    pub since: Option<String>,
+    pub features: BTreeMap<String, u64>,
+    pub failures: BTreeMap<String, u64>,
+}
+
+/// Opt-in telemetry, managed as Tauri state. Nothing is recorded or written
+/// until the user enables it, and disabling it discards what was collected.
+#[derive(Default)]
+pub struct Telemetry {
+    data: Mutex<Option<TelemetryData>>,
+}
+
+impl Telemetry {
+    pub fn is_enabled(&self, app: &AppHandle) -> bool {
+        self.with_data(app, |data| data.enabled)
+    }
+
+    pub fn set_enabled(&self, app: &AppHandle, enabled: bool) -> Result<(), String> {
+        let mut guard = self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let data = guard.get_or_insert_with(|| load(app));
+        *data = TelemetryData {
+            enabled,
+            since: enabled.then(|| Utc::now().format("%Y-%m-%d").to_string()),
+            ..TelemetryData::default()
+        };
+        save(app, data)
+    }
+
+    /// Counts the features a watermark run uses, keyed by the entry point.
+    pub fn record_usage(&self, app: &AppHandle, entry_point: &str, config: &WatermarkConfig) {
+        self.record(app, |data| {
+            for feature in config_features(entry_point, config) {
+                *data.features.entry(feature).or_insert(0) += 1;
+            }
+        });
+    }
+
+    pub fn record_failure(&self, app: &AppHandle, category: &str) {
+        self.record(app, |data| {
+            *data.failures.entry(category.to_string()).or_insert(0) += 1;
+        });
+    }
+
+    /// Writes the collected counters as JSON to `path`.
+    pub fn export(&self, app: &AppHandle, path: &Path) -> Result<(), String> {
+        let data = self.with_data(app, |data| data.clone());
+        if !data.enabled {
+            return Err("Telemetry is disabled".to_string());
+        }
+        let content = serde_json::to_string_pretty(&data)
+            .map_err(|e| format!("Failed to serialize telemetry: {}", e))?;
+        std::fs::write(path, content).map_err(|e| format!("Failed to export telemetry: {}", e))
+    }
+
+    fn record(&self, app: &AppHandle, update: impl FnOnce(&mut TelemetryData)) {
+        let mut guard = self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let data = guard.get_or_insert_with(|| load(app));
+        if !data.enabled {
+            return;
+        }
+        update(data);
+        if let Err(e) = save(app, data) {
+            warn!(error = %e, "Failed to save telemetry");
+        }
+    }
+
+    fn with_data<T>(&self, app: &AppHandle, read: impl FnOnce(&TelemetryData) -> T) -> T {
+        let mut guard = self.data.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        read(guard.get_or_insert_with(|| load(app)))
+    }
+}
+
+/// Records feature usage through the managed `Telemetry` state.
+pub fn record_usage(app: &AppHandle, entry_point: &str, config: &WatermarkConfig) {
+    app.state::<Telemetry>().record_usage(app, entry_point, config);
+}
+
+/// Records a failure category through the managed `Telemetry` state.
+pub fn record_failure(app: &AppHandle, category: &str) {
+    app.state::<Telemetry>().record_failure(app, category);
+}
+
+fn config_features(entry_point: &str, config: &WatermarkConfig) -> Vec<String> {
+    let mut features = vec![entry_point.to_string()];
+    features.push(match config.watermark_type {
+        WatermarkType::Text => "watermark_type:text".to_string(),
+        WatermarkType::Image => "watermark_type:image".to_string(),
+        WatermarkType::Video => "watermark_type:video".to_string(),
+    });
+    if let Some(blend) = config.blend_mode.ffmpeg_name() {
+        features.push(format!("blend_mode:{}", blend));
+    }
+
+    let flags = [
+        ("watermark_source", config.watermark_source.is_some()),
+        ("auto_contrast", config.auto_contrast),
+        ("adaptive_opacity", config.adaptive_opacity.is_some()),
+        ("color_key", config.color_key.is_some()),
+        ("custom_position", config.is_custom_position()),
+        ("jpeg_transform", config.jpeg_transform.is_some()),
+        ("resize", config.resize.is_some()),
+        ("export_variants", !config.export_variants.is_empty()),
+        ("output_name_template", config.output_name_template.is_some()),
+        ("text_rules", !config.text_rules.is_empty()),
+        ("io_limit", config.io_limit_kb_per_sec.is_some()),
+        ("cpu_affinity", config.cpu_affinity.is_some()),
+        ("encoding", config.encoding.is_some()),
+        ("preserve_metadata", config.preserve_metadata),
+        ("strip_metadata", config.strip_metadata),
+        ("preserve_streams", config.preserve_streams),
+        ("size_growth_check", config.size_growth != SizeGrowthPolicy::Accept),
+        ("image_format_conversion", config.image_output.format.is_some()),
+        ("hardware_acceleration", config.hardware_acceleration != HardwareAcceleration::Off),
+        ("hdr_tone_map", config.hdr_mode == HdrMode::ToneMap),
+        ("web_optimized", config.web_optimized),
+        ("audio_handling", config.audio_handling != AudioHandling::Auto),
+        ("time_window", config.has_time_window()),
+        ("schedule", config.schedule != ScheduleMode::Always),
+        ("fade", config.has_fade()),
+        ("motion", config.motion != MotionMode::Static),
+        ("jitter", config.jitter_interval_secs.is_some()),
+        ("chunked_encoding", config.chunked_encoding.is_some()),
+        ("archive_copy", config.archive_copy.is_some()),
+        ("sidecar", config.write_sidecar),
+        ("post_processors", !config.post_processors.is_empty()),
+    ];
+    features.extend(
+        flags
+            .into_iter()
+            .filter(|(_, used)| *used)
+            .map(|(name, _)| name.to_string()),
+    );
+    features
+}
+
+fn telemetry_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(TELEMETRY_FILE))
+}
+
+fn load(app: &AppHandle) -> TelemetryData {
+    telemetry_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, data: &TelemetryData) -> Result<(), String> {
+    let path = telemetry_path(app)
+        .ok_or_else(|| "Failed to resolve the telemetry file".to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("Failed to serialize telemetry: {}", e))?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write telemetry: {}", e))
+}
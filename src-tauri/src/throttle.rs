@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const CHUNK_SIZE: usize = 256 * 1024;
+
+static STAGING_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Creates a fresh directory under the temp dir for staging one file's
+/// input and output. The caller removes it when done.
+pub fn create_staging_dir() -> std::io::Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let sequence = STAGING_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+
+    let dir = std::env::temp_dir()
+        .join("bulk-watermark-staging")
+        .join(format!("{}-{}", millis, sequence));
+    std::fs::create_dir_all(dir.join("in"))?;
+    std::fs::create_dir_all(dir.join("out"))?;
+    Ok(dir)
+}
+
+/// Copies `src` to `dst` in chunks, sleeping between chunks so the average
+/// transfer rate stays at or below `bytes_per_sec`.
+pub async fn throttled_copy(src: &Path, dst: &Path, bytes_per_sec: u64) -> std::io::Result<u64> {
+    let mut reader = tokio::fs::File::open(src).await?;
+    let mut writer = tokio::fs::File::create(dst).await?;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let started = Instant::now();
+    let mut copied = 0u64;
+
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read]).await?;
+        copied += read as u64;
+
+        let expected = Duration::from_secs_f64(copied as f64 / bytes_per_sec as f64);
+        let elapsed = started.elapsed();
+        if expected > elapsed {
+            tokio::time::sleep(expected - elapsed).await;
+        }
+    }
+
+    writer.flush().await?;
+    Ok(copied)
+}
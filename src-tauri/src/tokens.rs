@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Locale};
+
+/// Widest `{counter:N}` padding honored, so a typo cannot produce huge names.
+const MAX_COUNTER_PADDING: usize = 20;
+
+/// Per-file values available to `{token}` placeholders in watermark text.
+pub struct TokenContext<'a> {
+    pub input_path: &'a Path,
+    pub timestamp: DateTime<Local>,
+    pub counter: u64,
+    pub counter_padding: usize,
+    /// Language of month and weekday names in date tokens
+    pub locale: Locale,
+}
+
+impl<'a> TokenContext<'a> {
+    /// Builds the context for a file. Dates come from the file's modification
+    /// time (which cameras set at capture), falling back to the current time.
+    pub fn for_file(input_path: &'a Path) -> Self {
+        let timestamp = std::fs::metadata(input_path)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Local>::from)
+            .unwrap_or_else(|_| Local::now());
+
+        Self {
+            input_path,
+            timestamp,
+            counter: 1,
+            counter_padding: 0,
+            locale: Locale::POSIX,
+        }
+    }
+
+    /// Sets the value of the `{counter}` token, zero-padded to `padding` digits.
+    pub fn with_counter(mut self, counter: u64, padding: usize) -> Self {
+        self.counter = counter;
+        self.counter_padding = padding;
+        self
+    }
+
+    /// Sets the locale date tokens are formatted in, such as `de_DE`. Unknown
+    /// locales keep the default.
+    pub fn with_locale(mut self, locale: Option<&str>) -> Self {
+        if let Some(locale) = locale.and_then(|name| parse_locale(name).ok()) {
+            self.locale = locale;
+        }
+        self
+    }
+}
+
+/// Parses a locale name, accepting `de-DE` as well as `de_DE`.
+pub fn parse_locale(name: &str) -> Result<Locale, String> {
+    Locale::try_from(name.replace('-', "_").as_str())
+        .map_err(|_| format!("Unknown locale: {}", name))
+}
+
+/// Replaces known `{token}` placeholders in `template`. Tokens take an optional
+/// format after a colon: a strftime pattern for dates (`{date:%d %B %Y}`) or a
+/// digit count for the counter (`{counter:05}`). Unknown tokens, invalid
+/// formats and unmatched braces are left untouched so literal text survives.
+pub fn expand_tokens(template: &str, context: &TokenContext) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let Some(end) = after.find('}') else {
+            output.push_str(&rest[start..]);
+            return output;
+        };
+
+        let token = &after[..end];
+        match resolve_token(token, context) {
+            Some(value) => output.push_str(&value),
+            None => {
+                output.push('{');
+                output.push_str(token);
+                output.push('}');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn resolve_token(token: &str, context: &TokenContext) -> Option<String> {
+    let (name, spec) = match token.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (token, None),
+    };
+    match name {
+        "date" => format_date(context, spec.unwrap_or("%Y-%m-%d")),
+        "time" => format_date(context, spec.unwrap_or("%H:%M")),
+        "year" => format_date(context, spec.unwrap_or("%Y")),
+        "counter" => {
+            let width = match spec {
+                Some(digits) => digits.parse::<usize>().ok()?.min(MAX_COUNTER_PADDING),
+                None => context.counter_padding,
+            };
+            Some(format!("{:0width$}", context.counter, width = width))
+        }
+        "name" if spec.is_none() => context
+            .input_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned()),
+        "filename" if spec.is_none() => context
+            .input_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned()),
+        _ => None,
+    }
+}
+
+/// Formats the context's timestamp, or `None` when `format` is not a valid
+/// strftime pattern (chrono would panic while writing it).
+fn format_date(context: &TokenContext, format: &str) -> Option<String> {
+    let items = StrftimeItems::new_with_locale(format, context.locale);
+    if items.clone().any(|item| matches!(item, Item::Error)) {
+        return None;
+    }
+    Some(context.timestamp.format_localized_with_items(items, context.locale).to_string())
+}
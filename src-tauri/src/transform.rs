@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::warn;
+
+use crate::sniff::sniff;
+use crate::types::JpegTransform;
+
+// Worst-case JPEG MCU size; crop offsets on this grid are always lossless
+const MCU_SIZE: u32 = 16;
+
+static TRANSFORM_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Whether `path` holds a JPEG, going by its magic bytes rather than the
+/// extension.
+pub fn is_jpeg(path: &Path) -> bool {
+    sniff(path).is_some_and(|sniffed| sniffed.extensions.contains(&"jpg"))
+}
+
+/// Rotates/crops a JPEG losslessly with jpegtran, keeping all markers (EXIF,
+/// ICC). Returns `None` when jpegtran is unavailable or the transform cannot be
+/// done losslessly, in which case the caller falls back to FFmpeg filters.
+pub async fn apply_lossless_transform(
+    input_path: &Path,
+    transform: &JpegTransform,
+) -> Option<PathBuf> {
+    if let Some(crop) = &transform.crop {
+        if crop.x % MCU_SIZE != 0 || crop.y % MCU_SIZE != 0 {
+            warn!(
+                path = %input_path.display(),
+                x = crop.x,
+                y = crop.y,
+                "Crop offset is not on the JPEG block grid, falling back to FFmpeg filters"
+            );
+            return None;
+        }
+    }
+
+    let work_dir = std::env::temp_dir().join("bulk-watermark-transform");
+    if let Err(e) = std::fs::create_dir_all(&work_dir) {
+        warn!(path = %work_dir.display(), error = %e, "Failed to create transform directory");
+        return None;
+    }
+
+    let stem = input_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("transformed");
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let sequence = TRANSFORM_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let output_path = work_dir.join(format!("{}_{}-{}.jpg", stem, millis, sequence));
+
+    let mut command = tokio::process::Command::new("jpegtran");
+    command.args(["-copy", "all"]);
+    if transform.rotate != 0 {
+        // -perfect makes jpegtran fail instead of leaving edge blocks untransformed
+        command.args(["-perfect", "-rotate"]);
+        command.arg(transform.rotate.to_string());
+    }
+    if let Some(crop) = &transform.crop {
+        command.arg("-crop");
+        command.arg(format!("{}x{}+{}+{}", crop.width, crop.height, crop.x, crop.y));
+    }
+    command.arg("-outfile").arg(&output_path).arg(input_path);
+
+    match command.output().await {
+        Ok(output) if output.status.success() => Some(output_path),
+        Ok(output) => {
+            warn!(
+                path = %input_path.display(),
+                error = %String::from_utf8_lossy(&output.stderr).trim(),
+                "jpegtran could not transform losslessly, falling back to FFmpeg filters"
+            );
+            let _ = std::fs::remove_file(&output_path);
+            None
+        }
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                warn!(
+                    path = %input_path.display(),
+                    "jpegtran is not installed, falling back to FFmpeg filters"
+                );
+            } else {
+                warn!(error = %e, "Failed to run jpegtran, falling back to FFmpeg filters");
+            }
+            None
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::error::ErrorCode;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum WatermarkPosition {
@@ -49,6 +51,138 @@ impl WatermarkPosition {
 pub enum WatermarkType {
     Text,
     Image,
+    /// A short clip, ideally with alpha (ProRes 4444, VP9), looped over the source
+    Video,
+}
+
+/// How the text watermark's size is specified.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum FontSizeMode {
+    /// `font_size` in pixels
+    #[default]
+    Pixels,
+    /// `relative_font_size` percent of the frame height, resolved per file
+    PercentHeight,
+}
+
+/// What an image watermark's `image_scale` is relative to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageScaleMode {
+    /// Percent of the source width
+    #[default]
+    Width,
+    /// Percent of the source height
+    Height,
+    /// Percent of the shorter source side, so portrait and landscape match
+    ShorterSide,
+    /// Absolute watermark width in pixels
+    Pixels,
+}
+
+/// How an image watermark is composited onto the source.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    /// Plain alpha overlay
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    SoftLight,
+}
+
+impl BlendMode {
+    /// Name of the mode in FFmpeg's `blend` filter; `None` for a plain overlay.
+    pub fn ffmpeg_name(&self) -> Option<&'static str> {
+        match self {
+            BlendMode::Normal => None,
+            BlendMode::Multiply => Some("multiply"),
+            BlendMode::Screen => Some("screen"),
+            BlendMode::Overlay => Some("overlay"),
+            BlendMode::SoftLight => Some("softlight"),
+        }
+    }
+
+    /// Color that leaves the base unchanged when blended with this mode.
+    pub fn neutral_color(&self) -> &'static str {
+        match self {
+            BlendMode::Multiply => "white",
+            BlendMode::Screen => "black",
+            BlendMode::Normal | BlendMode::Overlay | BlendMode::SoftLight => "0x808080",
+        }
+    }
+}
+
+/// Background color keyed out of an image watermark before it is overlaid,
+/// for logos delivered on a solid white or black background.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ColorKey {
+    /// Color to make transparent, as `#rrggbb` or an FFmpeg color name
+    pub color: String,
+    /// How close a pixel must be to `color` to be removed, 0.01-1.0
+    pub similarity: f32,
+    /// Softness of the edge between kept and removed pixels, 0.0-1.0
+    pub blend: f32,
+}
+
+/// Bounds of `adaptive_opacity`, in percent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpacityRange {
+    /// Opacity on plain backgrounds
+    pub min: u8,
+    /// Opacity on the busiest backgrounds
+    pub max: u8,
+}
+
+impl Default for ColorKey {
+    fn default() -> Self {
+        Self {
+            color: "#ffffff".to_string(),
+            similarity: 0.1,
+            blend: 0.0,
+        }
+    }
+}
+
+/// How a text watermark moves over a video, making it harder to crop out.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum MotionMode {
+    #[default]
+    Static,
+    /// Scrolls right to left across the frame at the preset's height
+    Ticker,
+    /// Bounces slowly between the frame edges
+    Drift,
+}
+
+/// How audio-only inputs (mp3, wav, m4a) are branded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioWatermarkMode {
+    /// Tags `audio_owner` into the copyright and comment metadata only
+    #[default]
+    Metadata,
+    /// Watermarks the embedded cover art, or a blank cover when there is none
+    CoverArt,
+    /// Renders a waveform video of the audio and watermarks that
+    Waveform,
+}
+
+/// Unit for watermark margins: pixels, or percent of the frame width
+/// (horizontal margin) and height (vertical margin).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MarginUnit {
+    #[default]
+    Px,
+    Percent,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,24 +191,564 @@ pub struct CustomPosition {
     pub y: f32,
 }
 
+/// Crop rectangle in pixels, expressed in the coordinates of the rotated image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Rotation and crop applied to JPEG inputs before the watermark; other inputs
+/// are left as they are. It is done losslessly with jpegtran, keeping EXIF,
+/// and falls back to FFmpeg filters in the watermark pass when jpegtran is
+/// missing or the crop is not on the JPEG block grid.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct JpegTransform {
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270
+    pub rotate: u16,
+    pub crop: Option<CropRect>,
+}
+
+impl JpegTransform {
+    pub fn is_identity(&self) -> bool {
+        self.rotate == 0 && self.crop.is_none()
+    }
+}
+
+/// Scaling of image and video outputs, done in the same FFmpeg pass before
+/// the watermark is drawn.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct OutputResize {
+    pub mode: ResizeMode,
+    /// Maximum or exact width in pixels
+    pub width: Option<u32>,
+    /// Maximum or exact height in pixels
+    pub height: Option<u32>,
+    pub filter: ScaleFilter,
+}
+
+impl OutputResize {
+    /// Size of a `width` x `height` frame after resizing.
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if let (ResizeMode::Exact, Some(exact_width), Some(exact_height)) =
+            (self.mode, self.width, self.height)
+        {
+            return (exact_width, exact_height);
+        }
+        let scale = [
+            self.width.map(|max| max as f64 / width.max(1) as f64),
+            self.height.map(|max| max as f64 / height.max(1) as f64),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(1.0, f64::min);
+        (
+            (width as f64 * scale).round() as u32,
+            (height as f64 * scale).round() as u32,
+        )
+    }
+}
+
+/// One of several outputs written for each input, e.g. a 1080x1350 crop for
+/// Instagram next to a 1920x1080 version for YouTube.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ExportVariant {
+    /// Appended to the output name, e.g. `photo_watermarked_instagram.jpg`
+    pub name: String,
+    /// Replaces the config's `resize`; `None` keeps the original size
+    pub resize: Option<OutputResize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResizeMode {
+    /// Shrinks to fit within `width` and `height`, keeping the aspect ratio;
+    /// smaller outputs are left as they are
+    #[default]
+    Fit,
+    /// Scales to cover `width` x `height` and crops the overflow, so the
+    /// output has exactly that size without distortion
+    Exact,
+}
+
+/// Resampling filter used for resizing.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScaleFilter {
+    /// Sharpest downscaling, the usual choice for photos
+    #[default]
+    Lanczos,
+    Bicubic,
+    Bilinear,
+    /// Averages source pixels; smooth for large reductions
+    Area,
+    /// Keeps hard pixel edges, for pixel art and screenshots
+    Neighbor,
+}
+
+impl ScaleFilter {
+    pub fn ffmpeg_name(&self) -> &'static str {
+        match self {
+            ScaleFilter::Lanczos => "lanczos",
+            ScaleFilter::Bicubic => "bicubic",
+            ScaleFilter::Bilinear => "bilinear",
+            ScaleFilter::Area => "area",
+            ScaleFilter::Neighbor => "neighbor",
+        }
+    }
+}
+
+/// Shortcut windows that place a video watermark relative to each file's
+/// duration instead of fixed start and end times.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScheduleMode {
+    /// Uses `start_time` and `end_time` as given
+    #[default]
+    Always,
+    /// Only the first `schedule_secs` seconds
+    Intro,
+    /// Only the last `schedule_secs` seconds
+    Outro,
+}
+
+/// Derives a file's watermark text from its name: when `pattern` matches
+/// the file name, the text becomes `text` with `$1` or `${name}` replaced by
+/// the pattern's capture groups.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextRule {
+    pub pattern: String,
+    pub text: String,
+}
+
+/// Codec video output is re-encoded with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Vp9,
+    Av1,
+}
+
+impl VideoCodec {
+    /// FFmpeg's software encoder for the codec.
+    pub fn ffmpeg_encoder(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::H265 => "libx265",
+            VideoCodec::Vp9 => "libvpx-vp9",
+            VideoCodec::Av1 => "libaom-av1",
+        }
+    }
+
+    /// Highest (worst) CRF the encoder accepts.
+    pub fn max_crf(&self) -> u8 {
+        match self {
+            VideoCodec::H264 | VideoCodec::H265 => 51,
+            VideoCodec::Vp9 | VideoCodec::Av1 => 63,
+        }
+    }
+}
+
+/// Encoder speed/size trade-off, named after the x264 presets.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderPreset {
+    Ultrafast,
+    Superfast,
+    Veryfast,
+    Faster,
+    Fast,
+    #[default]
+    Medium,
+    Slow,
+    Slower,
+    Veryslow,
+}
+
+impl EncoderPreset {
+    /// Value of x264's and x265's `-preset`.
+    pub fn ffmpeg_name(&self) -> &'static str {
+        match self {
+            EncoderPreset::Ultrafast => "ultrafast",
+            EncoderPreset::Superfast => "superfast",
+            EncoderPreset::Veryfast => "veryfast",
+            EncoderPreset::Faster => "faster",
+            EncoderPreset::Fast => "fast",
+            EncoderPreset::Medium => "medium",
+            EncoderPreset::Slow => "slow",
+            EncoderPreset::Slower => "slower",
+            EncoderPreset::Veryslow => "veryslow",
+        }
+    }
+
+    /// Equivalent `-cpu-used` for the libvpx and libaom encoders, where lower
+    /// is slower and smaller.
+    pub fn cpu_used(&self) -> u8 {
+        match self {
+            EncoderPreset::Ultrafast => 8,
+            EncoderPreset::Superfast => 7,
+            EncoderPreset::Veryfast => 6,
+            EncoderPreset::Faster => 5,
+            EncoderPreset::Fast => 4,
+            EncoderPreset::Medium => 3,
+            EncoderPreset::Slow => 2,
+            EncoderPreset::Slower => 1,
+            EncoderPreset::Veryslow => 0,
+        }
+    }
+}
+
+/// How FFmpeg shares the machine in background mode.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct BackgroundMode {
+    /// Runs FFmpeg below normal OS priority (nice 10 / below-normal class)
+    pub low_priority: bool,
+    /// Threads FFmpeg may use for decoding, filtering and encoding
+    pub threads: Option<u32>,
+}
+
+/// Video encoder settings. Without them FFmpeg picks the codec from the
+/// container and uses its default quality.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct EncodingOptions {
+    pub codec: VideoCodec,
+    /// Constant quality; lower is better. Defaults to the encoder's own.
+    pub crf: Option<u8>,
+    /// Target bitrate in kbit/s; replaces `crf` when set
+    pub bitrate_kbps: Option<u32>,
+    pub preset: EncoderPreset,
+}
+
+/// Format still images can be converted to on output.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl OutputImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputImageFormat::Jpeg => "jpg",
+            OutputImageFormat::Png => "png",
+            OutputImageFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Encoding of still image outputs, per format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ImageOutputOptions {
+    /// Converts every image to this format; `None` keeps each input's format
+    pub format: Option<OutputImageFormat>,
+    /// JPEG quality from 1 to 100, higher is better
+    pub jpeg_quality: u8,
+    /// PNG zlib compression level from 0 (fastest) to 9 (smallest)
+    pub png_compression: u8,
+    /// WebP quality from 0 to 100, higher is better
+    pub webp_quality: u8,
+}
+
+impl Default for ImageOutputOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            jpeg_quality: 92,
+            png_compression: 6,
+            webp_quality: 90,
+        }
+    }
+}
+
+/// What happens when a watermarked output is larger than its input.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeGrowthPolicy {
+    /// Keeps the output without checking
+    #[default]
+    Accept,
+    /// Keeps the output and reports it in the batch result
+    Warn,
+    /// Encodes again with stronger compression, keeps the smaller output and
+    /// reports it
+    Recompress,
+}
+
+/// Whether videos are encoded on the GPU when a working hardware encoder
+/// is detected.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum HardwareAcceleration {
+    /// Uses the first detected encoder, or the CPU when there is none
+    #[default]
+    Auto,
+    Off,
+    /// Fails instead of falling back to the CPU
+    Force,
+}
+
+/// Audio of video outputs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioHandling {
+    /// Copies audio the output container can hold and converts the rest to
+    /// the container's usual codec
+    #[default]
+    Auto,
+    /// Always copies, which fails when the container cannot hold the codec
+    Copy,
+    Aac,
+    Opus,
+    /// Writes the video without audio
+    Remove,
+}
+
+/// Handling of 10-bit and HDR video sources.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum HdrMode {
+    /// Keeps the bit depth, color tags and HDR mastering metadata. Such
+    /// videos are encoded on the CPU, and HDR sources without `encoding`
+    /// settings are written as H.265.
+    #[default]
+    Preserve,
+    /// Converts to 8-bit SDR for players without HDR support, tone mapping
+    /// HDR sources. Needs an FFmpeg build with zimg.
+    ToneMap,
+}
+
+/// Color format of a video source with more than 8 bits per component or an
+/// HDR transfer function, probed per file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceColor {
+    /// Bits per component, e.g. 10 for `yuv420p10le`
+    pub bit_depth: u32,
+    /// FFmpeg color names, e.g. `bt2020`, `smpte2084` and `bt2020nc`
+    pub primaries: Option<String>,
+    pub transfer: Option<String>,
+    pub matrix: Option<String>,
+    /// Mastering display metadata in x265 `master-display` syntax
+    pub master_display: Option<String>,
+    /// Content light level in x265 `max-cll` syntax
+    pub max_cll: Option<String>,
+}
+
+impl SourceColor {
+    /// PQ (HDR10) or HLG transfer.
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.transfer.as_deref(), Some("smpte2084" | "arib-std-b67"))
+    }
+}
+
+/// Periodic display of a video watermark: visible for `show_secs` out of
+/// every `every_secs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntermittentDisplay {
+    pub every_secs: f64,
+    pub show_secs: f64,
+}
+
+/// Untouched copy of each source written next to the watermarked delivery,
+/// for keeping a master archive from the same batch.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ArchiveCopy {
+    pub output_dir: String,
+    /// Re-encode videos to H.264/AAC MP4 instead of copying them byte for byte
+    pub transcode: bool,
+}
+
+/// Settings for the segmented video pipeline: split at keyframes, watermark
+/// segments in parallel, then concatenate without re-encoding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ChunkedEncoding {
+    pub segment_secs: u32,
+    /// Number of segments encoded at once; 0 uses half of the available cores
+    pub parallelism: usize,
+}
+
+impl Default for ChunkedEncoding {
+    fn default() -> Self {
+        Self {
+            segment_secs: 300,
+            parallelism: 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct WatermarkConfig {
     pub watermark_type: WatermarkType,
     pub text: String,
+    /// Rules deriving per-file text from file names, tried in order; files
+    /// in a text mapping keep their mapped text
+    pub text_rules: Vec<TextRule>,
     pub image_path: Option<String>,
+    /// Watermark clip for `WatermarkType::Video`; sized and blended like an image
+    pub video_path: Option<String>,
+    /// ID of a registered watermark source that generates each file's text or
+    /// image, replacing `text` and `image_path`
+    pub watermark_source: Option<String>,
     pub position: WatermarkPosition,
     pub opacity: u8,
     pub text_color: String,
+    /// Switches to the dark variant of the watermark when the content under it
+    /// is bright
+    pub auto_contrast: bool,
+    /// Text color used on bright content; defaults to the inverse of `text_color`
+    pub dark_text_color: Option<String>,
+    /// Image watermark used on bright content
+    pub dark_image_path: Option<String>,
+    /// Picks the opacity of each file within this range from how busy the
+    /// content under the watermark is; replaces `opacity`
+    pub adaptive_opacity: Option<OpacityRange>,
     pub font_size: u32,
+    pub font_size_mode: FontSizeMode,
+    /// Font size as a percentage of the frame height, used with
+    /// `FontSizeMode::PercentHeight`
+    pub relative_font_size: f32,
     pub font_family: String,
+    /// Movement of the text watermark on videos
+    pub motion: MotionMode,
+    /// Speed of `motion` in pixels per second
+    pub motion_speed: f32,
+    /// Moves the watermark to a new pseudo-random position every this many
+    /// seconds of video; overrides `motion`
+    pub jitter_interval_secs: Option<f32>,
+    /// Image watermark size: a percentage for the relative modes, or a width
+    /// in pixels for `ImageScaleMode::Pixels`
     #[serde(rename = "imageScale")]
     pub image_scale: Option<u32>,
+    pub image_scale_mode: ImageScaleMode,
+    pub blend_mode: BlendMode,
+    /// Keys a background color out of the watermark image
+    pub color_key: Option<ColorKey>,
     #[serde(rename = "positionMode")]
     pub position_mode: Option<String>,
     #[serde(rename = "customPosition")]
     pub custom_position: Option<CustomPosition>,
+    /// Horizontal distance between the watermark and the frame edge for
+    /// preset positions, in `margin_unit`
+    pub margin_x: f32,
+    /// Vertical distance between the watermark and the frame edge for
+    /// preset positions, in `margin_unit`
+    pub margin_y: f32,
+    pub margin_unit: MarginUnit,
+    pub jpeg_transform: Option<JpegTransform>,
+    /// Scales outputs, after `jpeg_transform` and before the watermark
+    pub resize: Option<OutputResize>,
+    /// Writes one output per variant instead of a single output
+    pub export_variants: Vec<ExportVariant>,
+    /// Value of the `{counter}` token for the first file in a batch
+    pub counter_start: u64,
+    /// Minimum number of digits for `{counter}`, padded with zeros
+    pub counter_padding: usize,
+    /// Locale of month and weekday names in date tokens, e.g. `fr_FR`
+    pub token_locale: Option<String>,
+    /// Output file name without extension, e.g. `{counter}_{name}`.
+    /// Defaults to `{name}_watermarked`.
+    pub output_name_template: Option<String>,
+    /// Seconds without frame progress before FFmpeg is treated as stalled
+    pub stall_timeout_secs: Option<u64>,
+    /// Seconds FFmpeg may spend on one file before it is killed and the file
+    /// fails with `TIMEOUT`; no limit when unset
+    pub file_timeout_secs: Option<u64>,
+    /// Caps disk/network IO per file in KB/s by staging through a local copy
+    pub io_limit_kb_per_sec: Option<u64>,
+    /// Zero-based CPU cores the encoding FFmpeg processes are pinned to, so
+    /// other software keeps the rest. Ignored on macOS.
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// Lower priority and fewer threads for FFmpeg, so the machine stays
+    /// usable during a batch
+    pub background_mode: Option<BackgroundMode>,
+    /// Codec, quality and speed of re-encoded videos
+    pub encoding: Option<EncodingOptions>,
+    /// Handling of image and video outputs larger than their input
+    pub size_growth: SizeGrowthPolicy,
+    /// Copies EXIF, XMP, ICC and IPTC data from JPEG inputs to JPEG outputs
+    pub preserve_metadata: bool,
+    /// Removes EXIF, GPS, device and serial number metadata from outputs for
+    /// publishing; overrides `preserve_metadata` and the metadata part of
+    /// `preserve_streams`
+    pub strip_metadata: bool,
+    /// Keeps every audio and subtitle track, metadata and chapters of videos
+    /// instead of only the default streams
+    pub preserve_streams: bool,
+    /// Format and quality of still image outputs
+    pub image_output: ImageOutputOptions,
+    /// Hardware encoding for MP4, MOV and MKV video output
+    pub hardware_acceleration: HardwareAcceleration,
+    /// Keeps or tone maps 10-bit and HDR videos
+    pub hdr_mode: HdrMode,
+    /// Copies, converts or removes the audio of videos
+    pub audio_handling: AudioHandling,
+    /// Audio codecs of the video being encoded; resolved per file for
+    /// `AudioHandling::Auto`, never part of a saved config
+    #[serde(skip)]
+    pub source_audio_codecs: Vec<String>,
+    /// Writes videos as MP4 that browsers and phones play: 8-bit H.264 High
+    /// profile, AAC audio and the index at the start of the file. Overrides
+    /// `encoding.codec` and `hdr_mode`
+    pub web_optimized: bool,
+    /// Color format of the video being encoded; resolved per file, never
+    /// part of a saved config
+    #[serde(skip)]
+    pub source_color: Option<SourceColor>,
+    /// Seconds into a video at which the watermark appears
+    pub start_time: Option<f64>,
+    /// Seconds into a video after which the watermark disappears
+    pub end_time: Option<f64>,
+    /// Shows the watermark periodically instead of continuously
+    pub intermittent: Option<IntermittentDisplay>,
+    /// Intro/outro shortcut; replaces `start_time` and `end_time`
+    pub schedule: ScheduleMode,
+    /// Length of the intro or outro window in seconds
+    pub schedule_secs: f64,
+    /// Seconds over which the watermark fades in on videos
+    pub fade_in_secs: f32,
+    /// Seconds over which the watermark fades out on videos, ending at
+    /// `end_time` or the end of the video
+    pub fade_out_secs: f32,
+    /// Splits videos into segments that are watermarked in parallel
+    pub chunked_encoding: Option<ChunkedEncoding>,
+    pub audio_mode: AudioWatermarkMode,
+    /// Owner written into audio metadata; defaults to the text watermark
+    pub audio_owner: Option<String>,
+    /// Also writes an unwatermarked archive copy of every source
+    pub archive_copy: Option<ArchiveCopy>,
+    /// Processes identical inputs (hard links, equal checksums) once per batch.
+    /// Off by default: every input of a shared size is hashed before the batch
+    pub reuse_duplicate_inputs: bool,
+    /// Writes `<output>.json` with provenance details next to each output
+    pub write_sidecar: bool,
+    /// IDs of registered post-processors to run on each output, in order
+    pub post_processors: Vec<String>,
 }
 
 impl WatermarkConfig {
@@ -82,6 +756,83 @@ impl WatermarkConfig {
         self.position_mode.as_ref().map_or(false, |mode| mode == "custom")
     }
 
+    /// Whether the watermark is limited to part of a video's duration.
+    pub fn has_time_window(&self) -> bool {
+        self.start_time.is_some()
+            || self.end_time.is_some()
+            || self.intermittent.is_some()
+            || self.schedule != ScheduleMode::Always
+    }
+
+    /// Settings for encoding an output again when it came out larger than
+    /// its input: a higher CRF and slower preset on the CPU for videos, lower
+    /// quality and maximum compression for images.
+    pub fn with_stronger_compression(&self) -> Self {
+        let mut config = self.clone();
+        let mut encoding = config.encoding.take().unwrap_or_default();
+        let crf = encoding.crf.unwrap_or(23).saturating_add(5);
+        encoding.crf = Some(crf.min(encoding.codec.max_crf()));
+        encoding.bitrate_kbps = None;
+        encoding.preset = EncoderPreset::Slow;
+        config.encoding = Some(encoding);
+        config.hardware_acceleration = HardwareAcceleration::Off;
+
+        let image_output = &mut config.image_output;
+        image_output.jpeg_quality = image_output.jpeg_quality.saturating_sub(10).max(50);
+        image_output.png_compression = 9;
+        image_output.webp_quality = image_output.webp_quality.saturating_sub(10).max(50);
+        config
+    }
+
+    /// Turns the intro/outro shortcut into a concrete `start_time` and
+    /// `end_time` for one video. Outros need the video's `duration`.
+    pub fn apply_schedule(&mut self, duration: Option<f64>) {
+        match (self.schedule, duration) {
+            (ScheduleMode::Intro, _) => {
+                self.start_time = Some(0.0);
+                self.end_time = Some(self.schedule_secs);
+            }
+            (ScheduleMode::Outro, Some(duration)) => {
+                self.start_time = Some((duration - self.schedule_secs).max(0.0));
+                self.end_time = Some(duration);
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the video is encoded with more than 8 bits per component.
+    pub fn keeps_high_bit_depth(&self) -> bool {
+        self.hdr_mode == HdrMode::Preserve
+            && !self.web_optimized
+            && self.source_color.as_ref().is_some_and(|color| color.bit_depth > 8)
+    }
+
+    /// Whether an HDR video is converted to SDR.
+    pub fn tone_maps(&self) -> bool {
+        (self.hdr_mode == HdrMode::ToneMap || self.web_optimized)
+            && self.source_color.as_ref().is_some_and(SourceColor::is_hdr)
+    }
+
+    pub fn has_fade(&self) -> bool {
+        self.fade_in_secs > 0.0 || self.fade_out_secs > 0.0
+    }
+
+    /// Whether FFmpeg runs below normal priority.
+    pub fn low_priority(&self) -> bool {
+        self.background_mode.as_ref().is_some_and(|mode| mode.low_priority)
+    }
+
+    /// Whether the watermark changes over a video's timeline, so it must be
+    /// rendered against the original timestamps.
+    pub fn is_time_based(&self) -> bool {
+        let moving_text =
+            matches!(self.watermark_type, WatermarkType::Text) && self.motion != MotionMode::Static;
+        self.has_time_window()
+            || self.has_fade()
+            || moving_text
+            || self.jitter_interval_secs.is_some()
+    }
+
     pub fn validate_custom_position(&self) -> Result<(), String> {
         if let Some(custom_pos) = &self.custom_position {
             if custom_pos.x < 0.0 || custom_pos.x > 1.0 {
@@ -100,15 +851,71 @@ impl Default for WatermarkConfig {
         Self {
             watermark_type: WatermarkType::Text,
             text: "Watermark".to_string(),
+            text_rules: Vec::new(),
             image_path: None,
+            video_path: None,
+            watermark_source: None,
             position: WatermarkPosition::BottomRight,
             opacity: 80,
             text_color: "#ffffff".to_string(),
+            auto_contrast: false,
+            dark_text_color: None,
+            dark_image_path: None,
+            adaptive_opacity: None,
             font_size: 48,
+            font_size_mode: FontSizeMode::Pixels,
+            relative_font_size: 5.0,
             font_family: "Arial".to_string(),
+            motion: MotionMode::Static,
+            motion_speed: 120.0,
+            jitter_interval_secs: None,
             image_scale: Some(20),
+            image_scale_mode: ImageScaleMode::Width,
+            blend_mode: BlendMode::Normal,
+            color_key: None,
             position_mode: Some("preset".to_string()),
             custom_position: None,
+            margin_x: 20.0,
+            margin_y: 20.0,
+            margin_unit: MarginUnit::Px,
+            jpeg_transform: None,
+            resize: None,
+            export_variants: Vec::new(),
+            counter_start: 1,
+            counter_padding: 3,
+            token_locale: None,
+            output_name_template: None,
+            stall_timeout_secs: None,
+            file_timeout_secs: None,
+            io_limit_kb_per_sec: None,
+            cpu_affinity: None,
+            background_mode: None,
+            encoding: None,
+            size_growth: SizeGrowthPolicy::Accept,
+            preserve_metadata: true,
+            strip_metadata: false,
+            preserve_streams: true,
+            image_output: ImageOutputOptions::default(),
+            hardware_acceleration: HardwareAcceleration::Auto,
+            hdr_mode: HdrMode::Preserve,
+            audio_handling: AudioHandling::Auto,
+            source_audio_codecs: Vec::new(),
+            web_optimized: false,
+            source_color: None,
+            start_time: None,
+            end_time: None,
+            intermittent: None,
+            schedule: ScheduleMode::Always,
+            schedule_secs: 5.0,
+            fade_in_secs: 0.0,
+            fade_out_secs: 0.0,
+            chunked_encoding: None,
+            audio_mode: AudioWatermarkMode::Metadata,
+            audio_owner: None,
+            archive_copy: None,
+            reuse_duplicate_inputs: false,
+            write_sidecar: false,
+            post_processors: Vec::new(),
         }
     }
 }
@@ -122,12 +929,49 @@ pub struct FileItem {
     pub size: Option<u64>,
 }
 
+/// Media files found in a folder plus the files that were left out.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderScan {
+    pub files: Vec<FileItem>,
+    pub excluded: Vec<ExcludedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExcludedFile {
+    pub path: PathBuf,
+    pub reason: ExclusionReason,
+    /// Underlying error or the unsupported extension, when there is one
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExclusionReason {
+    UnsupportedExtension,
+    ZeroBytes,
+    Unreadable,
+    /// The header cannot be decoded (truncated or damaged file)
+    Corrupt,
+}
+
+/// Files of a batch that failed the preflight check.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    pub checked: usize,
+    pub problems: Vec<ExcludedFile>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ProcessingStatus {
     Success,
     Failed,
     Skipped,
+    /// Same content as another input in the batch; the output was reused
+    Duplicate,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +981,36 @@ pub struct FileResult {
     pub output_path: Option<PathBuf>,
     pub status: ProcessingStatus,
     pub error: Option<String>,
+    /// Kind of failure, when `status` is `Failed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    /// Input whose output was reused, when `status` is `Duplicate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<PathBuf>,
+    /// Set when the output came out larger than the input and the
+    /// `size_growth` policy reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_growth: Option<SizeGrowth>,
+    /// Expected output size, reported by simulated batches instead of an output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_bytes: Option<u64>,
+    /// Time spent watermarking the file; not set for reused duplicate outputs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Every output written when the config has export variants; `output_path`
+    /// is the first of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variant_outputs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeGrowth {
+    pub input_bytes: u64,
+    /// Size of the output that was kept
+    pub output_bytes: u64,
+    /// Whether the kept output is a stronger-compressed second encode
+    pub recompressed: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -146,6 +1020,113 @@ pub struct BatchResult {
     pub total: usize,
     pub successful: usize,
     pub failed: usize,
+    /// Successful files whose output was reused from an identical input
+    #[serde(default)]
+    pub duplicates: usize,
+    /// Successful files whose output came out larger than their input
+    #[serde(default)]
+    pub grown: usize,
+    /// ID for paging the files with `get_batch_results`, once the batch is done
+    #[serde(default)]
+    pub job_id: Option<String>,
+    /// Set when the batch was only simulated and nothing was written
+    #[serde(default)]
+    pub simulated: bool,
+    /// Expected size of all outputs of a simulated batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_bytes: Option<u64>,
+}
+
+/// Narrows the files returned by `get_batch_results`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ResultFilter {
+    pub status: Option<ProcessingStatus>,
+    /// Case-insensitive text the input path must contain
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResultPage {
+    pub files: Vec<FileResult>,
+    pub offset: usize,
+    /// Files matching the filter, across all pages
+    pub matching: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostProcessorInfo {
+    pub id: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatermarkSourceInfo {
+    pub id: String,
+    pub description: String,
+}
+
+/// Provenance record written next to an output file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputSidecar {
+    pub source_path: PathBuf,
+    pub output_path: PathBuf,
+    pub source_sha256: String,
+    pub output_sha256: String,
+    pub config_hash: String,
+    pub source_modified_at: Option<String>,
+    pub processed_at: String,
+    pub app_version: String,
+}
+
+/// Dry run of a batch returned by `plan_batch`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchPlan {
+    pub files: Vec<PlannedFile>,
+    /// Files that would be skipped because they are missing or unsupported
+    pub skipped: usize,
+    /// Files whose output path is already taken by an earlier file in the batch
+    pub collisions: usize,
+    /// Files whose output would replace an existing file
+    pub overwrites: usize,
+    /// Estimated disk space the outputs (and archive copies) will take
+    pub estimated_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFile {
+    pub input_path: PathBuf,
+    pub output_path: Option<PathBuf>,
+    pub issues: Vec<PlanIssue>,
+    pub estimated_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum PlanIssue {
+    Missing,
+    Unsupported,
+    /// Same output path as the file at `with_index`, which would be overwritten
+    #[serde(rename_all = "camelCase")]
+    Collision { with_index: usize },
+    OverwritesExisting,
+    OverwritesInput,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleBatchResult {
+    pub job_id: String,
+    pub sample: BatchResult,
+    pub remaining: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -157,12 +1138,54 @@ pub struct ProgressPayload {
     pub status: String,
 }
 
+/// A batch whose journal shows it stopped before finishing, e.g. after a
+/// crash or power loss.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedBatch {
+    pub batch_id: String,
+    pub started_at: String,
+    pub output_dir: PathBuf,
+    pub total: usize,
+    pub successful: usize,
+    pub failed: usize,
+    /// Files that were being encoded when the batch stopped
+    pub in_progress: Vec<InterruptedFile>,
+    /// Files that were never started
+    pub pending: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedFile {
+    pub input_path: PathBuf,
+    /// The first output; export variants write several
+    pub output_path: PathBuf,
+    /// Whether a partially written output, of any variant, is still on disk
+    pub partial_output_exists: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PresetMetadata {
     pub id: String,
     pub name: String,
     pub description: String,
+    pub source: PresetSource,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    /// Whether `load_preset` returns this preset when called without an ID
+    pub is_default: bool,
+}
+
+/// Where a listed preset is stored.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresetSource {
+    /// Shipped with the app and read-only
+    Bundled,
+    /// Saved in the app data directory; can be overwritten and deleted
+    User,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -170,23 +1193,316 @@ pub struct PresetMetadata {
 pub struct WatermarkPreset {
     pub name: String,
     pub description: String,
+    /// ID of the preset this one inherits from; only the config fields that
+    /// differ from the base are stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Group the preset is listed under, e.g. `photography` or `real-estate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Free-form labels such as client names, for filtering
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Presets with a base may list only the fields they override
+    #[serde(default)]
     pub config: WatermarkConfig,
+    /// Output settings applied by batches run with this preset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PresetOutput>,
+    /// Watermark images carried inside an exported preset file; importing
+    /// stores them in the app data directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<EmbeddedAsset>,
 }
 
+/// An image referenced by a preset's config, embedded in an exported file.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct ThumbnailCacheEntry {
-    pub video_path: String,
-    pub video_mtime: u64,  // Unix timestamp of video file modification time
-    pub thumbnail_path: PathBuf,
-    pub created_at: u64,  // Unix timestamp when thumbnail was created
-    pub last_accessed: u64,  // Unix timestamp of last access
-    pub file_size: u64,  // Size of thumbnail in bytes
+pub struct EmbeddedAsset {
+    pub field: AssetField,
+    pub file_name: String,
+    /// File contents, base64 encoded
+    pub data: String,
+}
+
+/// Config fields that reference a file a preset can carry.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AssetField {
+    ImagePath,
+    DarkImagePath,
 }
 
+impl AssetField {
+    pub const ALL: [AssetField; 2] = [AssetField::ImagePath, AssetField::DarkImagePath];
+
+    /// Prefix of the stored file, which keeps two assets with the same
+    /// file name apart.
+    pub fn name(self) -> &'static str {
+        match self {
+            AssetField::ImagePath => "image",
+            AssetField::DarkImagePath => "dark-image",
+        }
+    }
+
+    pub fn path_mut(self, config: &mut WatermarkConfig) -> &mut Option<String> {
+        match self {
+            AssetField::ImagePath => &mut config.image_path,
+            AssetField::DarkImagePath => &mut config.dark_image_path,
+        }
+    }
+}
+
+/// Output settings a preset can carry next to its watermark config. Each
+/// one applies unless the batch config already sets it.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
-pub struct ThumbnailCache {
-    pub entries: std::collections::HashMap<String, ThumbnailCacheEntry>,
-    pub version: u32,  // Cache format version for future compatibility
+#[serde(default)]
+pub struct PresetOutput {
+    /// Format images are converted to
+    pub format: Option<OutputImageFormat>,
+    /// JPEG and WebP quality from 1 to 100
+    pub quality: Option<u8>,
+    /// Appended to each input's name in place of `_watermarked`
+    pub suffix: Option<String>,
+    /// Folder inside the output directory that outputs are written to
+    pub subfolder: Option<String>,
+}
+
+impl PresetOutput {
+    /// Fills in the output settings `config` leaves at their defaults.
+    pub fn apply_to(&self, config: &mut WatermarkConfig) {
+        let defaults = ImageOutputOptions::default();
+        let image_output = &mut config.image_output;
+        if image_output.format.is_none() {
+            image_output.format = self.format;
+        }
+        if let Some(quality) = self.quality {
+            if image_output.jpeg_quality == defaults.jpeg_quality {
+                image_output.jpeg_quality = quality;
+            }
+            if image_output.webp_quality == defaults.webp_quality {
+                image_output.webp_quality = quality;
+            }
+        }
+
+        let has_template = config
+            .output_name_template
+            .as_deref()
+            .is_some_and(|template| !template.trim().is_empty());
+        if let Some(suffix) = self.suffix.as_deref().filter(|_| !has_template) {
+            config.output_name_template = Some(format!("{{name}}{}", suffix));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubFrame {
+    pub index: u32,
+    pub timestamp: f64,  // Seconds from the start of the video
+}
+
+/// Contact sheet of evenly spaced frames, laid out left to right, top to bottom.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubStrip {
+    pub image_path: String,
+    pub duration: f64,
+    pub columns: u32,
+    pub rows: u32,
+    pub frames: Vec<ScrubFrame>,
+}
+
+/// Evenly spaced small frames of a video for a scrub-style preview, either as
+/// one image per frame or tiled into a single row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoFilmstrip {
+    pub duration: f64,
+    pub frames: Vec<ScrubFrame>,
+    /// One image per frame, in order; empty when tiled
+    pub frame_paths: Vec<String>,
+    /// All frames left to right, when tiled
+    pub sheet_path: Option<String>,
+}
+
+/// Media details returned by `get_media_info`. `width` and `height` are those
+/// of the stored frames, before `rotation` is applied.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Size after rotation, as players show the video and as watermark
+    /// positions are computed
+    pub display_width: Option<u32>,
+    pub display_height: Option<u32>,
+    /// Seconds; `None` for still images
+    pub duration: Option<f64>,
+    /// Container format as named by FFmpeg, e.g. `mov,mp4,m4a,3gp,3g2,mj2`
+    pub format: Option<String>,
+    pub video_codec: Option<String>,
+    pub frame_rate: Option<f64>,
+    /// Clockwise degrees: 0, 90, 180 or 270
+    pub rotation: i32,
+    /// FFmpeg pixel format of the video, e.g. `yuv420p10le`
+    pub pixel_format: Option<String>,
+    /// Bits per color component of the video
+    pub bit_depth: Option<u32>,
+    /// Whether the video uses a PQ (HDR10) or HLG transfer
+    pub hdr: bool,
+    pub audio_streams: Vec<AudioStreamInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioStreamInfo {
+    pub index: u32,
+    pub codec: Option<String>,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub language: Option<String>,
+}
+
+/// FFmpeg invocation returned by `preview_ffmpeg_command`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegCommandPreview {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// `program` and `args` quoted for pasting into a shell
+    pub command_line: String,
+}
+
+/// Outcome of `nudge_watermark_position`. Busyness is the edge density under
+/// the watermark, from 0.0 (flat) to 1.0 (edges everywhere).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionNudge {
+    pub config: WatermarkConfig,
+    pub moved: bool,
+    pub original_busyness: f32,
+    pub busyness: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewFrame {
+    pub timestamp: f64,  // Seconds from the start of the video
+    pub image_path: String,
+}
+
+/// A short watermarked clip from the start of a video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewClip {
+    pub video_path: String,
+    /// Seconds rendered; shorter than requested for short videos
+    pub duration: f64,
+}
+
+/// One frame rendered before and after watermarking at the same resolution,
+/// for a comparison slider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonPreview {
+    pub timestamp: f64,
+    pub original_path: String,
+    pub watermarked_path: String,
+    /// Both frames side by side, original on the left, when requested
+    pub composite_path: Option<String>,
+}
+
+/// Which FFmpeg and ffprobe binaries are run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum FfmpegSource {
+    /// The bundled binaries, or the system ones when they are missing
+    #[default]
+    Auto,
+    Bundled,
+    /// Binaries on `PATH`, when they are recent enough
+    System,
+}
+
+/// The chosen FFmpeg source and what is available, from `get_ffmpeg_source`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegSourceInfo {
+    pub source: FfmpegSource,
+    pub bundled_available: bool,
+    pub system_path: Option<PathBuf>,
+    pub system_version: Option<String>,
+    /// Why the system FFmpeg cannot be used, when it cannot
+    pub system_error: Option<String>,
+}
+
+/// Result of `self_test`, for triaging installs where nothing works.
+#[derive(Debug, Clone, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    /// The FFmpeg that was tested, bundled or from the system
+    pub ffmpeg_path: Option<PathBuf>,
+    pub ffmpeg_version: Option<String>,
+    /// FFmpeg was found and runs
+    pub binary_found: bool,
+    pub drawtext_available: bool,
+    /// drawtext found a font for the sample watermark
+    pub fonts_ok: bool,
+    pub sample_rendered: bool,
+    pub problems: Vec<String>,
+}
+
+/// FFmpeg build installed by `download_ffmpeg`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadedFfmpeg {
+    pub version: String,
+    pub ffmpeg_path: PathBuf,
+    pub ffprobe_path: PathBuf,
+}
+
+/// Thumbnail cache limits and location, set with `set_cache_settings`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct CacheSettings {
+    /// Total thumbnail size before the least recently used ones are deleted
+    pub max_bytes: u64,
+    pub max_entries: u64,
+    /// Age at which `cleanup_thumbnail_cache` removes thumbnails by default
+    pub retention_days: u32,
+    /// Directory the thumbnail folder is created in; the system temp
+    /// directory by default
+    pub directory: Option<PathBuf>,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes: 500 * 1024 * 1024,
+            max_entries: 100,
+            retention_days: 7,
+            directory: None,
+        }
+    }
+}
+
+/// A file from the processing history returned by `get_processing_history`,
+/// `get_history` and `search_history`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessedFileRecord {
+    pub id: i64,
+    pub job_id: String,
+    pub input_path: PathBuf,
+    pub output_path: Option<PathBuf>,
+    pub status: ProcessingStatus,
+    pub error: Option<String>,
+    pub processed_at: u64,  // Unix timestamp when the file finished
+    pub duration_ms: Option<u64>,
+    /// Config the file was processed with, its own caption included; missing
+    /// for files recorded by older versions
+    pub config: Option<WatermarkConfig>,
 }
@@ -0,0 +1,198 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use tracing::warn;
+
+const UPDATES_FILE: &str = "updates.json";
+
+const STABLE_ENDPOINT: &str =
+    "https://github.com/kehhhh/Bulk-Watermark/releases/latest/download/latest.json";
+/// Prereleases publish their manifest on the rolling `beta` release
+const BETA_ENDPOINT: &str =
+    "https://github.com/kehhhh/Bulk-Watermark/releases/download/beta/latest.json";
+
+/// Stable releases reach every install within this many days of publishing.
+const ROLLOUT_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Early builds, offered as soon as they are published
+    Beta,
+}
+
+impl UpdateChannel {
+    fn endpoint(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => STABLE_ENDPOINT,
+            UpdateChannel::Beta => BETA_ENDPOINT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+struct UpdateSettings {
+    channel: UpdateChannel,
+    /// 0-99, drawn once per install; lower buckets get stable releases first
+    rollout_bucket: Option<u8>,
+}
+
+/// A release newer than the running version.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseInfo {
+    pub version: String,
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    /// Publish date, RFC 3339
+    pub date: Option<String>,
+    pub notes: Option<String>,
+    /// False while the staged rollout has not reached this install
+    pub ready: bool,
+    /// When the rollout reaches this install, RFC 3339
+    pub ready_at: Option<String>,
+}
+
+/// Payload of `update-progress` events while an update downloads.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// Update channel and rollout bucket, persisted in the app data directory and
+/// managed as Tauri state.
+#[derive(Default)]
+pub struct Updates {
+    settings: Mutex<Option<UpdateSettings>>,
+}
+
+impl Updates {
+    pub fn channel(&self, app: &AppHandle) -> UpdateChannel {
+        self.with_settings(app, |settings| settings.channel)
+    }
+
+    pub fn set_channel(&self, app: &AppHandle, channel: UpdateChannel) -> Result<(), String> {
+        self.with_settings(app, |settings| {
+            settings.channel = channel;
+            save(app, settings)
+        })
+    }
+
+    /// The install's rollout bucket, drawn and saved on first use.
+    fn rollout_bucket(&self, app: &AppHandle) -> u8 {
+        self.with_settings(app, |settings| {
+            if let Some(bucket) = settings.rollout_bucket {
+                return bucket;
+            }
+            let bucket = rand::thread_rng().gen_range(0..100);
+            settings.rollout_bucket = Some(bucket);
+            if let Err(e) = save(app, settings) {
+                warn!(error = %e, "Failed to save update settings");
+            }
+            bucket
+        })
+    }
+
+    fn with_settings<T>(
+        &self,
+        app: &AppHandle,
+        update: impl FnOnce(&mut UpdateSettings) -> T,
+    ) -> T {
+        let mut guard = self.settings.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        update(guard.get_or_insert_with(|| load(app)))
+    }
+}
+
+/// Asks the endpoint of the selected channel for a newer release. Stable
+/// releases are rolled out over `ROLLOUT_DAYS`: the release is reported but
+/// not `ready` until this install's bucket comes up.
+pub async fn check(app: &AppHandle) -> Result<Option<(Update, ReleaseInfo)>, String> {
+    let state = app.state::<Updates>();
+    let channel = state.channel(app);
+    let endpoint = Url::parse(channel.endpoint())
+        .map_err(|e| format!("Invalid update endpoint: {}", e))?;
+    let update = app
+        .updater_builder()
+        .endpoints(vec![endpoint])
+        .and_then(|builder| builder.build())
+        .map_err(|e| format!("Failed to set up the updater: {}", e))?
+        .check()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+    let Some(update) = update else {
+        return Ok(None);
+    };
+
+    let published = update
+        .date
+        .and_then(|date| DateTime::<Utc>::from_timestamp(date.unix_timestamp(), 0));
+    let ready_at = match (channel, published) {
+        (UpdateChannel::Stable, Some(published)) => {
+            let delay = Duration::days(ROLLOUT_DAYS) * i32::from(state.rollout_bucket(app)) / 100;
+            Some(published + delay)
+        }
+        _ => None,
+    };
+
+    let info = ReleaseInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        channel,
+        date: published.map(|date| date.to_rfc3339()),
+        notes: update.body.clone(),
+        ready: ready_at.is_none_or(|ready_at| ready_at <= Utc::now()),
+        ready_at: ready_at.map(|date| date.to_rfc3339()),
+    };
+    Ok(Some((update, info)))
+}
+
+/// Downloads and installs `update`, emitting `update-progress` as it goes.
+/// The app has to be restarted afterwards.
+pub async fn install(app: &AppHandle, update: &Update) -> Result<(), String> {
+    let mut downloaded = 0u64;
+    update
+        .download_and_install(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                let progress = UpdateProgress { downloaded, total };
+                let _ = app.emit_to("main", "update-progress", progress);
+            },
+            || {},
+        )
+        .await
+        .map_err(|e| format!("Failed to install update: {}", e))
+}
+
+fn settings_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(UPDATES_FILE))
+}
+
+fn load(app: &AppHandle) -> UpdateSettings {
+    settings_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, settings: &UpdateSettings) -> Result<(), String> {
+    let path = settings_path(app)
+        .ok_or_else(|| "Failed to resolve the update settings file".to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize update settings: {}", e))?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write update settings: {}", e))
+}
@@ -10,13 +10,21 @@ import {
   Box,
   Tooltip,
 } from '@mantine/core'
-import { IconPlus, IconTrash, IconPhoto, IconVideo, IconCloudUpload } from '@tabler/icons-react'
+import {
+  IconPlus,
+  IconTrash,
+  IconPhoto,
+  IconVideo,
+  IconCloudUpload,
+  IconFolderPlus,
+} from '@tabler/icons-react'
 
 import type { FileItem } from '@/types/watermark'
 
 interface FileListProps {
   files: FileItem[]
   onAddFiles: () => void
+  onAddFolder?: () => void
   onRemoveFile: (path: string) => void
   onSelectFile?: (file: FileItem) => void
   onClearFiles?: () => void
@@ -31,7 +39,14 @@ const formatFileSize = (bytes?: number): string => {
   return `${(bytes / (1024 * 1024)).toFixed(1)} MB`
 }
 
-export function FileList({ files, onAddFiles, onRemoveFile, onSelectFile, onClearFiles }: FileListProps) {
+export function FileList({
+  files,
+  onAddFiles,
+  onAddFolder,
+  onRemoveFile,
+  onSelectFile,
+  onClearFiles,
+}: FileListProps) {
   return (
     <Stack gap="md">
       {/* Header */}
@@ -40,17 +55,26 @@ export function FileList({ files, onAddFiles, onRemoveFile, onSelectFile, onClea
           <Text fw={700} size="lg">{files.length} Files</Text>
           <Text size="xs" c="dimmed">Ready to process</Text>
         </div>
-        <Tooltip label="Select images or videos to watermark">
-          <Button
-            leftSection={<IconPlus size={16} />}
-            variant="gradient"
-            gradient={{ from: 'blue', to: 'cyan' }}
-            size="sm"
-            onClick={onAddFiles}
-          >
-            Add Files
-          </Button>
-        </Tooltip>
+        <Group gap="xs">
+          {onAddFolder && (
+            <Tooltip label="Add every supported file in a folder">
+              <ActionIcon variant="light" size="lg" onClick={onAddFolder} aria-label="Add folder">
+                <IconFolderPlus size={18} />
+              </ActionIcon>
+            </Tooltip>
+          )}
+          <Tooltip label="Select images or videos to watermark">
+            <Button
+              leftSection={<IconPlus size={16} />}
+              variant="gradient"
+              gradient={{ from: 'blue', to: 'cyan' }}
+              size="sm"
+              onClick={onAddFiles}
+            >
+              Add Files
+            </Button>
+          </Tooltip>
+        </Group>
       </Group>
 
       {/* Empty State */}
@@ -76,6 +76,13 @@ export function PreviewCanvas({ config, previewFile, onConfigUpdate }: PreviewCa
     setIsLoading(true)
 
     const loadSourceImage = async () => {
+      // Audio files have no frame to preview
+      if (previewFile.type === 'audio') {
+        setShowVideoPlaceholder(true)
+        setIsLoading(false)
+        return
+      }
+
       try {
         if (previewFile.type === 'video') {
           // Handle video files by extracting thumbnail
@@ -506,7 +513,9 @@ export function PreviewCanvas({ config, previewFile, onConfigUpdate }: PreviewCa
 
         {!isLoading && !error && showVideoPlaceholder && (
           <Text size="sm" c="dimmed" ta="center">
-            Unable to generate video preview. Watermark will be applied during processing.
+            {previewFile?.type === 'audio'
+              ? 'Audio files have no preview. Branding will be applied during processing.'
+              : 'Unable to generate video preview. Watermark will be applied during processing.'}
           </Text>
         )}
 
@@ -18,7 +18,7 @@ import {
 import { IconCheck, IconX, IconAlertCircle, IconInfoCircle, IconCopy, IconFolderOpen } from '@tabler/icons-react'
 import { invoke } from '@tauri-apps/api/core'
 
-import type { BatchResult } from '@/types/watermark'
+import type { BatchResult, SizeGrowth } from '@/types/watermark'
 
 interface ResultsSummaryProps {
   result: BatchResult | null
@@ -37,6 +37,12 @@ function getDirectoryPath(filePath: string): string {
   return segments.join('\\') || segments.join('/')
 }
 
+function describeGrowth(growth: SizeGrowth) {
+  const percent = Math.round((growth.outputBytes / growth.inputBytes - 1) * 100)
+  const size = percent > 0 ? `${percent}% larger than the input` : 'no larger than the input'
+  return growth.recompressed ? `Output is ${size} after recompressing.` : `Output is ${size}.`
+}
+
 export function ResultsSummary({ result, isOpen, onClose }: ResultsSummaryProps) {
   const titleText = result && result.failed === 0 ? 'Processing Complete! 🎉' : result && result.failed > 0 ? 'Processing Finished with Errors' : 'Processing Complete'
   const successPercentage = result ? (result.successful / result.total) * 100 : 0
@@ -121,6 +127,15 @@ export function ResultsSummary({ result, isOpen, onClose }: ResultsSummaryProps)
             </Alert>
           )}
 
+          {/* Size Growth Alert */}
+          {(result.grown ?? 0) > 0 && (
+            <Alert color="yellow" icon={<IconAlertCircle size={24} />} variant="light">
+              <Text size="sm">
+                {result.grown} output{result.grown === 1 ? ' is' : 's are'} larger than the original file. See the results below for details.
+              </Text>
+            </Alert>
+          )}
+
           {/* Timeline View */}
           <Divider label="Processing Results" labelPosition="center" my="lg" />
 
@@ -155,6 +170,11 @@ export function ResultsSummary({ result, isOpen, onClose }: ResultsSummaryProps)
                         )}
                       </CopyButton>
                     </Group>
+                    {file.sizeGrowth && (
+                      <Text size="xs" c="yellow">
+                        {describeGrowth(file.sizeGrowth)}
+                      </Text>
+                    )}
                   </Stack>
                 ) : file.status === 'failed' && file.error ? (
                   <Alert color="red" variant="light" mt="xs">
@@ -1,8 +1,10 @@
 import { useEffect, useState } from 'react'
-import { check } from '@tauri-apps/plugin-updater'
-import { relaunch } from '@tauri-apps/plugin-process'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { Modal, Button, Text, Progress, Group, Stack, Alert } from '@mantine/core'
 import { IconDownload, IconInfoCircle } from '@tabler/icons-react'
+import type { ReleaseInfo, UpdateProgress } from '@/types/watermark'
+import { errorMessage } from '@/utils/errors'
 
 export function UpdateChecker() {
   const [updateAvailable, setUpdateAvailable] = useState(false)
@@ -17,10 +19,11 @@ export function UpdateChecker() {
 
   const checkForUpdates = async () => {
     try {
-      const update = await check()
-      if (update?.available) {
+      // Releases still rolling out to other installs are not offered yet
+      const release = await invoke<ReleaseInfo | null>('check_for_updates_now')
+      if (release?.ready) {
         setUpdateAvailable(true)
-        setUpdateVersion(update.version)
+        setUpdateVersion(release.version)
       }
     } catch (err) {
       console.error('Failed to check for updates:', err)
@@ -33,32 +36,22 @@ export function UpdateChecker() {
       setDownloading(true)
       setError(null)
       
-      const update = await check()
-      if (!update?.available) {
-        setError('No update available')
-        return
-      }
-
-      // Download and install with progress
-      await update.downloadAndInstall((event: any) => {
-        switch (event.event) {
-          case 'Started':
-            setDownloadProgress(0)
-            break
-          case 'Progress':
-            setDownloadProgress(event.data.chunkLength / event.data.contentLength! * 100)
-            break
-          case 'Finished':
-            setDownloadProgress(100)
-            break
+      const unlisten = await listen<UpdateProgress>('update-progress', (event) => {
+        const { downloaded, total } = event.payload
+        if (total) {
+          setDownloadProgress(downloaded / total * 100)
         }
       })
-
-      // Restart the app to apply the update
-      await relaunch()
+      try {
+        // Installs from the selected channel and restarts the app
+        setDownloadProgress(0)
+        await invoke('install_update')
+      } finally {
+        unlisten()
+      }
     } catch (err) {
       console.error('Failed to install update:', err)
-      setError(err instanceof Error ? err.message : 'Failed to install update')
+      setError(errorMessage(err))
       setDownloading(false)
     }
   }
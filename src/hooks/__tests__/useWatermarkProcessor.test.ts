@@ -179,6 +179,52 @@ describe('useWatermarkProcessor', () => {
     expect(result.current.error).toBe(error.message)
   })
 
+  it('uses the message of structured command errors', async () => {
+    const { invoke } = await import('@tauri-apps/api/core')
+    vi.mocked(invoke).mockRejectedValueOnce({
+      code: 'FFMPEG_MISSING',
+      message: 'FFmpeg binary not found: sidecar missing',
+      details: null,
+    })
+
+    const { result } = renderHook(() => useWatermarkProcessor())
+
+    await act(async () => {
+      await result.current.processBatch(sampleFiles, sampleConfig, '/output')
+    })
+
+    await waitFor(() => {
+      expect(result.current.processingState).toBe('error')
+    })
+
+    expect(result.current.error).toBe('FFmpeg binary not found: sidecar missing')
+  })
+
+  it('retries after the user approves a new output folder', async () => {
+    const { invoke } = await import('@tauri-apps/api/core')
+    vi.mocked(invoke).mockRejectedValueOnce({
+      code: 'CONFIRMATION_REQUIRED',
+      message: 'Confirm writing to /output',
+      details: null,
+      confirmation: { path: '/output', exists: true, freeBytes: null, existingFiles: 3 },
+    })
+    const confirm = vi.spyOn(window, 'confirm').mockReturnValue(true)
+
+    const { result } = renderHook(() => useWatermarkProcessor())
+
+    await act(async () => {
+      await result.current.processBatch(sampleFiles, sampleConfig, '/output')
+    })
+
+    expect(confirm).toHaveBeenCalled()
+    expect(invoke).toHaveBeenCalledWith('approve_output_location', { path: '/output' })
+    expect(vi.mocked(invoke).mock.calls.filter(([command]) => command === 'process_batch'))
+      .toHaveLength(2)
+    expect(result.current.error).toBeNull()
+
+    confirm.mockRestore()
+  })
+
   it('supports cancellation and ignores subsequent events', async () => {
     const { result } = renderHook(() => useWatermarkProcessor())
 
@@ -1,15 +1,22 @@
-import { useState } from 'react'
+import { useCallback, useEffect, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { open } from '@tauri-apps/plugin-dialog'
 
-import type { FileItem } from '@/types/watermark'
+import type { FileItem, FolderScan } from '@/types/watermark'
 
 const IMAGE_EXTENSIONS = ['png', 'jpg', 'jpeg', 'gif', 'bmp', 'webp']
 const VIDEO_EXTENSIONS = ['mp4', 'avi', 'mov', 'mkv', 'webm', 'flv']
+const AUDIO_EXTENSIONS = ['mp3', 'wav', 'm4a']
 
 const FILE_FILTERS = [
-  { name: 'All Media Files', extensions: [...IMAGE_EXTENSIONS, ...VIDEO_EXTENSIONS] },
+  {
+    name: 'All Media Files',
+    extensions: [...IMAGE_EXTENSIONS, ...VIDEO_EXTENSIONS, ...AUDIO_EXTENSIONS],
+  },
   { name: 'Images', extensions: IMAGE_EXTENSIONS },
   { name: 'Videos', extensions: VIDEO_EXTENSIONS },
+  { name: 'Audio', extensions: AUDIO_EXTENSIONS },
 ]
 
 const IMAGE_FILTER = [{ name: 'Images', extensions: IMAGE_EXTENSIONS }]
@@ -23,7 +30,11 @@ const toFileItem = (path: string): FileItem => {
   const segments = path.split(/[/\\]/)
   const name = segments[segments.length - 1] ?? path
   const extension = getExtension(name)
-  const type: FileItem['type'] = IMAGE_EXTENSIONS.includes(extension) ? 'image' : 'video'
+  const type: FileItem['type'] = IMAGE_EXTENSIONS.includes(extension)
+    ? 'image'
+    : AUDIO_EXTENSIONS.includes(extension)
+      ? 'audio'
+      : 'video'
 
   return { path, name, type }
 }
@@ -31,20 +42,57 @@ const toFileItem = (path: string): FileItem => {
 export function useFileSelection() {
   const [selectedFiles, setSelectedFiles] = useState<FileItem[]>([])
 
+  const addPaths = useCallback((paths: string[]) => {
+    setSelectedFiles((current) => {
+      const existingPaths = new Set(current.map((file) => file.path))
+      const newItems = paths
+        .filter((path) => !existingPaths.has(path))
+        .map(toFileItem)
+      return [...current, ...newItems]
+    })
+  }, [])
+
+  // Files opened through the OS (double-click, "Open With") join the session
+  useEffect(() => {
+    const takeOpenedFiles = async () => {
+      try {
+        addPaths(await invoke<string[]>('take_opened_files'))
+      } catch (err) {
+        console.error('Failed to take opened files:', err)
+      }
+    }
+
+    takeOpenedFiles()
+    const unlistenPromise = listen('files-opened', () => {
+      takeOpenedFiles()
+    })
+    return () => {
+      unlistenPromise.then(unlisten => unlisten())
+    }
+  }, [addPaths])
+
   const selectFiles = async () => {
     const result = await open({ multiple: true, directory: false, filters: FILE_FILTERS })
     if (!result) {
       return
     }
 
-    const files = Array.isArray(result) ? result : [result]
+    addPaths(Array.isArray(result) ? result : [result])
+  }
+
+  const selectFolder = async (): Promise<FolderScan | null> => {
+    const result = await open({ multiple: false, directory: true })
+    const folder = Array.isArray(result) ? result[0] : result
+    if (!folder) {
+      return null
+    }
+
+    const scan = await invoke<FolderScan>('scan_folder', { path: folder, recursive: true })
     setSelectedFiles((current) => {
       const existingPaths = new Set(current.map((file) => file.path))
-      const newItems = files
-        .filter((path) => !existingPaths.has(path))
-        .map(toFileItem)
-      return [...current, ...newItems]
+      return [...current, ...scan.files.filter((file) => !existingPaths.has(file.path))]
     })
+    return scan
   }
 
   const selectWatermarkImage = async () => {
@@ -64,5 +112,12 @@ export function useFileSelection() {
     setSelectedFiles([])
   }
 
-  return { selectedFiles, selectFiles, selectWatermarkImage, removeFile, clearFiles }
+  return {
+    selectedFiles,
+    selectFiles,
+    selectFolder,
+    selectWatermarkImage,
+    removeFile,
+    clearFiles,
+  }
 }
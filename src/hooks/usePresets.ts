@@ -1,16 +1,37 @@
 import { useState, useEffect, useCallback } from 'react'
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import type { PresetMetadata, WatermarkConfig } from '@/types/watermark'
+import { isAppError } from '@/utils/errors'
 
 interface UsePresetsReturn {
   presets: PresetMetadata[]
   isLoading: boolean
   error: string | null
   loadPresetConfig: (id: string) => Promise<WatermarkConfig | null>
+  /** Only user presets can be deleted or renamed; resolves to false on failure */
+  deletePreset: (id: string) => Promise<boolean>
+  renamePreset: (id: string, name: string) => Promise<boolean>
+  /** Makes a preset the one new sessions start from; null restores the bundled default */
+  setDefaultPreset: (id: string | null) => Promise<boolean>
+  /** Writes a shareable preset file, embedding its images by default */
+  exportPreset: (id: string, outputPath: string, embedAssets?: boolean) => Promise<boolean>
+  /** Adds a preset file as a user preset; resolves to its metadata, or null on failure */
+  importPreset: (path: string) => Promise<PresetMetadata | null>
   refreshPresets: () => Promise<void>
 }
 
-export function usePresets(): UsePresetsReturn {
+export interface PresetFilter {
+  category?: string | null
+  /** Presets must carry every tag */
+  tags?: string[]
+}
+
+export function usePresets(filter: PresetFilter = {}): UsePresetsReturn {
+  const category = filter.category ?? null
+  // Joined so a new array with the same tags does not refetch
+  const tagKey = (filter.tags ?? []).join('\u0000')
+
   const [presets, setPresets] = useState<PresetMetadata[]>([])
   const [isLoading, setIsLoading] = useState(true)
   const [error, setError] = useState<string | null>(null)
@@ -20,16 +41,16 @@ export function usePresets(): UsePresetsReturn {
     setError(null)
     
     try {
-      const loadedPresets = await invoke<PresetMetadata[]>('list_presets')
+      const tags = tagKey ? tagKey.split('\u0000') : null
+      const loadedPresets = await invoke<PresetMetadata[]>('list_presets', { category, tags })
       setPresets(loadedPresets)
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : 'Failed to load presets'
-      setError(errorMessage)
+      setError(isAppError(err) || err instanceof Error ? err.message : 'Failed to load presets')
       console.error('Failed to fetch presets:', err)
     } finally {
       setIsLoading(false)
     }
-  }, [])
+  }, [category, tagKey])
 
   const loadPresetConfig = useCallback(async (id: string): Promise<WatermarkConfig | null> => {
     try {
@@ -41,6 +62,64 @@ export function usePresets(): UsePresetsReturn {
     }
   }, [])
 
+  // The list refreshes through the presets-changed event
+  const deletePreset = useCallback(async (id: string): Promise<boolean> => {
+    try {
+      await invoke('delete_preset', { presetId: id })
+      return true
+    } catch (err) {
+      setError(isAppError(err) || err instanceof Error ? err.message : 'Failed to delete preset')
+      console.error(`Failed to delete preset ${id}:`, err)
+      return false
+    }
+  }, [])
+
+  const renamePreset = useCallback(async (id: string, name: string): Promise<boolean> => {
+    try {
+      await invoke('rename_preset', { presetId: id, name })
+      return true
+    } catch (err) {
+      setError(isAppError(err) || err instanceof Error ? err.message : 'Failed to rename preset')
+      console.error(`Failed to rename preset ${id}:`, err)
+      return false
+    }
+  }, [])
+
+  const setDefaultPreset = useCallback(async (id: string | null): Promise<boolean> => {
+    try {
+      await invoke('set_default_preset', { presetId: id })
+      return true
+    } catch (err) {
+      setError(isAppError(err) || err instanceof Error ? err.message : 'Failed to set default preset')
+      console.error(`Failed to set default preset ${id}:`, err)
+      return false
+    }
+  }, [])
+
+  const exportPreset = useCallback(
+    async (id: string, outputPath: string, embedAssets = true): Promise<boolean> => {
+      try {
+        await invoke('export_preset', { presetId: id, outputPath, embedAssets })
+        return true
+      } catch (err) {
+        setError(isAppError(err) || err instanceof Error ? err.message : 'Failed to export preset')
+        console.error(`Failed to export preset ${id}:`, err)
+        return false
+      }
+    },
+    []
+  )
+
+  const importPreset = useCallback(async (path: string): Promise<PresetMetadata | null> => {
+    try {
+      return await invoke<PresetMetadata>('import_preset', { path })
+    } catch (err) {
+      setError(isAppError(err) || err instanceof Error ? err.message : 'Failed to import preset')
+      console.error(`Failed to import preset ${path}:`, err)
+      return null
+    }
+  }, [])
+
   const refreshPresets = useCallback(async () => {
     await fetchPresets()
   }, [fetchPresets])
@@ -49,11 +128,26 @@ export function usePresets(): UsePresetsReturn {
     fetchPresets()
   }, [fetchPresets])
 
+  // Refresh when a preset is saved, renamed, deleted or made the default in any window
+  useEffect(() => {
+    const unlistenPromise = listen('presets-changed', () => {
+      fetchPresets()
+    })
+    return () => {
+      unlistenPromise.then(unlisten => unlisten())
+    }
+  }, [fetchPresets])
+
   return {
     presets,
     isLoading,
     error,
     loadPresetConfig,
+    deletePreset,
+    renamePreset,
+    setDefaultPreset,
+    exportPreset,
+    importPreset,
     refreshPresets,
   }
 }
@@ -8,10 +8,18 @@ import type {
   BatchResult,
   ProgressPayload,
   ProcessingState,
+  OutputLocationConfirmation,
 } from '@/types/watermark'
+import { errorMessage, isAppError } from '@/utils/errors'
 
 interface UseWatermarkProcessorReturn {
-  processBatch: (files: FileItem[], config: WatermarkConfig, outputDir: string) => Promise<void>
+  /** With a presetId, the preset's output settings fill in what config leaves unset */
+  processBatch: (
+    files: FileItem[],
+    config: WatermarkConfig,
+    outputDir: string,
+    presetId?: string | null,
+  ) => Promise<void>
   cancelProcessing: () => void
   processingState: ProcessingState
   isProcessing: boolean
@@ -27,6 +35,20 @@ const VALIDATION_MESSAGES = {
   missingImage: 'Select a watermark image before processing.',
 }
 
+/** Asks before the first batch writes to a folder */
+const confirmOutputLocation = (confirmation: OutputLocationConfirmation): boolean => {
+  const lines = [
+    `Save watermarked files to ${confirmation.path}?`,
+    confirmation.exists
+      ? `The folder already contains ${confirmation.existingFiles} file(s).`
+      : 'The folder will be created.',
+  ]
+  if (confirmation.freeBytes !== null) {
+    lines.push(`${(confirmation.freeBytes / 1024 ** 3).toFixed(1)} GB free.`)
+  }
+  return window.confirm(lines.join('\n'))
+}
+
 export function useWatermarkProcessor(): UseWatermarkProcessorReturn {
   const [processingState, setProcessingState] = useState<ProcessingState>('idle')
   const [progress, setProgress] = useState<Map<string, ProgressPayload>>(new Map())
@@ -129,7 +151,7 @@ export function useWatermarkProcessor(): UseWatermarkProcessorReturn {
   )
 
   const processBatch = useCallback<UseWatermarkProcessorReturn['processBatch']>(
-    async (files, config, outputDir) => {
+    async (files, config, outputDir, presetId) => {
       const validationError = validateInputs(files, config, outputDir)
 
       if (validationError) {
@@ -145,15 +167,25 @@ export function useWatermarkProcessor(): UseWatermarkProcessorReturn {
       setProgress(new Map())
       isCancelledRef.current = false
 
+      const request = { files, config, outputDir, presetId: presetId ?? null }
+
       try {
-        await invoke<BatchResult>('process_batch', {
-          files,
-          config,
-          outputDir,
-        })
+        try {
+          await invoke<BatchResult>('process_batch', request)
+        } catch (err) {
+          if (!isAppError(err) || !err.confirmation) {
+            throw err
+          }
+          if (!confirmOutputLocation(err.confirmation)) {
+            setProcessingState('idle')
+            return
+          }
+          await invoke('approve_output_location', { path: err.confirmation.path })
+          await invoke<BatchResult>('process_batch', request)
+        }
         console.info('Watermark batch processing started')
       } catch (err) {
-        const message = err instanceof Error ? err.message : String(err)
+        const message = errorMessage(err)
         console.error('Watermark batch processing failed', err)
         setError(message)
         setProcessingState('error')
@@ -1,44 +1,37 @@
 import { useEffect, useState } from 'react'
-import { LazyStore } from '@tauri-apps/plugin-store'
-import { resolveResource } from '@tauri-apps/api/path'
-import { readTextFile } from '@tauri-apps/plugin-fs'
+import { invoke } from '@tauri-apps/api/core'
 
-import { DEFAULT_WATERMARK_CONFIG, type WatermarkConfig } from '@/types/watermark'
+import {
+  DEFAULT_WATERMARK_CONFIG,
+  type LastConfig,
+  type PresetMetadata,
+  type WatermarkConfig,
+} from '@/types/watermark'
 
-const STORE_KEY = 'watermarkConfig'
-const store = new LazyStore('watermark-settings.json')
+function saveLastConfig(config: WatermarkConfig, presetId: string | null) {
+  invoke('save_last_config', { config, presetId }).catch((error) => {
+    console.error('Failed to save watermark configuration', error)
+  })
+}
 
 // Load order:
-// 1. User's saved settings (from store)
-// 2. Default preset (from bundled resources)
+// 1. User's saved settings (load_last_config, complete and migrated by the backend)
+// 2. Default preset (load_preset without an id, see set_default_preset)
 // 3. Hardcoded DEFAULT_WATERMARK_CONFIG (fallback)
-async function loadDefaultPreset(): Promise<Partial<WatermarkConfig> | null> {
+async function loadDefaultPreset(): Promise<{ id: string; config: WatermarkConfig } | null> {
   try {
-    const resourcePath = await resolveResource('resources/presets/default.json')
-    const presetJson = await readTextFile(resourcePath)
-    const preset = JSON.parse(presetJson)
-    return preset.config ?? null
+    const [config, presets] = await Promise.all([
+      invoke<WatermarkConfig>('load_preset'),
+      invoke<PresetMetadata[]>('list_presets'),
+    ])
+    const id = presets.find(preset => preset.isDefault)?.id ?? 'default'
+    return { id, config }
   } catch (error) {
     console.warn('Failed to load default preset, using hardcoded defaults', error)
     return null
   }
 }
 
-/**
- * Migration helper to handle existing saved configurations that don't have the new positioning fields.
- * Ensures backward compatibility by defaulting to preset mode for configurations without positionMode.
- */
-function migrateConfig(config: Partial<WatermarkConfig>): Partial<WatermarkConfig> {
-  // If positionMode is missing, assume preset mode for backward compatibility
-  if (!config.positionMode) {
-    return {
-      ...config,
-      positionMode: 'preset',
-    };
-  }
-  return config;
-}
-
 export function useWatermarkStore() {
   const [config, setConfig] = useState<WatermarkConfig>(DEFAULT_WATERMARK_CONFIG)
   const [isLoading, setIsLoading] = useState(true)
@@ -49,29 +42,21 @@ export function useWatermarkStore() {
 
     const loadConfig = async () => {
       try {
-        const saved = (await store.get<Partial<WatermarkConfig>>(STORE_KEY)) ?? null
-        const migrated = saved ? migrateConfig(saved) : null
-        
-        // Load active preset ID
-        const savedPresetId = await store.get<string>('activePresetId')
-        if (savedPresetId && isMounted) {
-          setActivePresetId(savedPresetId)
-        }
-        
-        if (migrated && isMounted) {
+        const saved = await invoke<LastConfig | null>('load_last_config')
+
+        if (saved && isMounted) {
           // User has saved settings, use them
-          setConfig({ ...DEFAULT_WATERMARK_CONFIG, ...migrated })
+          setConfig({ ...DEFAULT_WATERMARK_CONFIG, ...saved.config })
+          setActivePresetId(saved.presetId)
         } else if (isMounted) {
           // First launch, try to load default preset
           const defaultPreset = await loadDefaultPreset()
           if (defaultPreset) {
-            const presetWithPositionMode = { ...DEFAULT_WATERMARK_CONFIG, ...defaultPreset, positionMode: 'preset' as const }
+            const presetWithPositionMode = { ...DEFAULT_WATERMARK_CONFIG, ...defaultPreset.config, positionMode: 'preset' as const }
             setConfig(presetWithPositionMode)
-            // Save the preset to store so it persists
-            await store.set(STORE_KEY, presetWithPositionMode)
-            // Set default as active preset
-            setActivePresetId('default')
-            await store.set('activePresetId', 'default')
+            // Save the preset, as the active one, so it persists
+            setActivePresetId(defaultPreset.id)
+            saveLastConfig(presetWithPositionMode, defaultPreset.id)
           } else {
             // Fallback to hardcoded defaults
             setConfig(DEFAULT_WATERMARK_CONFIG)
@@ -117,12 +102,12 @@ export function useWatermarkStore() {
       )
       
       // If substantial changes, clear the active preset
+      const presetId = isSubstantialChange ? null : activePresetId
       if (isSubstantialChange && activePresetId) {
         setActivePresetId(null)
-        void store.delete('activePresetId')
       }
       
-      void store.set(STORE_KEY, updated)
+      saveLastConfig(updated, presetId)
       return updated
     })
   }
@@ -137,8 +122,7 @@ export function useWatermarkStore() {
     setConfig(mergedConfig)
     setActivePresetId(presetId)
     
-    void store.set(STORE_KEY, mergedConfig)
-    void store.set('activePresetId', presetId)
+    saveLastConfig(mergedConfig, presetId)
   }
 
   return { config, updateConfig, isLoading, activePresetId, applyPreset }
@@ -14,12 +14,14 @@ import { useFileSelection } from '@/hooks/useFileSelection'
 import { useWatermarkProcessor } from '@/hooks/useWatermarkProcessor'
 import { useWatermarkStore } from '@/hooks/useWatermarkStore'
 import type { FileItem } from '@/types/watermark'
+import { errorMessage } from '@/utils/errors'
 
 export function Home() {
   const { config, updateConfig, isLoading, activePresetId, applyPreset } = useWatermarkStore()
   const {
     selectedFiles,
     selectFiles,
+    selectFolder,
     selectWatermarkImage,
     removeFile,
     clearFiles,
@@ -81,11 +83,41 @@ export function Home() {
     }
   }, [processingState, result, error])
 
+  const handleAddFolder = useCallback(async () => {
+    try {
+      const scan = await selectFolder()
+      if (scan && scan.excluded.length > 0) {
+        const reasons = {
+          'unsupported-extension': 'unsupported format',
+          'zero-bytes': 'empty',
+          unreadable: 'unreadable',
+          corrupt: 'corrupt',
+        } as const
+        const names = scan.excluded
+          .slice(0, 5)
+          .map((file) => `${file.path.split(/[/\\]/).pop()} (${reasons[file.reason]})`)
+          .join(', ')
+        const more = scan.excluded.length > 5 ? ` and ${scan.excluded.length - 5} more` : ''
+        notifications.show({
+          title: `${scan.excluded.length} file${scan.excluded.length === 1 ? '' : 's'} skipped`,
+          message: `${names}${more}`,
+          color: 'yellow',
+        })
+      }
+    } catch (err) {
+      notifications.show({
+        title: 'Failed to add folder',
+        message: errorMessage(err),
+        color: 'red',
+      })
+    }
+  }, [selectFolder])
+
   const handleProcess = useCallback(
     (outputDir: string) => {
-      void processBatch(selectedFiles, config, outputDir)
+      void processBatch(selectedFiles, config, outputDir, activePresetId)
     },
-    [processBatch, selectedFiles, config],
+    [processBatch, selectedFiles, config, activePresetId],
   )
 
   const handleCloseResults = useCallback(() => {
@@ -144,6 +176,7 @@ export function Home() {
               <FileList
                 files={selectedFiles}
                 onAddFiles={selectFiles}
+                onAddFolder={handleAddFolder}
                 onRemoveFile={removeFile}
                 onSelectFile={setCurrentPreviewFile}
                 onClearFiles={clearFiles}
@@ -9,12 +9,21 @@ export type WatermarkPosition =
   | 'bottom-center'
   | 'bottom-right'
 
-export type WatermarkType = 'text' | 'image'
+export type WatermarkType = 'text' | 'image' | 'video'
 
 export interface WatermarkConfig {
   watermarkType: WatermarkType
   text: string
+  /**
+   * Derive per-file text from file names: the first rule whose pattern matches
+   * sets the text, with $1 or ${name} replaced by capture groups
+   */
+  textRules?: TextRule[]
   imagePath: string | null
+  /** Looped watermark clip, used when watermarkType is 'video' */
+  videoPath?: string | null
+  /** ID of a registered watermark source that generates each file's text or image */
+  watermarkSource?: string | null
   position: WatermarkPosition
   opacity: number
   textColor: string
@@ -35,15 +44,316 @@ export interface WatermarkConfig {
    * The watermark will be centered at the specified position
    */
   customPosition?: { x: number; y: number };
+  /**
+   * Video time window in seconds during which the watermark is shown.
+   * Either end may be omitted; ignored for still images.
+   */
+  startTime?: number | null;
+  endTime?: number | null;
+  /** Show the watermark for showSecs out of every everySecs */
+  intermittent?: { everySecs: number; showSecs: number } | null;
+  /** Seconds over which the watermark fades in/out on videos */
+  fadeInSecs?: number;
+  fadeOutSecs?: number;
+  /** Text watermark movement on videos, to resist cropping */
+  motion?: 'static' | 'ticker' | 'drift';
+  /** Motion speed in pixels per second */
+  motionSpeed?: number;
+  /** Shows a video watermark only during the intro or outro of each file */
+  schedule?: 'always' | 'intro' | 'outro';
+  /** Length of the intro or outro window in seconds */
+  scheduleSecs?: number;
+  /** Seconds between random watermark relocations on videos */
+  jitterIntervalSecs?: number | null;
+  /** How audio-only inputs are branded */
+  audioMode?: 'metadata' | 'cover-art' | 'waveform';
+  /** Owner tagged into audio metadata; defaults to the watermark text */
+  audioOwner?: string | null;
+  /** Locale of month and weekday names in {date:...} tokens, e.g. 'fr_FR' */
+  tokenLocale?: string | null;
+  /** Zero-based CPU cores FFmpeg is pinned to while encoding (not on macOS) */
+  cpuAffinity?: number[] | null;
+  /** Low priority and a thread limit for FFmpeg, shown as "background mode" */
+  backgroundMode?: BackgroundMode | null;
+  /** Seconds FFmpeg may spend on one file before the file fails with TIMEOUT */
+  fileTimeoutSecs?: number | null;
+  /** Video codec, quality and speed; FFmpeg defaults when omitted */
+  encoding?: EncodingOptions | null;
+  /** Copy EXIF/XMP/ICC/IPTC from JPEG inputs to JPEG outputs (default true) */
+  preserveMetadata?: boolean;
+  /** Remove EXIF, GPS and device metadata from outputs; overrides preserveMetadata */
+  stripMetadata?: boolean;
+  /** Keep all audio/subtitle tracks, metadata and chapters of videos (default true) */
+  preserveStreams?: boolean;
+  /** Outputs larger than their input: keep silently, report, or recompress and report */
+  sizeGrowth?: 'accept' | 'warn' | 'recompress';
+  /** Format and quality of still image outputs */
+  imageOutput?: ImageOutputOptions;
+  /** GPU video encoding: 'auto' falls back to the CPU, 'force' fails instead */
+  hardwareAcceleration?: 'auto' | 'off' | 'force';
+  /** 10-bit/HDR videos: keep depth and HDR metadata, or tone map to 8-bit SDR */
+  hdrMode?: 'preserve' | 'tone-map';
+  /**
+   * Writes videos as MP4 that play in browsers and on phones: 8-bit H.264
+   * High profile, AAC audio and +faststart; overrides the codec and hdrMode
+   */
+  webOptimized?: boolean;
+  /**
+   * Video audio: 'auto' copies codecs the output container holds and converts
+   * the rest (AAC for MP4/MOV, Opus for WebM); 'remove' drops it
+   */
+  audioHandling?: 'auto' | 'copy' | 'aac' | 'opus' | 'remove';
+  /**
+   * Per-file opacity between min (plain backgrounds) and max (busy ones),
+   * from the content under the watermark; replaces opacity
+   */
+  adaptiveOpacity?: { min: number; max: number } | null;
+  /** Scales outputs in the same pass, before the watermark is drawn */
+  resize?: OutputResize | null;
+  /**
+   * Writes one output per variant, named `<output>_<name>`, e.g. an
+   * Instagram crop next to a YouTube version
+   */
+  exportVariants?: ExportVariant[];
+}
+
+export interface ExportVariant {
+  name: string
+  /** Replaces resize; null keeps the original size */
+  resize?: OutputResize | null
+}
+
+/**
+ * 'fit' shrinks to fit within width/height and never enlarges; 'exact'
+ * scales to cover width x height and crops the overflow
+ */
+export interface OutputResize {
+  mode?: 'fit' | 'exact'
+  width?: number | null
+  height?: number | null
+  filter?: 'lanczos' | 'bicubic' | 'bilinear' | 'area' | 'neighbor'
+}
+
+export type EncoderPreset =
+  | 'ultrafast'
+  | 'superfast'
+  | 'veryfast'
+  | 'faster'
+  | 'fast'
+  | 'medium'
+  | 'slow'
+  | 'slower'
+  | 'veryslow'
+
+/** How FFmpeg shares the machine while a batch runs */
+export interface BackgroundMode {
+  /** Run FFmpeg below normal OS priority */
+  lowPriority: boolean
+  /** Threads FFmpeg may use; all cores when null */
+  threads: number | null
+}
+
+export interface EncodingOptions {
+  codec: 'h264' | 'h265' | 'vp9' | 'av1'
+  /** Constant quality, lower is better: 0-51 for H.264/H.265, 0-63 for VP9/AV1 */
+  crf?: number | null
+  /** Target bitrate in kbit/s; replaces crf when set */
+  bitrateKbps?: number | null
+  preset: EncoderPreset
+}
+
+export type OutputImageFormat = 'jpeg' | 'png' | 'webp'
+
+export interface ImageOutputOptions {
+  /** Converts every image to this format; null keeps each input's format */
+  format?: OutputImageFormat | null
+  /** 1-100, higher is better */
+  jpegQuality?: number
+  /** zlib level 0-9, higher is smaller */
+  pngCompression?: number
+  /** 0-100, higher is better */
+  webpQuality?: number
+}
+
+export interface TextRule {
+  /** Regular expression matched against the file name */
+  pattern: string
+  text: string
 }
 
 export interface FileItem {
   path: string
   name: string
-  type: 'image' | 'video'
+  type: 'image' | 'video' | 'audio'
   size?: number
 }
 
+export type PlanIssue =
+  | { kind: 'missing' }
+  | { kind: 'unsupported' }
+  | { kind: 'collision'; withIndex: number }
+  | { kind: 'overwrites-existing' }
+  | { kind: 'overwrites-input' }
+
+export interface PlannedFile {
+  inputPath: string
+  outputPath: string | null
+  issues: PlanIssue[]
+  estimatedBytes: number
+}
+
+/** Dry run of a batch returned by `plan_batch` */
+export interface BatchPlan {
+  files: PlannedFile[]
+  skipped: number
+  collisions: number
+  overwrites: number
+  estimatedBytes: number
+}
+
+export interface AudioStreamInfo {
+  index: number
+  codec: string | null
+  channels: number | null
+  sampleRate: number | null
+  language: string | null
+}
+
+/** Media details returned by `get_media_info`; rotation is clockwise degrees */
+export interface MediaInfo {
+  width: number | null
+  height: number | null
+  /** Size after rotation, which watermark positions are computed against */
+  displayWidth: number | null
+  displayHeight: number | null
+  duration: number | null
+  format: string | null
+  videoCodec: string | null
+  frameRate: number | null
+  rotation: number
+  /** FFmpeg pixel format of the video, e.g. yuv420p10le */
+  pixelFormat: string | null
+  bitDepth: number | null
+  /** PQ (HDR10) or HLG transfer */
+  hdr: boolean
+  audioStreams: AudioStreamInfo[]
+}
+
+/** FFmpeg invocation returned by `preview_ffmpeg_command` */
+export interface FfmpegCommandPreview {
+  program: string
+  args: string[]
+  commandLine: string
+}
+
+/** Payload of the `thumbnail-ready` event emitted by `prefetch_thumbnails` */
+export interface ThumbnailReady {
+  path: string
+  thumbnailPath: string | null
+  error: string | null
+}
+
+/** Small frames from `extract_video_filmstrip`, one from the middle of each equal part */
+export interface VideoFilmstrip {
+  duration: number
+  frames: { index: number; timestamp: number }[]
+  /** One image per frame, in order; empty when tiled */
+  framePaths: string[]
+  /** All frames left to right in one image; only with `tiled` */
+  sheetPath: string | null
+}
+
+/** Watermarked clip from the start of a video, rendered by `render_video_preview` */
+export interface PreviewClip {
+  videoPath: string
+  /** Seconds rendered; shorter than requested for short videos */
+  duration: number
+}
+
+/** Frames rendered by `render_comparison_preview` for a before/after slider */
+export interface ComparisonPreview {
+  timestamp: number
+  originalPath: string
+  watermarkedPath: string
+  /** Original on the left, watermarked on the right; only with `sideBySide` */
+  compositePath: string | null
+}
+
+/** Result of `nudge_watermark_position`; busyness is edge density from 0 to 1 */
+export interface PositionNudge {
+  config: WatermarkConfig
+  moved: boolean
+  originalBusyness: number
+  busyness: number
+}
+
+/** Error code reported by every backend command */
+export type ErrorCode =
+  | 'FFMPEG_MISSING'
+  | 'FFMPEG_FAILED'
+  | 'FFMPEG_STALLED'
+  | 'TIMEOUT'
+  | 'UNSUPPORTED_FORMAT'
+  | 'INVALID_CONFIG'
+  | 'INVALID_INPUT'
+  | 'NOT_FOUND'
+  | 'PERMISSION_DENIED'
+  | 'DISK_FULL'
+  | 'DISK_FULL_PREDICTED'
+  | 'CONFIRMATION_REQUIRED'
+  | 'IO'
+  | 'PROCESSING'
+  | 'INTERNAL'
+
+export interface AppError {
+  code: ErrorCode
+  message: string
+  details: string | null
+  /** Set with CONFIRMATION_REQUIRED */
+  confirmation?: OutputLocationConfirmation
+  /** Set with DISK_FULL_PREDICTED */
+  diskSpace?: DiskSpaceShortfall
+}
+
+/**
+ * Returned before a batch writes to an output folder the user has not
+ * approved yet. Approve it with approve_output_location and retry.
+ */
+export interface OutputLocationConfirmation {
+  path: string
+  exists: boolean
+  freeBytes: number | null
+  existingFiles: number
+}
+
+/** Returned when a batch is not expected to fit on its output volume */
+export interface DiskSpaceShortfall {
+  path: string
+  /** Estimated output size of the batch, safety margin included */
+  requiredBytes: number
+  freeBytes: number
+}
+
+export type ExclusionReason = 'unsupported-extension' | 'zero-bytes' | 'unreadable' | 'corrupt'
+
+export interface ExcludedFile {
+  path: string
+  reason: ExclusionReason
+  detail: string | null
+}
+
+/** Files of a batch that failed the preflight check */
+export interface PreflightReport {
+  checked: number
+  problems: ExcludedFile[]
+}
+
+/** Result of scanning a folder: usable media files and the files left out */
+export interface FolderScan {
+  files: FileItem[]
+  excluded: ExcludedFile[]
+}
+
 export const DEFAULT_WATERMARK_CONFIG: WatermarkConfig = {
   watermarkType: 'text',
   text: 'Watermark',
@@ -57,13 +367,65 @@ export const DEFAULT_WATERMARK_CONFIG: WatermarkConfig = {
   positionMode: 'preset',
 }
 
-export type ProcessingStatus = 'success' | 'failed' | 'skipped'
+export type ProcessingStatus = 'success' | 'failed' | 'skipped' | 'duplicate'
 
 export interface FileResult {
   inputPath: string
   outputPath: string | null
   status: ProcessingStatus
   error: string | null
+  /** Kind of failure, for failed files */
+  errorCode?: ErrorCode
+  duplicateOf?: string
+  /** Set when the output came out larger than the input */
+  sizeGrowth?: SizeGrowth
+  /** Expected output size, reported by simulated batches */
+  estimatedBytes?: number
+  /** Time spent watermarking the file; not set for reused duplicate outputs */
+  durationMs?: number
+  /** Every output written for export variants; outputPath is the first */
+  variantOutputs?: string[]
+}
+
+/** Payload of the watermark-file-log event: one line of FFmpeg output */
+export interface FileLogLine {
+  inputPath: string
+  line: string
+}
+
+/** A processed file from `get_processing_history`, `get_history` or `search_history` */
+export interface ProcessedFileRecord {
+  id: number
+  jobId: string
+  inputPath: string
+  outputPath: string | null
+  status: ProcessingStatus
+  error: string | null
+  /** Unix seconds when the file finished */
+  processedAt: number
+  durationMs: number | null
+  /**
+   * Config the file was processed with, its own caption included; null for
+   * files recorded by older versions
+   */
+  config: WatermarkConfig | null
+}
+
+export interface CacheSettings {
+  /** Total thumbnail size before the least recently used ones are deleted */
+  maxBytes: number
+  maxEntries: number
+  /** Default age in days at which cleanup removes thumbnails */
+  retentionDays: number
+  /** Directory the thumbnail folder is created in; system temp by default */
+  directory: string | null
+}
+
+export interface SizeGrowth {
+  inputBytes: number
+  /** Size of the output that was kept */
+  outputBytes: number
+  recompressed: boolean
 }
 
 export interface BatchResult {
@@ -71,6 +433,26 @@ export interface BatchResult {
   total: number
   successful: number
   failed: number
+  duplicates: number
+  /** Successful files whose output is larger than the input */
+  grown?: number
+  jobId?: string
+  /** Set when the batch was only simulated and nothing was written */
+  simulated?: boolean
+  /** Expected size of all outputs of a simulated batch */
+  estimatedBytes?: number
+}
+
+export interface ResultFilter {
+  status?: ProcessingStatus
+  query?: string
+}
+
+export interface BatchResultPage {
+  files: FileResult[]
+  offset: number
+  matching: number
+  total: number
 }
 
 export interface ProgressPayload {
@@ -90,8 +472,23 @@ export interface PresetMetadata {
   id: string
   name: string
   description: string
+  /** 'bundled' presets ship with the app; only 'user' presets can be deleted */
+  source: PresetSource
+  category: string | null
+  tags: string[]
+  /** The preset load_preset returns without an id, chosen with set_default_preset */
+  isDefault: boolean
+}
+
+/** The configuration restored on launch by load_last_config */
+export interface LastConfig {
+  config: WatermarkConfig
+  /** Preset the config was applied from, while it is unmodified */
+  presetId: string | null
 }
 
+export type PresetSource = 'bundled' | 'user'
+
 /**
  * Complete preset structure including the full watermark configuration.
  * This is loaded when a specific preset is selected.
@@ -99,5 +496,97 @@ export interface PresetMetadata {
 export interface WatermarkPreset {
   name: string
   description: string
+  /** ID of the preset this one inherits from; `config` is already resolved */
+  base?: string | null
+  /** Group the preset is listed under, e.g. 'photography' or 'real-estate' */
+  category?: string | null
+  tags?: string[]
   config: WatermarkConfig
+  output?: PresetOutput | null
+  /** Images embedded in an exported preset file */
+  assets?: EmbeddedAsset[]
+}
+
+export interface EmbeddedAsset {
+  field: 'image-path' | 'dark-image-path'
+  fileName: string
+  /** Base64-encoded file contents */
+  data: string
+}
+
+/**
+ * Output settings carried by a preset. process_batch applies each one the
+ * batch config leaves at its default.
+ */
+export interface PresetOutput {
+  format?: OutputImageFormat | null
+  /** JPEG and WebP quality, 1-100 */
+  quality?: number | null
+  /** Appended to each input's name in place of "_watermarked" */
+  suffix?: string | null
+  /** Folder inside the output directory */
+  subfolder?: string | null
+}
+
+/** Hardware encoders reported by get_hardware_encoders */
+export type HardwareEncoder = 'nvenc' | 'video-toolbox' | 'qsv' | 'amf' | 'vaapi'
+
+/** Which FFmpeg runs: bundled, found on PATH, or bundled with a PATH fallback */
+export type FfmpegSource = 'auto' | 'bundled' | 'system'
+
+/** Returned by get_ffmpeg_source */
+export interface FfmpegSourceInfo {
+  source: FfmpegSource
+  bundledAvailable: boolean
+  systemPath: string | null
+  systemVersion: string | null
+  /** Why the system FFmpeg cannot be used, when it cannot */
+  systemError: string | null
+}
+
+/** Returned by self_test */
+export interface SelfTestReport {
+  ffmpegPath: string | null
+  ffmpegVersion: string | null
+  binaryFound: boolean
+  drawtextAvailable: boolean
+  fontsOk: boolean
+  sampleRendered: boolean
+  problems: string[]
+}
+
+/** Returned by download_ffmpeg */
+export interface DownloadedFfmpeg {
+  version: string
+  ffmpegPath: string
+  ffprobePath: string
+}
+
+/** Payload of the ffmpeg-download-progress event */
+export interface FfmpegDownloadProgress {
+  binary: 'ffmpeg' | 'ffprobe'
+  downloaded: number
+  total: number | null
+}
+
+/** Release channel used by check_for_updates_now and install_update */
+export type UpdateChannel = 'stable' | 'beta'
+
+/** A newer release, as returned by check_for_updates_now */
+export interface ReleaseInfo {
+  version: string
+  currentVersion: string
+  channel: UpdateChannel
+  /** Publish date, RFC 3339 */
+  date: string | null
+  notes: string | null
+  /** False while the staged rollout has not reached this install */
+  ready: boolean
+  readyAt: string | null
+}
+
+/** Payload of update-progress events */
+export interface UpdateProgress {
+  downloaded: number
+  total: number | null
 }
@@ -0,0 +1,19 @@
+/**
+ * Helpers for errors returned by Tauri commands, which reject with an
+ * `AppError` object rather than a string.
+ */
+import type { AppError } from '@/types/watermark'
+
+export const isAppError = (value: unknown): value is AppError =>
+  typeof value === 'object' &&
+  value !== null &&
+  typeof (value as AppError).code === 'string' &&
+  typeof (value as AppError).message === 'string'
+
+/** User-facing message for anything thrown by `invoke` or plain JS code */
+export const errorMessage = (err: unknown): string => {
+  if (isAppError(err)) {
+    return err.message
+  }
+  return err instanceof Error ? err.message : String(err)
+}